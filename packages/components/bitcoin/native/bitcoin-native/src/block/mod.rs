@@ -0,0 +1,278 @@
+use napi::bindgen_prelude::Buffer;
+use napi::{Error, Result};
+use napi_derive::napi;
+use serde_json::{json, Value};
+
+use crate::merkle::merkle_root;
+use crate::transaction::{dsha256, hash_to_key, key_to_hash, Reader, Transaction};
+use crate::utils::{txid_to_hex, TxKey};
+
+pub const HEADER_LEN: usize = 80;
+
+/// Smallest serialized transaction: one empty-script input and one
+/// empty-script output.
+const MIN_TRANSACTION_LEN: usize = 60;
+
+/// BIP141 commitment output prefix: `OP_RETURN`, a 36-byte push and the
+/// `aa21a9ed` header.
+const WITNESS_COMMITMENT_PREFIX: [u8; 6] = [0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+pub struct BlockHeader {
+  pub version: i32,
+  /// Display order, like every hash below.
+  pub prev_block: TxKey,
+  pub merkle_root: TxKey,
+  pub time: u32,
+  pub bits: u32,
+  pub nonce: u32,
+  pub hash: TxKey,
+}
+
+impl BlockHeader {
+  pub fn read(reader: &mut Reader) -> Result<Self> {
+    let bytes = reader.take(HEADER_LEN)?;
+    let mut header = Reader::new(bytes);
+    Ok(Self {
+      version: header.u32()? as i32,
+      prev_block: hash_to_key(header.hash()?),
+      merkle_root: hash_to_key(header.hash()?),
+      time: header.u32()?,
+      bits: header.u32()?,
+      nonce: header.u32()?,
+      hash: hash_to_key(dsha256(bytes)),
+    })
+  }
+
+  /// Header fields named as in `getblockheader`.
+  pub fn to_value(&self) -> Value {
+    json!({
+      "hash": txid_to_hex(self.hash),
+      "version": self.version,
+      "versionHex": format!("{:08x}", self.version),
+      "previousblockhash": txid_to_hex(self.prev_block),
+      "merkleroot": txid_to_hex(self.merkle_root),
+      "time": self.time,
+      "bits": format!("{:08x}", self.bits),
+      "nonce": self.nonce,
+    })
+  }
+}
+
+/// Block decoded from its wire serialization.
+pub struct Block {
+  pub header: BlockHeader,
+  pub transactions: Vec<Transaction>,
+  /// Serialized size including witness data.
+  pub size: u32,
+}
+
+impl Block {
+  /// Decodes exactly one block; trailing bytes are an error.
+  pub fn parse(bytes: &[u8]) -> Result<Self> {
+    let mut reader = Reader::new(bytes);
+    let header = BlockHeader::read(&mut reader)?;
+
+    let count = reader.count(MIN_TRANSACTION_LEN)?;
+    if count == 0 {
+      return Err(Error::from_reason("Block has no transactions"));
+    }
+    let transactions = (0..count).map(|_| Transaction::read(&mut reader)).collect::<Result<Vec<_>>>()?;
+    if reader.remaining() > 0 {
+      return Err(Error::from_reason("Trailing bytes after block"));
+    }
+    Ok(Self { header, transactions, size: bytes.len() as u32 })
+  }
+
+  /// Serialized size without witness data.
+  pub fn stripped_size(&self) -> u32 {
+    let witness_bytes: u32 = self.transactions.iter().map(|tx| tx.size - tx.stripped_size).sum();
+    self.size - witness_bytes
+  }
+
+  pub fn weight(&self) -> u32 {
+    self.stripped_size() * 3 + self.size
+  }
+
+  pub fn merkle_root_valid(&self) -> bool {
+    let leaves = self.transactions.iter().map(|tx| key_to_hash(tx.txid)).collect();
+    hash_to_key(merkle_root(leaves)) == self.header.merkle_root
+  }
+
+  /// Commitment of the highest-index coinbase output carrying one, as BIP141
+  /// specifies.
+  pub fn witness_commitment(&self) -> Option<[u8; 32]> {
+    self.transactions[0].outputs.iter().rev().find_map(|output| {
+      let script = &output.script_pubkey;
+      (script.len() >= 38 && script.starts_with(&WITNESS_COMMITMENT_PREFIX)).then(|| script[6..38].try_into().unwrap())
+    })
+  }
+
+  /// Without a commitment no transaction may carry witness data; with one,
+  /// the coinbase witness must be a single 32-byte reserved value and the
+  /// commitment must hash the wtxid merkle root with it.
+  pub fn witness_commitment_valid(&self) -> bool {
+    let Some(commitment) = self.witness_commitment() else {
+      return self.transactions.iter().all(|tx| tx.wtxid == tx.txid);
+    };
+    let Some([reserved]) = self.transactions[0].inputs.first().map(|input| &*input.witness) else {
+      return false;
+    };
+    if reserved.len() != 32 {
+      return false;
+    }
+    let leaves =
+      std::iter::once([0; 32]).chain(self.transactions[1..].iter().map(|tx| key_to_hash(tx.wtxid))).collect();
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(&merkle_root(leaves));
+    preimage[32..].copy_from_slice(reserved);
+    dsha256(&preimage) == commitment
+  }
+}
+
+/// Decodes a serialized block in one pass: header fields named as in
+/// `getblock`, sizes and weight, `nTx`, `txids` and `wtxids` in block order,
+/// the coinbase `witnessCommitment` (or `null`) and whether the merkle root
+/// and the witness commitment check out. Throws on malformed input.
+#[napi(js_name = "bitcoinParseBlock")]
+pub fn bitcoin_parse_block(raw: Buffer) -> Result<Value> {
+  parse_block_bytes(&raw)
+}
+
+/// True when `raw` is a well-formed block whose merkle root and witness
+/// commitment both check out; malformed input returns false.
+#[napi(js_name = "bitcoinVerifyBlockBytes")]
+pub fn bitcoin_verify_block_bytes(raw: Buffer) -> bool {
+  verify_block_bytes(&raw)
+}
+
+pub fn parse_block_bytes(bytes: &[u8]) -> Result<Value> {
+  let block = Block::parse(bytes)?;
+  let hex_keys =
+    |key: fn(&Transaction) -> TxKey| block.transactions.iter().map(|tx| txid_to_hex(key(tx))).collect::<Vec<_>>();
+  let mut value = block.header.to_value();
+  for (key, field) in [
+    ("size", json!(block.size)),
+    ("strippedsize", json!(block.stripped_size())),
+    ("weight", json!(block.weight())),
+    ("nTx", json!(block.transactions.len())),
+    ("txids", json!(hex_keys(|tx| tx.txid))),
+    ("wtxids", json!(hex_keys(|tx| tx.wtxid))),
+    ("witnessCommitment", json!(block.witness_commitment().map(hex::encode))),
+    ("merkleRootValid", json!(block.merkle_root_valid())),
+    ("witnessCommitmentValid", json!(block.witness_commitment_valid())),
+  ] {
+    value[key] = field;
+  }
+  Ok(value)
+}
+
+pub fn verify_block_bytes(bytes: &[u8]) -> bool {
+  Block::parse(bytes).is_ok_and(|block| block.merkle_root_valid() && block.witness_commitment_valid())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const GENESIS_BLOCK: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+  /// Segwit coinbase paying `commitment` in its last output.
+  fn coinbase(commitment: [u8; 32]) -> Vec<u8> {
+    let mut out = hex::decode(
+      "020000000001010000000000000000000000000000000000000000000000000000000000000000ffffffff020101ffffffff02",
+    )
+    .unwrap();
+    out.extend(5_000_000_000u64.to_le_bytes());
+    out.extend(hex::decode(format!("160014{}", "33".repeat(20))).unwrap());
+    out.extend(0u64.to_le_bytes());
+    out.push(38);
+    out.extend(WITNESS_COMMITMENT_PREFIX);
+    out.extend(commitment);
+    out.extend([1, 32]);
+    out.extend([0; 32]);
+    out.extend(0u32.to_le_bytes());
+    out
+  }
+
+  /// One-input P2WPKH spend in BIP144 form.
+  fn spend() -> Vec<u8> {
+    let mut out = hex::decode("0200000000010111").unwrap();
+    out.extend([0x11; 31]);
+    out.extend(hex::decode("0000000000fdffffff01").unwrap());
+    out.extend(50_000u64.to_le_bytes());
+    out.extend(hex::decode(format!("160014{}", "22".repeat(20))).unwrap());
+    out.extend([1, 3, 0xaa, 0xbb, 0xcc]);
+    out.extend(0u32.to_le_bytes());
+    out
+  }
+
+  /// Block of a committed coinbase and `spend()`, with a correct merkle root.
+  fn segwit_block() -> Vec<u8> {
+    let spend = Transaction::parse(&spend()).unwrap();
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(&merkle_root(vec![[0; 32], key_to_hash(spend.wtxid)]));
+    let coinbase_bytes = coinbase(dsha256(&preimage));
+    let coinbase = Transaction::parse(&coinbase_bytes).unwrap();
+
+    let mut out = 0x2000_0000u32.to_le_bytes().to_vec();
+    out.extend([0x44; 32]);
+    out.extend(merkle_root(vec![key_to_hash(coinbase.txid), key_to_hash(spend.txid)]));
+    out.extend(1_700_000_000u32.to_le_bytes());
+    out.extend(0x1703_4e37u32.to_le_bytes());
+    out.extend(7u32.to_le_bytes());
+    out.push(2);
+    out.extend(coinbase_bytes);
+    out.extend(self::spend());
+    out
+  }
+
+  #[test]
+  fn parses_genesis_block() {
+    let block = parse_block_bytes(&hex::decode(GENESIS_BLOCK).unwrap()).unwrap();
+    assert_eq!(block["hash"], json!("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"));
+    assert_eq!(block["merkleroot"], json!("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"));
+    assert_eq!(block["previousblockhash"], json!("0".repeat(64)));
+    assert_eq!((block["time"].clone(), block["nonce"].clone()), (json!(1_231_006_505), json!(2_083_236_893)));
+    assert_eq!((block["bits"].clone(), block["versionHex"].clone()), (json!("1d00ffff"), json!("00000001")));
+    assert_eq!((block["size"].clone(), block["strippedsize"].clone()), (json!(285), json!(285)));
+    assert_eq!((block["weight"].clone(), block["nTx"].clone()), (json!(1140), json!(1)));
+    assert_eq!(block["txids"], block["wtxids"]);
+    assert_eq!(block["witnessCommitment"], Value::Null);
+    assert_eq!(block["merkleRootValid"], json!(true));
+    assert_eq!(block["witnessCommitmentValid"], json!(true));
+  }
+
+  #[test]
+  fn verifies_merkle_root_and_witness_commitment() {
+    let bytes = segwit_block();
+    let block = Block::parse(&bytes).unwrap();
+    assert!(block.witness_commitment().is_some());
+    assert!(block.merkle_root_valid() && block.witness_commitment_valid());
+    assert!(block.stripped_size() < block.size);
+    assert!(verify_block_bytes(&bytes));
+
+    // Merkle root byte in the header.
+    let mut bad_root = bytes.clone();
+    bad_root[36] ^= 1;
+    assert!(!Block::parse(&bad_root).unwrap().merkle_root_valid());
+    assert!(!verify_block_bytes(&bad_root));
+
+    // Witness byte of the spend: txids and the merkle root stay intact.
+    let mut bad_witness = bytes.clone();
+    let last_witness_byte = bad_witness.len() - 5;
+    bad_witness[last_witness_byte] ^= 1;
+    let block = Block::parse(&bad_witness).unwrap();
+    assert!(block.merkle_root_valid() && !block.witness_commitment_valid());
+    assert!(!verify_block_bytes(&bad_witness));
+  }
+
+  #[test]
+  fn rejects_malformed_blocks() {
+    let bytes = hex::decode(GENESIS_BLOCK).unwrap();
+    assert!(parse_block_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(parse_block_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+    assert!(parse_block_bytes(&bytes[..HEADER_LEN]).is_err());
+    assert!(parse_block_bytes(&[&bytes[..HEADER_LEN], &[0]].concat()).is_err());
+    assert!(!verify_block_bytes(&bytes[..HEADER_LEN - 1]));
+  }
+}
@@ -1,6 +1,14 @@
+mod block;
 mod mempool;
 mod merkle;
+mod transaction;
 mod utils;
 
+pub use block::{bitcoin_parse_block, bitcoin_verify_block_bytes};
 pub use mempool::NativeMempoolState;
-pub use merkle::{bitcoin_compute_merkle_root, bitcoin_verify_merkle_root, bitcoin_verify_witness_commitment};
+pub use merkle::{
+  bitcoin_build_merkle_block, bitcoin_compute_merkle_root, bitcoin_get_merkle_proof, bitcoin_parse_merkle_block,
+  bitcoin_verify_merkle_block, bitcoin_verify_merkle_proof, bitcoin_verify_merkle_root,
+  bitcoin_verify_witness_commitment,
+};
+pub use transaction::bitcoin_parse_transaction;
@@ -0,0 +1,50 @@
+use serde_json::{json, Value};
+
+/// Tombstone share of all handles above which mutations compact automatically.
+pub const DEFAULT_TOMBSTONE_RATIO: f64 = 0.5;
+/// Below this many tombstones compaction is not worth a full reindex.
+pub const MIN_TOMBSTONES: usize = 1_024;
+
+/// When mutations that remove entries should compact the handle table.
+pub struct CompactionPolicy {
+  /// `None` disables automatic compaction; `compact()` still works.
+  pub tombstone_ratio: Option<f64>,
+  pub min_tombstones: usize,
+}
+
+impl Default for CompactionPolicy {
+  fn default() -> Self {
+    Self { tombstone_ratio: Some(DEFAULT_TOMBSTONE_RATIO), min_tombstones: MIN_TOMBSTONES }
+  }
+}
+
+impl CompactionPolicy {
+  pub fn should_compact(&self, tombstones: usize, handles: usize) -> bool {
+    let Some(ratio) = self.tombstone_ratio else {
+      return false;
+    };
+    tombstones >= self.min_tombstones && handles > 0 && tombstones as f64 / handles as f64 >= ratio
+  }
+}
+
+/// Outcome of one compaction. Byte figures are container capacities of the
+/// handle-keyed tables, so they show what the allocator actually got back.
+pub struct CompactionStats {
+  pub handles_before: usize,
+  pub handles_after: usize,
+  pub bytes_before: usize,
+  pub bytes_after: usize,
+}
+
+impl CompactionStats {
+  pub fn to_value(&self) -> Value {
+    json!({
+      "handlesBefore": self.handles_before,
+      "handlesAfter": self.handles_after,
+      "tombstonesRemoved": self.handles_before - self.handles_after,
+      "bytesBefore": self.bytes_before,
+      "bytesAfter": self.bytes_after,
+      "reclaimedBytes": self.bytes_before.saturating_sub(self.bytes_after),
+    })
+  }
+}
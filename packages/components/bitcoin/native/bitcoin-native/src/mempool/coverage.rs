@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use super::types::ProviderId;
+use crate::utils::memory::{map_bytes, vec_bytes};
+
+/// When each provider first reported a txid, in store clock milliseconds.
+///
+/// Unlike `provider_tx`, which assigns every txid to the first provider that
+/// listed it, this keeps one `(provider, first seen)` pair per reporting
+/// provider. Entries live as long as the handle; a provider that stops
+/// listing a txid keeps its first-seen time.
+#[derive(Default)]
+pub struct ProviderCoverage {
+  seen: HashMap<u32, Vec<(ProviderId, u64)>>,
+}
+
+/// Per-provider counts over every txid some provider reported. Vectors are
+/// indexed by `ProviderId`.
+pub struct CoverageStats {
+  pub total: u64,
+  pub seen: Vec<u64>,
+  /// Txids reported by this provider only.
+  pub unique: Vec<u64>,
+  /// `overlap[a][b]`: txids reported by both `a` and `b`; the diagonal equals `seen`.
+  pub overlap: Vec<Vec<u64>>,
+}
+
+/// Median first-seen differences in milliseconds, indexed by `ProviderId`;
+/// `None` where no txid was seen by both sides.
+pub struct PropagationStats {
+  /// `delays[a][b]`: median of `firstSeen(b) - firstSeen(a)` over txids both
+  /// reported; positive when `b` tends to see transactions after `a`.
+  pub delays: Vec<Vec<Option<f64>>>,
+  /// Median delay of a provider behind the earliest provider of each txid.
+  pub lag: Vec<Option<f64>>,
+}
+
+impl ProviderCoverage {
+  pub fn clear(&mut self) {
+    self.seen.clear();
+  }
+
+  pub fn shrink_to_fit(&mut self) {
+    self.seen.shrink_to_fit();
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    map_bytes(&self.seen) + self.seen.values().map(vec_bytes).sum::<usize>()
+  }
+
+  /// Records that `provider` reported `handle` at `at_ms`, keeping the
+  /// earliest time per provider.
+  pub fn mark(&mut self, handle: u32, provider: ProviderId, at_ms: u64) {
+    let providers = self.seen.entry(handle).or_default();
+    match providers.iter_mut().find(|(candidate, _)| *candidate == provider) {
+      Some((_, first_seen)) => *first_seen = (*first_seen).min(at_ms),
+      None => providers.push((provider, at_ms)),
+    }
+  }
+
+  pub fn first_seen(&self, handle: u32) -> &[(ProviderId, u64)] {
+    self.seen.get(&handle).map(Vec::as_slice).unwrap_or(&[])
+  }
+
+  pub fn remove(&mut self, handle: u32) -> Option<Vec<(ProviderId, u64)>> {
+    self.seen.remove(&handle)
+  }
+
+  pub fn insert(&mut self, handle: u32, providers: Vec<(ProviderId, u64)>) {
+    self.seen.insert(handle, providers);
+  }
+
+  pub fn drain(&mut self) -> impl Iterator<Item = (u32, Vec<(ProviderId, u64)>)> + '_ {
+    self.seen.drain()
+  }
+
+  pub fn remap(&mut self, live: impl Fn(u32) -> Option<u32>) {
+    self.seen = std::mem::take(&mut self.seen)
+      .into_iter()
+      .filter_map(|(handle, providers)| live(handle).map(|handle| (handle, providers)))
+      .collect();
+  }
+
+  /// Handles reported by `provider` and no other provider.
+  pub fn unique_to(&self, provider: ProviderId) -> impl Iterator<Item = u32> + '_ {
+    self
+      .seen
+      .iter()
+      .filter(move |(_, seen)| matches!(seen.as_slice(), [(only, _)] if *only == provider))
+      .map(|(h, _)| *h)
+  }
+
+  /// Complexity: O(N * P^2) for N txids reported by up to P providers each.
+  pub fn stats(&self, providers: usize) -> CoverageStats {
+    let mut stats = CoverageStats {
+      total: self.seen.len() as u64,
+      seen: vec![0; providers],
+      unique: vec![0; providers],
+      overlap: vec![vec![0; providers]; providers],
+    };
+    for seen in self.seen.values() {
+      for (a, _) in seen {
+        let a = *a as usize;
+        if a >= providers {
+          continue;
+        }
+        stats.seen[a] += 1;
+        if seen.len() == 1 {
+          stats.unique[a] += 1;
+        }
+        for (b, _) in seen {
+          if let Some(count) = stats.overlap[a].get_mut(*b as usize) {
+            *count += 1;
+          }
+        }
+      }
+    }
+    stats
+  }
+
+  /// Complexity: O(N * P^2) time and memory for the collected differences.
+  pub fn propagation(&self, providers: usize) -> PropagationStats {
+    let mut pairs: Vec<Vec<Vec<i64>>> = vec![vec![Vec::new(); providers]; providers];
+    let mut behind: Vec<Vec<i64>> = vec![Vec::new(); providers];
+    for seen in self.seen.values() {
+      let earliest = seen.iter().map(|(_, at)| *at).min().unwrap_or(0);
+      for (a, at_a) in seen {
+        let Some(row) = pairs.get_mut(*a as usize) else {
+          continue;
+        };
+        behind[*a as usize].push(*at_a as i64 - earliest as i64);
+        for (b, at_b) in seen {
+          if a != b {
+            if let Some(delays) = row.get_mut(*b as usize) {
+              delays.push(*at_b as i64 - *at_a as i64);
+            }
+          }
+        }
+      }
+    }
+    PropagationStats {
+      delays: pairs.into_iter().map(|row| row.into_iter().map(median).collect()).collect(),
+      lag: behind.into_iter().map(median).collect(),
+    }
+  }
+}
+
+fn median(mut values: Vec<i64>) -> Option<f64> {
+  if values.is_empty() {
+    return None;
+  }
+  values.sort_unstable();
+  let mid = values.len() / 2;
+  Some(if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) as f64 / 2.0 } else { values[mid] as f64 })
+}
@@ -0,0 +1,102 @@
+use std::collections::VecDeque;
+
+/// Compactions whose dropped handles are kept for translating older cursors.
+const RETAINED_COMPACTIONS: usize = 4;
+
+/// Upper bound on `iterate` page sizes.
+pub const MAX_PAGE_SIZE: usize = 10_000;
+
+/// Collections `iterate` pages through.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PageKind {
+  TxIds,
+  Metadata,
+  LoadedTransactions,
+}
+
+impl PageKind {
+  pub fn parse(name: &str) -> Option<Self> {
+    match name {
+      "txIds" => Some(Self::TxIds),
+      "metadata" => Some(Self::Metadata),
+      "loadedTransactions" => Some(Self::LoadedTransactions),
+      _ => None,
+    }
+  }
+}
+
+/// Handles an `iterate` page may visit, so sparse kinds (few loaded
+/// transactions among many handles) still return promptly.
+pub fn scan_budget(page_size: usize) -> usize {
+  page_size.saturating_mul(4).max(1024)
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum CursorError {
+  Invalid,
+  /// Issued before a full replace, or before more compactions than are kept.
+  Stale,
+}
+
+/// Issues and resolves `iterate` cursors.
+///
+/// A cursor is `"<generation>.<compactions>.<handle>"`: the next handle to
+/// visit and the handle numbering it refers to. Full replaces
+/// (`applySnapshot`, imports, `clear`) start a new generation, which
+/// invalidates older cursors. Compactions keep handle order, so a cursor from
+/// one of the last `RETAINED_COMPACTIONS` compactions is translated by
+/// subtracting the dropped tombstones below its handle.
+#[derive(Default)]
+pub struct CursorEpochs {
+  generation: u32,
+  compactions: u32,
+  /// Sorted handles dropped by each retained compaction, oldest first.
+  dropped: VecDeque<Box<[u32]>>,
+}
+
+impl CursorEpochs {
+  pub fn replaced(&mut self) {
+    self.generation = self.generation.wrapping_add(1);
+    self.compactions = 0;
+    self.dropped.clear();
+  }
+
+  /// Records a compaction that dropped `dropped` (ascending old handles).
+  pub fn compacted(&mut self, dropped: Vec<u32>) {
+    self.compactions = self.compactions.wrapping_add(1);
+    self.dropped.push_back(dropped.into_boxed_slice());
+    if self.dropped.len() > RETAINED_COMPACTIONS {
+      self.dropped.pop_front();
+    }
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    self.dropped.capacity() * std::mem::size_of::<Box<[u32]>>()
+      + self.dropped.iter().map(|handles| handles.len() * std::mem::size_of::<u32>()).sum::<usize>()
+  }
+
+  pub fn cursor(&self, handle: u32) -> String {
+    format!("{}.{}.{}", self.generation, self.compactions, handle)
+  }
+
+  /// Current handle a cursor points at.
+  pub fn resolve(&self, cursor: &str) -> Result<u32, CursorError> {
+    let mut parts = cursor.split('.').map(str::parse::<u32>);
+    let (Some(Ok(generation)), Some(Ok(compactions)), Some(Ok(mut handle)), None) =
+      (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+      return Err(CursorError::Invalid);
+    };
+    if generation != self.generation {
+      return Err(CursorError::Stale);
+    }
+    let behind = self.compactions.checked_sub(compactions).ok_or(CursorError::Invalid)? as usize;
+    if behind > self.dropped.len() {
+      return Err(CursorError::Stale);
+    }
+    for dropped in self.dropped.iter().skip(self.dropped.len() - behind) {
+      handle -= dropped.partition_point(|dropped| *dropped < handle) as u32;
+    }
+    Ok(handle)
+  }
+}
@@ -0,0 +1,55 @@
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+use crate::utils::{txid_to_hex, TxKey};
+
+/// Fee of an entry whose provider metadata changed, in satoshis.
+pub struct MetadataChange {
+  pub txid: TxKey,
+  pub old_fee: u64,
+  pub new_fee: u64,
+}
+
+/// Txids a single mutation added, removed, re-described or loaded.
+///
+/// Collected by the store's insert/drop helpers while the mutation runs, so
+/// the TS aggregate can build its domain events without diffing txid sets in
+/// JS. Order follows the mutation's own processing order.
+#[derive(Default)]
+pub struct MutationDiff {
+  pub added: Vec<TxKey>,
+  pub removed: Vec<TxKey>,
+  pub metadata_changed: Vec<MetadataChange>,
+  pub loaded: Vec<TxKey>,
+}
+
+impl MutationDiff {
+  /// Drops txids the mutation both added and removed, such as new entries
+  /// evicted by `setLimits` bounds before it returned: the caller never saw
+  /// them and the store no longer holds them.
+  pub fn net_out(&mut self) {
+    let removed: HashSet<TxKey> = self.removed.iter().copied().collect();
+    let both: HashSet<TxKey> = self.added.iter().copied().filter(|key| removed.contains(key)).collect();
+    if both.is_empty() {
+      return;
+    }
+    self.added.retain(|key| !both.contains(key));
+    self.removed.retain(|key| !both.contains(key));
+    self.metadata_changed.retain(|change| !both.contains(&change.txid));
+    self.loaded.retain(|key| !both.contains(key));
+  }
+
+  pub fn to_value(&self) -> Value {
+    let hex = |keys: &[TxKey]| keys.iter().map(|key| txid_to_hex(*key)).collect::<Vec<_>>();
+    json!({
+      "added": hex(&self.added),
+      "removed": hex(&self.removed),
+      "metadataChanged": self
+        .metadata_changed
+        .iter()
+        .map(|change| json!({ "txid": txid_to_hex(change.txid), "oldFee": change.old_fee, "newFee": change.new_fee }))
+        .collect::<Vec<_>>(),
+      "loaded": hex(&self.loaded),
+    })
+  }
+}
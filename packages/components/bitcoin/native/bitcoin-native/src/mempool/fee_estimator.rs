@@ -0,0 +1,160 @@
+use std::collections::VecDeque;
+
+use crate::utils::memory::deque_bytes;
+
+/// Target block interval used to convert a confirmation target into a wait.
+pub const BLOCK_INTERVAL_MS: u64 = 600_000;
+/// Rolling window of confirmation samples kept per store.
+pub const MAX_CONFIRMATION_SAMPLES: usize = 50_000;
+/// Minimum relay fee rate; estimates never go below it.
+pub const MIN_FEE_RATE: f64 = 1.0;
+
+/// Geometric spacing of historical fee-rate buckets (same idea as Bitcoin
+/// Core's `FEE_SPACING`, coarser because the window is small).
+const BUCKET_SPACING: f64 = 1.25;
+/// A bucket range is judged only after it accumulated this many samples.
+const MIN_BUCKET_SAMPLES: usize = 20;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EstimateMode {
+  Economical,
+  Conservative,
+}
+
+impl EstimateMode {
+  pub fn parse(mode: Option<&str>) -> Option<Self> {
+    match mode.unwrap_or("economical").to_ascii_lowercase().as_str() {
+      "economical" => Some(Self::Economical),
+      "conservative" => Some(Self::Conservative),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::Economical => "economical",
+      Self::Conservative => "conservative",
+    }
+  }
+
+  /// Share of samples in a fee-rate range that must have confirmed within the
+  /// target for the range to be considered safe.
+  fn success_threshold(self) -> f64 {
+    match self {
+      Self::Economical => 0.85,
+      Self::Conservative => 0.95,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ConfirmationSample {
+  fee_rate: f64,
+  wait_ms: u64,
+}
+
+/// Rolling history of how long confirmed transactions waited in the mempool.
+///
+/// Samples are recorded when confirmed txids leave the store through
+/// `removeTxids`, using the first-seen timestamp the store already keeps in
+/// its load tracker. The window is bounded by `MAX_CONFIRMATION_SAMPLES`, so
+/// memory stays constant on long-running nodes.
+#[derive(Default)]
+pub struct ConfirmationHistory {
+  samples: VecDeque<ConfirmationSample>,
+}
+
+impl ConfirmationHistory {
+  pub fn record(&mut self, fee_rate: f64, wait_ms: u64) {
+    if !fee_rate.is_finite() || fee_rate < 0.0 {
+      return;
+    }
+    if self.samples.len() >= MAX_CONFIRMATION_SAMPLES {
+      self.samples.pop_front();
+    }
+    self.samples.push_back(ConfirmationSample { fee_rate, wait_ms });
+  }
+
+  pub fn len(&self) -> usize {
+    self.samples.len()
+  }
+
+  pub fn clear(&mut self) {
+    self.samples.clear();
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    deque_bytes(&self.samples)
+  }
+
+  pub fn shrink_to_fit(&mut self) {
+    self.samples.shrink_to_fit();
+  }
+
+  /// Lowest fee rate whose bucket range historically confirmed within
+  /// `target_blocks` with the mode's success threshold.
+  ///
+  /// Algorithm: samples are grouped into geometric buckets and scanned from
+  /// the highest fee rate downwards. Buckets are accumulated until a range has
+  /// `MIN_BUCKET_SAMPLES`; if the range passes the threshold its lower bound
+  /// becomes the current answer and scanning continues, otherwise scanning
+  /// stops. Returns `None` when no range has enough samples to pass.
+  ///
+  /// Complexity: O(S log S) for S samples.
+  pub fn estimate(&self, target_blocks: u32, mode: EstimateMode) -> Option<f64> {
+    let max_wait = target_blocks.max(1) as u64 * BLOCK_INTERVAL_MS;
+    let mut sorted: Vec<ConfirmationSample> = self.samples.iter().copied().collect();
+    sorted.sort_by(|a, b| b.fee_rate.total_cmp(&a.fee_rate));
+
+    let mut best = None;
+    let mut range_total = 0usize;
+    let mut range_ok = 0usize;
+    let mut i = 0;
+
+    while i < sorted.len() {
+      let bucket = bucket_floor(sorted[i].fee_rate);
+      while i < sorted.len() && bucket_floor(sorted[i].fee_rate) == bucket {
+        range_total += 1;
+        if sorted[i].wait_ms <= max_wait {
+          range_ok += 1;
+        }
+        i += 1;
+      }
+
+      if range_total < MIN_BUCKET_SAMPLES {
+        continue;
+      }
+      if (range_ok as f64 / range_total as f64) < mode.success_threshold() {
+        break;
+      }
+      best = Some(bucket);
+      range_total = 0;
+      range_ok = 0;
+    }
+
+    best
+  }
+}
+
+/// Lower bound of the geometric bucket containing `fee_rate`.
+fn bucket_floor(fee_rate: f64) -> f64 {
+  if fee_rate <= MIN_FEE_RATE {
+    return 0.0;
+  }
+  let exponent = (fee_rate / MIN_FEE_RATE).ln() / BUCKET_SPACING.ln();
+  MIN_FEE_RATE * BUCKET_SPACING.powi(exponent.floor() as i32)
+}
+
+/// Combines the projected-block and historical estimates.
+///
+/// `Conservative` takes the higher of the two signals and `Economical` the
+/// lower one; without history the projection is used alone. The result is
+/// floored at `MIN_FEE_RATE`.
+pub fn combine_estimates(projected: f64, historical: Option<f64>, mode: EstimateMode) -> f64 {
+  let combined = match (historical, mode) {
+    (Some(h), EstimateMode::Conservative) => projected.max(h),
+    (Some(h), EstimateMode::Economical) => projected.min(h),
+    (None, _) => projected,
+  };
+  combined.max(MIN_FEE_RATE)
+}
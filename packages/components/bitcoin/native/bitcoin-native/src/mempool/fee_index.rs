@@ -0,0 +1,146 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Bound;
+
+use crate::utils::memory::{btree_set_bytes, map_bytes};
+
+/// Total order over non-negative fee rates.
+///
+/// For finite non-negative `f64` values the IEEE-754 bit pattern is monotonic,
+/// so the raw bits can be used directly as a `BTreeSet` key without a float
+/// comparison wrapper. Negative, NaN and infinite rates are clamped to zero.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FeeRateKey(u64);
+
+impl FeeRateKey {
+  pub fn from_rate(rate: f64) -> Self {
+    let rate = if rate.is_finite() && rate > 0.0 { rate } else { 0.0 };
+    Self(rate.to_bits())
+  }
+
+  pub fn rate(self) -> f64 {
+    f64::from_bits(self.0)
+  }
+
+  pub fn bits(self) -> u64 {
+    self.0
+  }
+}
+
+/// Ordered fee-rate index over mempool handles.
+///
+/// `by_rate` keeps `(fee rate, handle)` pairs sorted so top-N and range
+/// queries walk only the entries they return; `entries` maps a handle back to
+/// its current key and vsize so updates and removals are O(log N).
+#[derive(Default)]
+pub struct FeeRateIndex {
+  by_rate: BTreeSet<(FeeRateKey, u32)>,
+  entries: HashMap<u32, (FeeRateKey, u32)>,
+}
+
+pub struct HistogramBucket {
+  pub min: f64,
+  pub max: Option<f64>,
+  pub count: u64,
+  pub vsize: u64,
+}
+
+impl FeeRateIndex {
+  pub fn clear(&mut self) {
+    self.by_rate.clear();
+    self.entries.clear();
+  }
+
+  pub fn shrink_to_fit(&mut self) {
+    self.entries.shrink_to_fit();
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    btree_set_bytes(&self.by_rate) + map_bytes(&self.entries)
+  }
+
+  /// Inserts or re-keys `handle`. Average O(log N).
+  pub fn upsert(&mut self, handle: u32, rate: f64, vsize: u32) {
+    let key = FeeRateKey::from_rate(rate);
+    if let Some((old_key, _)) = self.entries.insert(handle, (key, vsize)) {
+      self.by_rate.remove(&(old_key, handle));
+    }
+    self.by_rate.insert((key, handle));
+  }
+
+  pub fn remove(&mut self, handle: u32) {
+    if let Some((key, _)) = self.entries.remove(&handle) {
+      self.by_rate.remove(&(key, handle));
+    }
+  }
+
+  /// Handles ordered from the highest to the lowest fee rate.
+  pub fn iter_desc(&self) -> impl Iterator<Item = (u32, f64, u32)> + '_ {
+    self.by_rate.iter().rev().map(move |(key, handle)| (*handle, key.rate(), self.vsize_of(*handle)))
+  }
+
+  /// Handles with `min <= rate <= max`, highest rate first. An infinite
+  /// `max` is unbounded; an inverted or NaN range yields nothing.
+  pub fn range_desc(&self, min: f64, max: f64) -> impl Iterator<Item = (u32, f64, u32)> + '_ {
+    let max = if max == f64::INFINITY { f64::MAX } else { max };
+    let range = (min <= max).then(|| {
+      let lower = Bound::Included((FeeRateKey::from_rate(min), 0));
+      self.range(lower, Bound::Included((FeeRateKey::from_rate(max), u32::MAX)))
+    });
+    range.into_iter().flatten().rev().map(move |(key, handle)| (*handle, key.rate(), self.vsize_of(*handle)))
+  }
+
+  /// `by_rate` between two bounds, empty when the clamped keys are inverted.
+  /// `BTreeSet::range` panics on such bounds, which would abort the process.
+  fn range(
+    &self,
+    lower: Bound<(FeeRateKey, u32)>,
+    upper: Bound<(FeeRateKey, u32)>,
+  ) -> impl DoubleEndedIterator<Item = &(FeeRateKey, u32)> + '_ {
+    let valid = match (lower, upper) {
+      (Bound::Included(start), Bound::Included(end)) => start <= end,
+      (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end) | Bound::Excluded(end)) => start < end,
+      _ => true,
+    };
+    valid.then(|| self.by_rate.range((lower, upper))).into_iter().flatten()
+  }
+
+  /// Buckets the index by ascending `boundaries`.
+  ///
+  /// Bucket `i` covers `[boundaries[i], boundaries[i + 1])`; the last bucket is
+  /// open-ended. When the first boundary is above zero, a leading `[0, b0)`
+  /// bucket is added so every entry is counted exactly once.
+  ///
+  /// Complexity: O(B log N + N) for B buckets.
+  pub fn histogram(&self, boundaries: &[f64]) -> Vec<HistogramBucket> {
+    let mut edges: Vec<f64> = boundaries.iter().copied().filter(|b| b.is_finite() && *b >= 0.0).collect();
+    edges.sort_by(f64::total_cmp);
+    edges.dedup();
+    if edges.first().map(|first| *first > 0.0).unwrap_or(true) {
+      edges.insert(0, 0.0);
+    }
+
+    edges
+      .iter()
+      .enumerate()
+      .map(|(i, min)| {
+        let max = edges.get(i + 1).copied();
+        let lower = Bound::Included((FeeRateKey::from_rate(*min), 0));
+        let upper = match max {
+          Some(max) => Bound::Excluded((FeeRateKey::from_rate(max), 0)),
+          None => Bound::Unbounded,
+        };
+
+        let mut bucket = HistogramBucket { min: *min, max, count: 0, vsize: 0 };
+        for (_, handle) in self.range(lower, upper) {
+          bucket.count += 1;
+          bucket.vsize += self.vsize_of(*handle) as u64;
+        }
+        bucket
+      })
+      .collect()
+  }
+
+  fn vsize_of(&self, handle: u32) -> u32 {
+    self.entries.get(&handle).map(|(_, vsize)| *vsize).unwrap_or(0)
+  }
+}
@@ -0,0 +1,137 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::utils::memory::{map_bytes, vec_bytes};
+use crate::utils::TxKey;
+
+/// In-mempool parent/child graph built from loaded transaction prevouts.
+///
+/// Edges exist only between live handles. A loaded transaction may reference
+/// a parent txid that the store does not know yet (e.g. a later provider
+/// refresh adds it); such references wait in `waiting` keyed by the parent
+/// txid and are linked once that txid receives a handle.
+#[derive(Default)]
+pub struct TxGraph {
+  parents: HashMap<u32, Vec<u32>>,
+  children: HashMap<u32, Vec<u32>>,
+  waiting: HashMap<TxKey, Vec<u32>>,
+}
+
+impl TxGraph {
+  pub fn clear(&mut self) {
+    self.parents.clear();
+    self.children.clear();
+    self.waiting.clear();
+  }
+
+  pub fn shrink_to_fit(&mut self) {
+    self.parents.shrink_to_fit();
+    self.children.shrink_to_fit();
+    self.waiting.shrink_to_fit();
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    fn lists<K>(map: &HashMap<K, Vec<u32>>) -> usize {
+      map_bytes(map) + map.values().map(vec_bytes).sum::<usize>()
+    }
+    lists(&self.parents) + lists(&self.children) + lists(&self.waiting)
+  }
+
+  pub fn link(&mut self, parent: u32, child: u32) {
+    if parent == child {
+      return;
+    }
+    let parents = self.parents.entry(child).or_default();
+    if parents.contains(&parent) {
+      return;
+    }
+    parents.push(parent);
+    self.children.entry(parent).or_default().push(child);
+  }
+
+  pub fn wait_for(&mut self, parent: TxKey, child: u32) {
+    let waiting = self.waiting.entry(parent).or_default();
+    if !waiting.contains(&child) {
+      waiting.push(child);
+    }
+  }
+
+  /// Links children that were waiting for `parent_key`, now known as `parent`.
+  pub fn resolve_waiting(&mut self, parent_key: TxKey, parent: u32) {
+    if let Some(children) = self.waiting.remove(&parent_key) {
+      for child in children {
+        self.link(parent, child);
+      }
+    }
+  }
+
+  /// Removes every edge of `handle`. `prevouts` are the parent txids the
+  /// handle referenced, used to drop its unresolved `waiting` entries.
+  pub fn remove(&mut self, handle: u32, prevouts: impl Iterator<Item = TxKey>) {
+    if let Some(parents) = self.parents.remove(&handle) {
+      for parent in parents {
+        detach(&mut self.children, parent, handle);
+      }
+    }
+    if let Some(children) = self.children.remove(&handle) {
+      for child in children {
+        detach(&mut self.parents, child, handle);
+      }
+    }
+    for key in prevouts {
+      if let Some(waiting) = self.waiting.get_mut(&key) {
+        waiting.retain(|candidate| *candidate != handle);
+        if waiting.is_empty() {
+          self.waiting.remove(&key);
+        }
+      }
+    }
+  }
+
+  pub fn parents_of(&self, handle: u32) -> &[u32] {
+    self.parents.get(&handle).map(Vec::as_slice).unwrap_or(&[])
+  }
+
+  pub fn children_of(&self, handle: u32) -> &[u32] {
+    self.children.get(&handle).map(Vec::as_slice).unwrap_or(&[])
+  }
+
+  /// All in-mempool ancestors of `handle` (excluding itself), nearest first.
+  ///
+  /// Complexity: O(A) for A ancestors and their edges.
+  pub fn ancestors(&self, handle: u32) -> Vec<u32> {
+    walk(handle, |h| self.parents_of(h))
+  }
+
+  /// All in-mempool descendants of `handle` (excluding itself), nearest first.
+  ///
+  /// Complexity: O(D) for D descendants and their edges.
+  pub fn descendants(&self, handle: u32) -> Vec<u32> {
+    walk(handle, |h| self.children_of(h))
+  }
+}
+
+fn detach(edges: &mut HashMap<u32, Vec<u32>>, from: u32, to: u32) {
+  if let Some(list) = edges.get_mut(&from) {
+    list.retain(|candidate| *candidate != to);
+    if list.is_empty() {
+      edges.remove(&from);
+    }
+  }
+}
+
+fn walk<'a>(start: u32, next: impl Fn(u32) -> &'a [u32]) -> Vec<u32> {
+  let mut out = Vec::new();
+  let mut visited = HashSet::from([start]);
+  let mut queue = VecDeque::from([start]);
+
+  while let Some(handle) = queue.pop_front() {
+    for neighbour in next(handle) {
+      if visited.insert(*neighbour) {
+        out.push(*neighbour);
+        queue.push_back(*neighbour);
+      }
+    }
+  }
+
+  out
+}
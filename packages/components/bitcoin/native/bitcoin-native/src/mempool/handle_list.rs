@@ -0,0 +1,75 @@
+use std::collections::HashSet;
+use std::ops::Deref;
+
+use crate::utils::memory::{set_bytes, vec_bytes};
+
+/// A provider's handles in insertion order, with a set beside the list so
+/// membership checks stay O(1) however long the list grows.
+#[derive(Clone, Debug, Default)]
+pub struct HandleList {
+  handles: Vec<u32>,
+  members: HashSet<u32>,
+}
+
+impl HandleList {
+  /// Appends `handle` unless already listed; returns whether it was added.
+  pub fn push(&mut self, handle: u32) -> bool {
+    let added = self.members.insert(handle);
+    if added {
+      self.handles.push(handle);
+    }
+    added
+  }
+
+  pub fn contains(&self, handle: &u32) -> bool {
+    self.members.contains(handle)
+  }
+
+  pub fn retain(&mut self, mut keep: impl FnMut(&u32) -> bool) {
+    let members = &mut self.members;
+    self.handles.retain(|handle| {
+      let kept = keep(handle);
+      if !kept {
+        members.remove(handle);
+      }
+      kept
+    });
+  }
+
+  pub fn shrink_to_fit(&mut self) {
+    self.handles.shrink_to_fit();
+    self.members.shrink_to_fit();
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    vec_bytes(&self.handles) + set_bytes(&self.members)
+  }
+}
+
+impl Deref for HandleList {
+  type Target = [u32];
+
+  fn deref(&self) -> &[u32] {
+    &self.handles
+  }
+}
+
+impl FromIterator<u32> for HandleList {
+  /// Keeps the first occurrence of each handle.
+  fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+    let mut list = Self::default();
+    iter.into_iter().for_each(|handle| {
+      list.push(handle);
+    });
+    list
+  }
+}
+
+impl<'a> IntoIterator for &'a HandleList {
+  type Item = &'a u32;
+  type IntoIter = std::slice::Iter<'a, u32>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.handles.iter()
+  }
+}
@@ -0,0 +1,154 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::utils::memory::{btree_map_bytes, map_bytes};
+use crate::utils::TxKey;
+
+/// Removals kept for delta export before the oldest ones are forgotten.
+pub const MAX_RETAINED_REMOVALS: usize = 100_000;
+
+#[derive(Clone, Copy)]
+struct JournalEntry {
+  /// Sequence of the latest change to this txid.
+  seq: u64,
+  /// Sequence at which the txid entered the store; the journal floor for
+  /// txids that were already present when the journal was reset.
+  created: u64,
+  removed: bool,
+}
+
+/// Changes visible to a reader that last synced at some sequence.
+pub struct JournalDelta {
+  pub added: Vec<TxKey>,
+  pub updated: Vec<TxKey>,
+  pub removed: Vec<TxKey>,
+}
+
+/// Change journal behind `exportDelta`.
+///
+/// Every mutation of a txid bumps a monotonically increasing sequence and
+/// records it as that txid's latest change, so the journal holds one entry
+/// per live txid plus a bounded tail of removals. A delta since `seq` is then
+/// a range scan over the changes newer than `seq`, reporting each txid once
+/// no matter how often it changed in between.
+///
+/// `floor` is the oldest sequence a delta can start from. It moves forward
+/// when the store is reset (snapshot import, `clear`) and when old removals
+/// are trimmed; older readers must resync from a full snapshot. A snapshot
+/// apply is journaled as the txids it added, re-described and removed.
+#[derive(Default)]
+pub struct ChangeJournal {
+  seq: u64,
+  floor: u64,
+  entries: HashMap<TxKey, JournalEntry>,
+  live: BTreeMap<u64, TxKey>,
+  removals: BTreeMap<u64, TxKey>,
+}
+
+impl ChangeJournal {
+  pub fn seq(&self) -> u64 {
+    self.seq
+  }
+
+  pub fn floor(&self) -> u64 {
+    self.floor
+  }
+
+  /// Forgets every recorded change; deltas can only start from now on.
+  pub fn reset(&mut self) {
+    self.seq += 1;
+    self.floor = self.seq;
+    self.entries.clear();
+    self.live.clear();
+    self.removals.clear();
+  }
+
+  pub fn shrink_to_fit(&mut self) {
+    self.entries.shrink_to_fit();
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    map_bytes(&self.entries) + btree_map_bytes(&self.live) + btree_map_bytes(&self.removals)
+  }
+
+  /// Records that `key` entered the store.
+  pub fn added(&mut self, key: TxKey) {
+    self.seq += 1;
+    let seq = self.seq;
+    if let Some(previous) = self.entries.insert(key, JournalEntry { seq, created: seq, removed: false }) {
+      self.unlink(previous.seq, previous.removed);
+    }
+    self.live.insert(seq, key);
+  }
+
+  /// Records that the payload of `key` changed.
+  pub fn updated(&mut self, key: TxKey) {
+    self.seq += 1;
+    let seq = self.seq;
+    let floor = self.floor;
+    let entry = self.entries.entry(key).or_insert(JournalEntry { seq, created: floor, removed: false });
+    let created = entry.created;
+    let previous = std::mem::replace(entry, JournalEntry { seq, created, removed: false });
+    if previous.seq != seq {
+      self.unlink(previous.seq, previous.removed);
+    }
+    self.live.insert(seq, key);
+  }
+
+  /// Records that `key` left the store.
+  pub fn removed(&mut self, key: TxKey) {
+    self.seq += 1;
+    let seq = self.seq;
+    let floor = self.floor;
+    let entry = self.entries.entry(key).or_insert(JournalEntry { seq, created: floor, removed: true });
+    let created = entry.created;
+    let previous = std::mem::replace(entry, JournalEntry { seq, created, removed: true });
+    if previous.seq != seq {
+      self.unlink(previous.seq, previous.removed);
+    }
+    self.removals.insert(seq, key);
+    self.trim_removals();
+  }
+
+  /// Txids changed after `since`, or `None` when `since` predates `floor`.
+  ///
+  /// A txid both added and removed after `since` is omitted: the reader never
+  /// saw it.
+  pub fn since(&self, since: u64) -> Option<JournalDelta> {
+    if since < self.floor {
+      return None;
+    }
+    let mut delta = JournalDelta { added: Vec::new(), updated: Vec::new(), removed: Vec::new() };
+    for key in self.live.range(since + 1..).map(|(_, key)| *key) {
+      let created = self.entries.get(&key).map_or(0, |entry| entry.created);
+      if created > since {
+        delta.added.push(key);
+      } else {
+        delta.updated.push(key);
+      }
+    }
+    for key in self.removals.range(since + 1..).map(|(_, key)| *key) {
+      if self.entries.get(&key).is_some_and(|entry| entry.created <= since) {
+        delta.removed.push(key);
+      }
+    }
+    Some(delta)
+  }
+
+  fn unlink(&mut self, seq: u64, removed: bool) {
+    if removed {
+      self.removals.remove(&seq);
+    } else {
+      self.live.remove(&seq);
+    }
+  }
+
+  fn trim_removals(&mut self) {
+    while self.removals.len() > MAX_RETAINED_REMOVALS {
+      let Some((seq, key)) = self.removals.pop_first() else {
+        break;
+      };
+      self.entries.remove(&key);
+      self.floor = self.floor.max(seq);
+    }
+  }
+}
@@ -0,0 +1,91 @@
+use serde_json::{json, Value};
+
+use crate::utils::{txid_to_hex, TxKey};
+
+/// Why an entry was evicted by `MempoolLimits` enforcement.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EvictionReason {
+  MaxEntries,
+  MaxVbytes,
+  MaxBytes,
+  /// First seen longer than `ttlMs` ago (or a descendant of such an entry).
+  Expired,
+}
+
+impl EvictionReason {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::MaxEntries => "maxEntries",
+      Self::MaxVbytes => "maxVbytes",
+      Self::MaxBytes => "maxBytes",
+      Self::Expired => "expired",
+    }
+  }
+}
+
+/// Current size of the store, in the units the limits are expressed in.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Usage {
+  pub entries: u64,
+  pub vbytes: u64,
+  pub bytes: u64,
+}
+
+/// Capacity bounds for the Bitcoin mempool store. `None` disables a bound;
+/// the default store is unbounded.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MempoolLimits {
+  pub max_entries: Option<u64>,
+  pub max_vbytes: Option<u64>,
+  /// Budget for `Usage::bytes`, the store's estimate of its per-entry memory.
+  pub max_bytes: Option<u64>,
+  pub ttl_ms: Option<u64>,
+}
+
+impl MempoolLimits {
+  /// Parses `{ maxEntries?, maxVbytes?, maxBytes?, ttlMs? }`. Missing, null or
+  /// zero values disable the corresponding bound.
+  pub fn from_value(value: &Value) -> Self {
+    let bound =
+      |field: &str| value.get(field).and_then(Value::as_f64).filter(|limit| *limit >= 1.0).map(|limit| limit as u64);
+    Self {
+      max_entries: bound("maxEntries"),
+      max_vbytes: bound("maxVbytes"),
+      max_bytes: bound("maxBytes"),
+      ttl_ms: bound("ttlMs"),
+    }
+  }
+
+  pub fn to_value(self) -> Value {
+    json!({
+      "maxEntries": self.max_entries,
+      "maxVbytes": self.max_vbytes,
+      "maxBytes": self.max_bytes,
+      "ttlMs": self.ttl_ms,
+    })
+  }
+
+  pub fn is_size_bounded(&self) -> bool {
+    self.max_entries.is_some() || self.max_vbytes.is_some() || self.max_bytes.is_some()
+  }
+
+  /// First bound `usage` exceeds, checked in declaration order.
+  pub fn exceeded(&self, usage: &Usage) -> Option<EvictionReason> {
+    let over = |limit: Option<u64>, used: u64| limit.is_some_and(|limit| used > limit);
+    if over(self.max_entries, usage.entries) {
+      Some(EvictionReason::MaxEntries)
+    } else if over(self.max_vbytes, usage.vbytes) {
+      Some(EvictionReason::MaxVbytes)
+    } else if over(self.max_bytes, usage.bytes) {
+      Some(EvictionReason::MaxBytes)
+    } else {
+      None
+    }
+  }
+}
+
+pub fn evictions_value(evicted: &[(TxKey, EvictionReason)]) -> Value {
+  Value::Array(
+    evicted.iter().map(|(txid, reason)| json!({ "txid": txid_to_hex(*txid), "reason": reason.as_str() })).collect(),
+  )
+}
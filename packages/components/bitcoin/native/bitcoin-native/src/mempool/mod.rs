@@ -1,4 +1,24 @@
+mod compaction;
+mod coverage;
+mod cursor;
+mod diff;
+mod fee_estimator;
+mod fee_index;
+mod graph;
+mod handle_list;
+mod journal;
+mod limits;
+mod pending;
+mod projection;
+mod query;
+mod retry;
+mod scripts;
 mod snapshot;
+mod snapshot_binary;
+mod spends;
 mod state_store;
+pub(crate) mod types;
+mod variants;
+mod watchlist;
 
 pub use state_store::NativeMempoolState;
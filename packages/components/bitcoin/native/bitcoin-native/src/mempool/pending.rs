@@ -0,0 +1,157 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::fee_index::FeeRateKey;
+use crate::utils::memory::{btree_set_bytes, map_bytes};
+
+/// Order in which `pendingTxids` offers txids that still need a load.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PendingOrder {
+  /// Provider list order; walks `provider_tx` and keeps no index.
+  #[default]
+  Insertion,
+  /// Highest metadata fee rate first.
+  FeeRate,
+  /// Oldest provider `time` first.
+  FirstSeen,
+  /// Watched txids and their direct children first, then insertion order.
+  WatchedFirst,
+}
+
+impl PendingOrder {
+  pub fn parse(name: &str) -> Option<Self> {
+    match name {
+      "insertion" => Some(Self::Insertion),
+      "feeRate" => Some(Self::FeeRate),
+      "firstSeen" => Some(Self::FirstSeen),
+      "watchedFirst" => Some(Self::WatchedFirst),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::Insertion => "insertion",
+      Self::FeeRate => "feeRate",
+      Self::FirstSeen => "firstSeen",
+      Self::WatchedFirst => "watchedFirst",
+    }
+  }
+
+  /// Sort key of a pending entry; smaller keys are offered first. Ties fall
+  /// back to the handle, which follows insertion order.
+  pub fn key(self, fee_rate: f64, time: u64, watched: bool) -> u64 {
+    match self {
+      Self::Insertion => 0,
+      Self::FeeRate => u64::MAX - FeeRateKey::from_rate(fee_rate).bits(),
+      Self::FirstSeen => time,
+      Self::WatchedFirst => u64::from(!watched),
+    }
+  }
+}
+
+/// Per-provider queues of handles awaiting a load, ordered by `PendingOrder`.
+///
+/// A handle is queued for every provider whose `provider_tx` list holds it
+/// while it has metadata and no loaded transaction. `keys` maps it back to
+/// its current key so re-keying and removal are O(P log N) for P providers.
+#[derive(Default)]
+pub struct PendingIndex {
+  order: PendingOrder,
+  queues: HashMap<String, BTreeSet<(u64, u32)>>,
+  keys: HashMap<u32, u64>,
+}
+
+impl PendingIndex {
+  pub fn order(&self) -> PendingOrder {
+    self.order
+  }
+
+  /// Switches the order and drops the queues; the caller rebuilds them.
+  pub fn set_order(&mut self, order: PendingOrder) {
+    self.order = order;
+    self.clear();
+  }
+
+  pub fn is_active(&self) -> bool {
+    self.order != PendingOrder::Insertion
+  }
+
+  pub fn clear(&mut self) {
+    self.queues.clear();
+    self.keys.clear();
+  }
+
+  pub fn shrink_to_fit(&mut self) {
+    self.queues.shrink_to_fit();
+    self.keys.shrink_to_fit();
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    map_bytes(&self.queues)
+      + self.queues.iter().map(|(name, queue)| name.capacity() + btree_set_bytes(queue)).sum::<usize>()
+      + map_bytes(&self.keys)
+  }
+
+  pub fn contains(&self, handle: u32) -> bool {
+    self.keys.contains_key(&handle)
+  }
+
+  /// Queues `handle` for `provider` under its current key, or under `key`
+  /// when it is not queued yet.
+  pub fn push(&mut self, provider: &str, handle: u32, key: u64) {
+    let key = *self.keys.entry(handle).or_insert(key);
+    match self.queues.get_mut(provider) {
+      Some(queue) => {
+        queue.insert((key, handle));
+      }
+      None => {
+        self.queues.insert(provider.to_string(), BTreeSet::from([(key, handle)]));
+      }
+    }
+  }
+
+  /// Moves a queued `handle` to `key` in every provider queue holding it.
+  pub fn rekey(&mut self, handle: u32, key: u64) {
+    let Some(old) = self.keys.insert(handle, key) else {
+      return;
+    };
+    if old == key {
+      return;
+    }
+    for queue in self.queues.values_mut() {
+      if queue.remove(&(old, handle)) {
+        queue.insert((key, handle));
+      }
+    }
+  }
+
+  pub fn remove(&mut self, handle: u32) {
+    let Some(key) = self.keys.remove(&handle) else {
+      return;
+    };
+    for queue in self.queues.values_mut() {
+      queue.remove(&(key, handle));
+    }
+    self.queues.retain(|_, queue| !queue.is_empty());
+  }
+
+  /// Dequeues `handle` from `provider` only, forgetting its key once no
+  /// queue holds it.
+  pub fn remove_from(&mut self, provider: &str, handle: u32) {
+    let (Some(key), Some(queue)) = (self.keys.get(&handle).copied(), self.queues.get_mut(provider)) else {
+      return;
+    };
+    queue.remove(&(key, handle));
+    if queue.is_empty() {
+      self.queues.remove(provider);
+    }
+    if !self.queues.values().any(|queue| queue.contains(&(key, handle))) {
+      self.keys.remove(&handle);
+    }
+  }
+
+  /// Queued handles of `provider` in pending order.
+  pub fn iter(&self, provider: &str) -> impl Iterator<Item = u32> + '_ {
+    self.queues.get(provider).into_iter().flatten().map(|(_, handle)| *handle)
+  }
+}
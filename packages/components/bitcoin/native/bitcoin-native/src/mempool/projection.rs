@@ -0,0 +1,203 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use super::fee_index::FeeRateKey;
+
+/// Consensus block weight limit.
+pub const MAX_BLOCK_WEIGHT: u64 = 4_000_000;
+/// Weight kept free for the coinbase transaction, as in Bitcoin Core's
+/// `BlockAssembler` default.
+pub const COINBASE_RESERVED_WEIGHT: u64 = 4_000;
+
+/// After this many consecutive packages fail to fit, a block that is already
+/// within `COINBASE_RESERVED_WEIGHT` of the limit is considered full. Mirrors
+/// Bitcoin Core's `MAX_CONSECUTIVE_FAILURES` heuristic.
+const MAX_CONSECUTIVE_FAILURES: u32 = 1_000;
+
+/// One mempool entry as seen by the block template simulation.
+///
+/// `ancestor_fee`/`ancestor_vsize` include the entry itself, exactly like the
+/// `ancestorfees`/`ancestorsize` fields returned by `getrawmempool true`.
+pub struct PackageNode {
+  pub handle: u32,
+  pub fee: u64,
+  pub vsize: u64,
+  pub weight: u64,
+  pub ancestor_fee: u64,
+  pub ancestor_vsize: u64,
+  pub parents: Vec<u32>,
+}
+
+pub struct ProjectedBlock {
+  pub handles: Vec<u32>,
+  pub total_fees: u64,
+  pub weight: u64,
+  pub vsize: u64,
+  /// Effective (package) fee rate of each included tx, in inclusion order.
+  pub fee_rates: Vec<f64>,
+}
+
+impl ProjectedBlock {
+  /// Returns `(min, median, max)` effective fee rate, or zeros when empty.
+  pub fn fee_range(&self) -> (f64, f64, f64) {
+    if self.fee_rates.is_empty() {
+      return (0.0, 0.0, 0.0);
+    }
+    let mut rates = self.fee_rates.clone();
+    rates.sort_by(f64::total_cmp);
+    let mid = rates.len() / 2;
+    let median = if rates.len().is_multiple_of(2) { (rates[mid - 1] + rates[mid]) / 2.0 } else { rates[mid] };
+    (rates[0], median, rates[rates.len() - 1])
+  }
+}
+
+fn score(fee: u64, vsize: u64) -> FeeRateKey {
+  FeeRateKey::from_rate(if vsize == 0 { 0.0 } else { fee as f64 / vsize as f64 })
+}
+
+/// Simulates up to `max_blocks` block templates using ancestor-score package
+/// selection.
+///
+/// Algorithm (simplified `BlockAssembler::addPackageTxs`):
+/// 1. Every entry starts with the provider-reported ancestor fee/size, so its
+///    initial score is `ancestor_fee / ancestor_vsize`.
+/// 2. The best-scoring entry is popped from a max-heap. Its not-yet-selected
+///    in-mempool ancestors plus itself form the package that is added to the
+///    current block, parents first.
+/// 3. Every selected tx is subtracted from the ancestor totals of its remaining
+///    descendants, which are re-queued with their updated score. Stale heap
+///    entries are skipped lazily.
+/// 4. A package that does not fit the remaining block weight is deferred to the
+///    next block. A block is closed when the heap is exhausted, or when it is
+///    nearly full and packages keep failing to fit.
+///
+/// Parents that are not part of `nodes` (unknown to the store) are treated as
+/// unavailable for ordering but do not block their children.
+///
+/// Complexity: O(N log N + sum of descendant walks of selected txs).
+pub fn project_blocks(nodes: Vec<PackageNode>, max_blocks: usize) -> Vec<ProjectedBlock> {
+  let index_of: HashMap<u32, usize> = nodes.iter().enumerate().map(|(i, node)| (node.handle, i)).collect();
+  let parents: Vec<Vec<usize>> =
+    nodes.iter().map(|node| node.parents.iter().filter_map(|parent| index_of.get(parent).copied()).collect()).collect();
+  let mut children: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+  for (child, list) in parents.iter().enumerate() {
+    for parent in list {
+      children[*parent].push(child);
+    }
+  }
+
+  let mut ancestor_fee: Vec<u64> = nodes.iter().map(|n| n.ancestor_fee.max(n.fee)).collect();
+  let mut ancestor_vsize: Vec<u64> = nodes.iter().map(|n| n.ancestor_vsize.max(n.vsize)).collect();
+  let mut selected = vec![false; nodes.len()];
+  let mut heap: BinaryHeap<(FeeRateKey, Reverse<usize>)> =
+    (0..nodes.len()).map(|i| (score(ancestor_fee[i], ancestor_vsize[i]), Reverse(i))).collect();
+
+  let limit = MAX_BLOCK_WEIGHT - COINBASE_RESERVED_WEIGHT;
+  let mut remaining = nodes.len();
+  let mut blocks = Vec::new();
+
+  while blocks.len() < max_blocks && remaining > 0 {
+    let mut block = ProjectedBlock { handles: Vec::new(), total_fees: 0, weight: 0, vsize: 0, fee_rates: Vec::new() };
+    let mut deferred = Vec::new();
+    let mut failures = 0;
+
+    while let Some((key, Reverse(i))) = heap.pop() {
+      if selected[i] || key != score(ancestor_fee[i], ancestor_vsize[i]) {
+        continue;
+      }
+
+      let package = unselected_ancestors_with_self(i, &parents, &selected);
+      let package_weight: u64 = package.iter().map(|j| nodes[*j].weight).sum();
+
+      if block.weight + package_weight > limit {
+        deferred.push(i);
+        failures += 1;
+        if failures > MAX_CONSECUTIVE_FAILURES && block.weight > limit - COINBASE_RESERVED_WEIGHT {
+          break;
+        }
+        continue;
+      }
+
+      failures = 0;
+      let package_fee: u64 = package.iter().map(|j| nodes[*j].fee).sum();
+      let package_vsize: u64 = package.iter().map(|j| nodes[*j].vsize).sum();
+      let package_rate = if package_vsize == 0 { 0.0 } else { package_fee as f64 / package_vsize as f64 };
+
+      for j in &package {
+        selected[*j] = true;
+        remaining -= 1;
+        block.handles.push(nodes[*j].handle);
+        block.total_fees += nodes[*j].fee;
+        block.weight += nodes[*j].weight;
+        block.vsize += nodes[*j].vsize;
+        block.fee_rates.push(package_rate);
+      }
+
+      let mut touched = Vec::new();
+      for j in &package {
+        for descendant in unselected_descendants(*j, &children, &selected) {
+          ancestor_fee[descendant] = ancestor_fee[descendant].saturating_sub(nodes[*j].fee).max(nodes[descendant].fee);
+          ancestor_vsize[descendant] =
+            ancestor_vsize[descendant].saturating_sub(nodes[*j].vsize).max(nodes[descendant].vsize);
+          touched.push(descendant);
+        }
+      }
+      touched.sort_unstable();
+      touched.dedup();
+      for d in touched {
+        heap.push((score(ancestor_fee[d], ancestor_vsize[d]), Reverse(d)));
+      }
+    }
+
+    if block.handles.is_empty() {
+      // Only packages heavier than a whole block are left; they can never fit.
+      break;
+    }
+
+    blocks.push(block);
+    for i in deferred {
+      heap.push((score(ancestor_fee[i], ancestor_vsize[i]), Reverse(i)));
+    }
+  }
+
+  blocks
+}
+
+/// Unselected in-mempool ancestors of `start` plus `start`, parents first.
+fn unselected_ancestors_with_self(start: usize, parents: &[Vec<usize>], selected: &[bool]) -> Vec<usize> {
+  let mut out = Vec::new();
+  let mut visited = HashSet::new();
+  let mut stack = vec![(start, false)];
+
+  while let Some((node, expanded)) = stack.pop() {
+    if expanded {
+      out.push(node);
+      continue;
+    }
+    if selected[node] || !visited.insert(node) {
+      continue;
+    }
+    stack.push((node, true));
+    for parent in &parents[node] {
+      stack.push((*parent, false));
+    }
+  }
+
+  out
+}
+
+fn unselected_descendants(start: usize, children: &[Vec<usize>], selected: &[bool]) -> Vec<usize> {
+  let mut out = Vec::new();
+  let mut visited = HashSet::new();
+  let mut stack: Vec<usize> = children[start].clone();
+
+  while let Some(node) = stack.pop() {
+    if selected[node] || !visited.insert(node) {
+      continue;
+    }
+    out.push(node);
+    stack.extend(children[node].iter().copied());
+  }
+
+  out
+}
@@ -0,0 +1,121 @@
+use napi::{Error, Result};
+use serde_json::Value;
+
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+pub const MAX_QUERY_LIMIT: usize = 10_000;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QuerySort {
+  /// Handle order, i.e. the order the store first saw the txids.
+  Insertion,
+  FeeRate,
+  Vsize,
+  /// Entry time, see `MempoolBackingStore::entry_time_ms`.
+  Age,
+  AncestorCount,
+  Txid,
+}
+
+impl QuerySort {
+  fn parse(name: &str) -> Option<Self> {
+    match name {
+      "insertion" => Some(Self::Insertion),
+      "feeRate" => Some(Self::FeeRate),
+      "vsize" => Some(Self::Vsize),
+      "age" => Some(Self::Age),
+      "ancestorCount" => Some(Self::AncestorCount),
+      "txid" => Some(Self::Txid),
+      _ => None,
+    }
+  }
+}
+
+/// Inclusive `[min, max]` bound; either side may be open.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Range {
+  pub min: Option<f64>,
+  pub max: Option<f64>,
+}
+
+impl Range {
+  fn from_value(value: &Value, min: &str, max: &str) -> Self {
+    Self { min: value.get(min).and_then(Value::as_f64), max: value.get(max).and_then(Value::as_f64) }
+  }
+
+  pub fn is_open(&self) -> bool {
+    self.min.is_none() && self.max.is_none()
+  }
+
+  pub fn contains(&self, value: f64) -> bool {
+    self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+  }
+}
+
+/// Parsed `query(filter)` argument. Every predicate is optional; entries
+/// must match all given ones.
+#[derive(Clone, Debug)]
+pub struct MempoolQuery {
+  pub fee_rate: Range,
+  pub vsize: Range,
+  pub ancestor_count: Range,
+  /// Milliseconds since the entry time.
+  pub age_ms: Range,
+  pub bip125_replaceable: Option<bool>,
+  pub loaded: Option<bool>,
+  pub provider: Option<String>,
+  /// Lowercase hex prefix.
+  pub txid_prefix: Option<String>,
+  pub sort: QuerySort,
+  pub descending: bool,
+  pub offset: usize,
+  pub limit: usize,
+  pub include_transactions: bool,
+}
+
+impl MempoolQuery {
+  /// Parses `{ minFeeRate?, maxFeeRate?, minVsize?, maxVsize?,
+  /// minAncestorCount?, maxAncestorCount?, minAgeMs?, maxAgeMs?,
+  /// bip125Replaceable?, loaded?, provider?, txidPrefix?, sort?, order?,
+  /// offset?, limit?, includeTransactions? }`.
+  ///
+  /// `order` defaults to `desc` for `feeRate` and `asc` otherwise; `limit`
+  /// defaults to `DEFAULT_QUERY_LIMIT` and is capped at `MAX_QUERY_LIMIT`.
+  pub fn from_value(filter: &Value) -> Result<Self> {
+    let sort = match filter.get("sort").and_then(Value::as_str) {
+      None => QuerySort::Insertion,
+      Some(name) => QuerySort::parse(name).ok_or_else(|| {
+        Error::from_reason(
+          "Unsupported query sort. Expected 'insertion', 'feeRate', 'vsize', 'age', 'ancestorCount' or 'txid'.",
+        )
+      })?,
+    };
+    let descending = match filter.get("order").and_then(Value::as_str) {
+      None => sort == QuerySort::FeeRate,
+      Some("asc") => false,
+      Some("desc") => true,
+      Some(_) => return Err(Error::from_reason("Unsupported query order. Expected 'asc' or 'desc'.")),
+    };
+    let txid_prefix = filter.get("txidPrefix").and_then(Value::as_str).map(str::to_ascii_lowercase);
+    if txid_prefix.as_deref().is_some_and(|prefix| prefix.len() > 64 || !prefix.bytes().all(|b| b.is_ascii_hexdigit()))
+    {
+      return Err(Error::from_reason("Query txidPrefix must be up to 64 hex characters"));
+    }
+    let count = |field: &str| filter.get(field).and_then(Value::as_f64).map(|value| value.max(0.0) as usize);
+
+    Ok(Self {
+      fee_rate: Range::from_value(filter, "minFeeRate", "maxFeeRate"),
+      vsize: Range::from_value(filter, "minVsize", "maxVsize"),
+      ancestor_count: Range::from_value(filter, "minAncestorCount", "maxAncestorCount"),
+      age_ms: Range::from_value(filter, "minAgeMs", "maxAgeMs"),
+      bip125_replaceable: filter.get("bip125Replaceable").and_then(Value::as_bool),
+      loaded: filter.get("loaded").and_then(Value::as_bool),
+      provider: filter.get("provider").and_then(Value::as_str).map(str::to_string),
+      txid_prefix,
+      sort,
+      descending,
+      offset: count("offset").unwrap_or(0),
+      limit: count("limit").unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT),
+      include_transactions: filter.get("includeTransactions").and_then(Value::as_bool).unwrap_or(false),
+    })
+  }
+}
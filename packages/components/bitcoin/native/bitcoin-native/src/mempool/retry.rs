@@ -0,0 +1,104 @@
+use serde_json::{json, Value};
+
+use super::types::{ProviderId, ProviderNames};
+
+pub const DEFAULT_BASE_DELAY_MS: u64 = 1_000;
+pub const DEFAULT_MAX_DELAY_MS: u64 = 5 * 60_000;
+pub const DEFAULT_MAX_FAILURES: u32 = 3;
+
+/// Backoff applied to txids a provider failed to load.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+  pub base_delay_ms: u64,
+  pub max_delay_ms: u64,
+  /// Failures after which a provider gives the txid up to another provider
+  /// that also lists it.
+  pub max_failures: u32,
+}
+
+impl Default for RetryPolicy {
+  fn default() -> Self {
+    Self {
+      base_delay_ms: DEFAULT_BASE_DELAY_MS,
+      max_delay_ms: DEFAULT_MAX_DELAY_MS,
+      max_failures: DEFAULT_MAX_FAILURES,
+    }
+  }
+}
+
+impl RetryPolicy {
+  /// Delay before attempt `failures + 1`: `base * 2^(failures - 1)`, capped.
+  pub fn delay_ms(&self, failures: u32) -> u64 {
+    let shift = failures.saturating_sub(1).min(32);
+    self.base_delay_ms.saturating_mul(1 << shift).min(self.max_delay_ms)
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ProviderFailures {
+  provider: ProviderId,
+  attempts: u32,
+  retry_at_ms: u64,
+}
+
+/// Failed load attempts of one txid, per provider.
+#[derive(Clone, Debug, Default)]
+pub struct LoadFailures {
+  providers: Vec<ProviderFailures>,
+  last_reason: Option<Box<str>>,
+}
+
+impl LoadFailures {
+  /// Records one more failure by `provider` and returns its attempt count and
+  /// the time before which it should not be retried.
+  pub fn record(
+    &mut self,
+    provider: ProviderId,
+    reason: Option<&str>,
+    now_ms: u64,
+    policy: &RetryPolicy,
+  ) -> (u32, u64) {
+    let index = match self.providers.iter().position(|entry| entry.provider == provider) {
+      Some(index) => index,
+      None => {
+        self.providers.push(ProviderFailures { provider, attempts: 0, retry_at_ms: 0 });
+        self.providers.len() - 1
+      }
+    };
+    let entry = &mut self.providers[index];
+    entry.attempts += 1;
+    entry.retry_at_ms = now_ms.saturating_add(policy.delay_ms(entry.attempts));
+    if let Some(reason) = reason {
+      self.last_reason = Some(reason.into());
+    }
+    (entry.attempts, entry.retry_at_ms)
+  }
+
+  pub fn attempts(&self, provider: ProviderId) -> u32 {
+    self.providers.iter().find(|entry| entry.provider == provider).map_or(0, |entry| entry.attempts)
+  }
+
+  pub fn in_backoff(&self, provider: ProviderId, now_ms: u64) -> bool {
+    self.providers.iter().any(|entry| entry.provider == provider && now_ms < entry.retry_at_ms)
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    self.providers.capacity() * std::mem::size_of::<ProviderFailures>()
+      + self.last_reason.as_ref().map_or(0, |reason| reason.len())
+  }
+
+  pub fn to_value(&self, names: &ProviderNames) -> Value {
+    let providers: Vec<Value> = self
+      .providers
+      .iter()
+      .map(|entry| {
+        json!({
+          "provider": names.name(entry.provider),
+          "attempts": entry.attempts,
+          "retryAt": entry.retry_at_ms,
+        })
+      })
+      .collect();
+    json!({ "reason": self.last_reason.as_deref(), "providers": providers })
+  }
+}
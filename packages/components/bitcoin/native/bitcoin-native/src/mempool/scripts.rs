@@ -0,0 +1,132 @@
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use super::types::LightScriptPubKey;
+use crate::utils::memory::{map_bytes, vec_bytes};
+
+/// SHA-256 of the raw scriptPubKey. Outputs that carry only an address (no
+/// `hex`) are keyed by the hash of the address string instead, so they are
+/// still reachable through `getTransactionsByAddress`.
+pub type ScriptKey = [u8; 32];
+
+pub fn script_key_from_hex(script_hex: &str) -> Option<ScriptKey> {
+  hex::decode(script_hex).ok().map(|script| Sha256::digest(script).into())
+}
+
+fn script_key(script_pub_key: &LightScriptPubKey) -> Option<ScriptKey> {
+  if let Some(script) = &script_pub_key.script {
+    return Some(Sha256::digest(script).into());
+  }
+  let address = addresses_of(script_pub_key).next()?;
+  let mut hasher = Sha256::new();
+  hasher.update(b"address:");
+  hasher.update(address.as_bytes());
+  Some(hasher.finalize().into())
+}
+
+fn addresses_of(script_pub_key: &LightScriptPubKey) -> impl Iterator<Item = &str> {
+  script_pub_key.address.iter().chain(script_pub_key.addresses.iter().flatten()).map(|address| &**address)
+}
+
+/// One output paid to (or input spending from) a script by a mempool entry.
+#[derive(Clone, Copy, Debug)]
+pub struct ScriptEntry {
+  pub handle: u32,
+  pub value_sat: u64,
+  pub spent: bool,
+}
+
+#[derive(Default)]
+struct ScriptBucket {
+  entries: Vec<ScriptEntry>,
+  addresses: Vec<Box<str>>,
+}
+
+/// Script / address index over loaded mempool transactions.
+///
+/// `owned` lists the scripts each handle contributed to, so removing a handle
+/// touches only its own buckets. Empty buckets are dropped together with
+/// their address aliases.
+#[derive(Default)]
+pub struct ScriptIndex {
+  buckets: HashMap<ScriptKey, ScriptBucket>,
+  by_address: HashMap<Box<str>, ScriptKey>,
+  owned: HashMap<u32, Vec<ScriptKey>>,
+}
+
+impl ScriptIndex {
+  pub fn clear(&mut self) {
+    self.buckets.clear();
+    self.by_address.clear();
+    self.owned.clear();
+  }
+
+  pub fn shrink_to_fit(&mut self) {
+    self.buckets.shrink_to_fit();
+    self.by_address.shrink_to_fit();
+    self.owned.shrink_to_fit();
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    let buckets: usize = self
+      .buckets
+      .values()
+      .map(|bucket| {
+        vec_bytes(&bucket.entries)
+          + bucket.addresses.len() * std::mem::size_of::<Box<str>>()
+          + bucket.addresses.iter().map(|address| address.len()).sum::<usize>()
+      })
+      .sum();
+    let aliases: usize = self.by_address.keys().map(|address| address.len()).sum();
+    let owned: usize = self.owned.values().map(vec_bytes).sum();
+    map_bytes(&self.buckets) + buckets + map_bytes(&self.by_address) + aliases + map_bytes(&self.owned) + owned
+  }
+
+  /// Records that `handle` pays `value_sat` to (or, with `spent`, spends
+  /// `value_sat` from) `script_pub_key`.
+  pub fn add(&mut self, handle: u32, script_pub_key: &LightScriptPubKey, value_sat: u64, spent: bool) {
+    let Some(key) = script_key(script_pub_key) else {
+      return;
+    };
+    let bucket = self.buckets.entry(key).or_default();
+    bucket.entries.push(ScriptEntry { handle, value_sat, spent });
+    for address in addresses_of(script_pub_key) {
+      if !bucket.addresses.iter().any(|known| &**known == address) {
+        bucket.addresses.push(address.into());
+        self.by_address.insert(address.into(), key);
+      }
+    }
+    let owned = self.owned.entry(handle).or_default();
+    if !owned.contains(&key) {
+      owned.push(key);
+    }
+  }
+
+  pub fn remove_handle(&mut self, handle: u32) {
+    let Some(keys) = self.owned.remove(&handle) else {
+      return;
+    };
+    for key in keys {
+      let Some(bucket) = self.buckets.get_mut(&key) else {
+        continue;
+      };
+      bucket.entries.retain(|entry| entry.handle != handle);
+      if bucket.entries.is_empty() {
+        for address in &bucket.addresses {
+          if self.by_address.get(address) == Some(&key) {
+            self.by_address.remove(address);
+          }
+        }
+        self.buckets.remove(&key);
+      }
+    }
+  }
+
+  pub fn entries(&self, key: &ScriptKey) -> &[ScriptEntry] {
+    self.buckets.get(key).map(|bucket| bucket.entries.as_slice()).unwrap_or(&[])
+  }
+
+  pub fn key_for_address(&self, address: &str) -> Option<ScriptKey> {
+    self.by_address.get(address).copied()
+  }
+}
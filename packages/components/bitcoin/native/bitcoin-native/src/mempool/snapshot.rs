@@ -16,6 +16,7 @@ pub fn ensure_snapshot_v2(state: &Value) -> Result<()> {
     || state.get("txids").is_some()
     || state.get("metadata").is_some()
     || state.get("transactions").is_some()
+    || state.get("rawTransactions").is_some()
     || state.get("loadTracker").is_some();
 
   if is_v2 || has_current_shape {
@@ -0,0 +1,621 @@
+use napi::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use super::types::{
+  ExtraFields, LightPrevout, LightScriptPubKey, LightTransaction, LightVin, LightVout, LoadInfo, MempoolFees,
+  MempoolTxMetadata, RawTransaction, ScriptType, StoredTransaction,
+};
+use crate::utils::TxKey;
+
+/// Binary mempool snapshot layout (all integers little-endian or LEB128):
+///
+/// ```text
+/// magic "EMPB" | format version u16 | flags u16
+/// ids:          count, count x 32 raw txid bytes
+/// providers:    count, names
+/// providerTx:   count, (name index, handle count, id indexes)
+/// metadata:     count, (id index, record)
+/// transactions: count, (id index, kind u8, typed record or raw bytes)
+/// loadTracker:  count, (id index, record)
+/// sha256(header + body)
+/// ```
+///
+/// Every txid of the store is written once in `ids`; records and txid
+/// references point into it by index. References to txids outside the table
+/// (confirmed prevouts, foreign `depends`) are written raw. Records end with
+/// their absent-field mask, where they have one, and their unmodeled fields
+/// as JSON.
+const MAGIC: &[u8; 4] = b"EMPB";
+pub const FORMAT_VERSION: u16 = 3;
+const HEADER_LEN: usize = 8;
+const CHECKSUM_LEN: usize = 32;
+
+const TYPED_RECORD: u8 = 0;
+const RAW_RECORD: u8 = 1;
+
+const SCRIPT_TYPES: [&str; 11] = [
+  "pubkey",
+  "pubkeyhash",
+  "scripthash",
+  "multisig",
+  "nulldata",
+  "witness_v0_keyhash",
+  "witness_v0_scripthash",
+  "witness_v1_taproot",
+  "witness_unknown",
+  "anchor",
+  "nonstandard",
+];
+
+/// Decoded snapshot. Record indexes refer to `ids`; load-info provider ids
+/// refer to `provider_names`.
+#[derive(Default)]
+pub struct BinarySnapshot {
+  pub ids: Vec<TxKey>,
+  pub provider_names: Vec<String>,
+  pub provider_tx: Vec<(u32, Vec<u32>)>,
+  pub metadata: Vec<(u32, MempoolTxMetadata)>,
+  pub transactions: Vec<(u32, StoredTransaction)>,
+  pub load_tracker: Vec<(u32, LoadInfo)>,
+}
+
+/// Streaming encoder; sections must be written in layout order.
+pub struct SnapshotEncoder {
+  out: Vec<u8>,
+  index_of: HashMap<TxKey, u32>,
+}
+
+impl SnapshotEncoder {
+  pub fn new(ids: &[TxKey]) -> Self {
+    let mut out = Vec::with_capacity(HEADER_LEN + ids.len() * 64);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    put_varint(&mut out, ids.len() as u64);
+    for key in ids {
+      out.extend_from_slice(&key.0);
+    }
+    let index_of = ids.iter().enumerate().map(|(i, key)| (*key, i as u32)).collect();
+    Self { out, index_of }
+  }
+
+  pub fn provider_names<'a>(&mut self, names: impl ExactSizeIterator<Item = &'a str>) {
+    put_varint(&mut self.out, names.len() as u64);
+    for name in names {
+      put_bytes(&mut self.out, name.as_bytes());
+    }
+  }
+
+  pub fn provider_tx(&mut self, lists: &[(u32, Vec<u32>)]) {
+    put_varint(&mut self.out, lists.len() as u64);
+    for (name, ids) in lists {
+      put_varint(&mut self.out, *name as u64);
+      put_varint(&mut self.out, ids.len() as u64);
+      for id in ids {
+        put_varint(&mut self.out, *id as u64);
+      }
+    }
+  }
+
+  pub fn metadata(&mut self, records: &[(u32, &MempoolTxMetadata)]) {
+    put_varint(&mut self.out, records.len() as u64);
+    for (id, md) in records {
+      put_varint(&mut self.out, *id as u64);
+      self.metadata_record(md);
+    }
+  }
+
+  pub fn transactions(&mut self, records: &[(u32, &StoredTransaction)]) {
+    put_varint(&mut self.out, records.len() as u64);
+    for (id, tx) in records {
+      put_varint(&mut self.out, *id as u64);
+      match tx {
+        StoredTransaction::Light(tx) => {
+          self.out.push(TYPED_RECORD);
+          self.transaction_record(tx);
+        }
+        StoredTransaction::Raw(raw) => {
+          self.out.push(RAW_RECORD);
+          put_bytes(&mut self.out, raw.bytes());
+        }
+      }
+    }
+  }
+
+  pub fn load_tracker(&mut self, records: &[(u32, LoadInfo)]) {
+    put_varint(&mut self.out, records.len() as u64);
+    for (id, load) in records {
+      put_varint(&mut self.out, *id as u64);
+      put_varint(&mut self.out, load.timestamp);
+      self.out.extend_from_slice(&load.fee_rate.to_le_bytes());
+      put_opt_u32(&mut self.out, load.provider.map(u32::from));
+    }
+  }
+
+  /// Appends the checksum and returns the encoded buffer.
+  pub fn finish(mut self) -> Vec<u8> {
+    let checksum = Sha256::digest(&self.out);
+    self.out.extend_from_slice(&checksum);
+    self.out
+  }
+
+  fn key(&mut self, key: Option<TxKey>) {
+    match key {
+      None => put_varint(&mut self.out, 0),
+      Some(key) => match self.index_of.get(&key) {
+        Some(id) => put_varint(&mut self.out, *id as u64 + 2),
+        None => {
+          put_varint(&mut self.out, 1);
+          self.out.extend_from_slice(&key.0);
+        }
+      },
+    }
+  }
+
+  fn metadata_record(&mut self, md: &MempoolTxMetadata) {
+    self.key(md.wtxid);
+    for value in [md.vsize as u64, md.weight as u64, md.fee] {
+      put_varint(&mut self.out, value);
+    }
+    put_signed(&mut self.out, md.modified_fee);
+    for value in [md.time, md.height as u64] {
+      put_varint(&mut self.out, value);
+    }
+    put_varint(&mut self.out, md.depends.len() as u64);
+    for key in md.depends.iter() {
+      self.key(Some(*key));
+    }
+    for value in [
+      md.descendant_count as u64,
+      md.descendant_size as u64,
+      md.descendant_fees,
+      md.ancestor_count as u64,
+      md.ancestor_size as u64,
+      md.ancestor_fees,
+      md.fees.base,
+    ] {
+      put_varint(&mut self.out, value);
+    }
+    put_signed(&mut self.out, md.fees.modified);
+    put_varint(&mut self.out, md.fees.ancestor);
+    put_varint(&mut self.out, md.fees.descendant);
+    self.out.push(md.fees.absent);
+    put_opt_bool(&mut self.out, Some(md.bip125_replaceable));
+    put_opt_bool(&mut self.out, md.unbroadcast);
+    put_varint(&mut self.out, md.absent as u64);
+    put_extra(&mut self.out, &md.extra);
+  }
+
+  fn transaction_record(&mut self, tx: &LightTransaction) {
+    self.key(tx.hash);
+    self.key(tx.wtxid);
+    put_signed(&mut self.out, tx.version as i64);
+    for value in [tx.size, tx.stripped_size, tx.vsize, tx.weight, tx.locktime] {
+      put_varint(&mut self.out, value as u64);
+    }
+    put_varint(&mut self.out, tx.vin.len() as u64);
+    for vin in tx.vin.iter() {
+      self.key(vin.txid);
+      put_opt_u32(&mut self.out, vin.vout);
+      put_opt_u32(&mut self.out, vin.sequence);
+      match &vin.prevout {
+        None => self.out.push(0),
+        Some(prevout) => {
+          self.out.push(1);
+          put_varint(&mut self.out, prevout.value_sat);
+          put_script(&mut self.out, prevout.script_pub_key.as_ref());
+          self.out.push(prevout.absent);
+          put_extra(&mut self.out, &prevout.extra);
+        }
+      }
+      put_extra(&mut self.out, &vin.extra);
+    }
+    put_varint(&mut self.out, tx.vout.len() as u64);
+    for vout in tx.vout.iter() {
+      put_varint(&mut self.out, vout.value_sat);
+      put_varint(&mut self.out, vout.n as u64);
+      put_script(&mut self.out, vout.script_pub_key.as_ref());
+      self.out.push(vout.absent);
+      put_extra(&mut self.out, &vout.extra);
+    }
+    match tx.fee_rate {
+      None => self.out.push(0),
+      Some(rate) => {
+        self.out.push(1);
+        self.out.extend_from_slice(&rate.to_le_bytes());
+      }
+    }
+    put_opt_bool(&mut self.out, tx.bip125_replaceable);
+    put_varint(&mut self.out, tx.absent as u64);
+    put_extra(&mut self.out, &tx.extra);
+  }
+}
+
+/// Verifies header and checksum, then decodes the whole buffer. Nothing is
+/// returned for a damaged buffer, so callers can decode before touching state.
+pub fn decode(buf: &[u8]) -> Result<BinarySnapshot> {
+  if buf.len() < HEADER_LEN + CHECKSUM_LEN || &buf[..4] != MAGIC {
+    return Err(Error::from_reason("Not a binary mempool snapshot"));
+  }
+  let version = u16::from_le_bytes([buf[4], buf[5]]);
+  if version != FORMAT_VERSION {
+    return Err(Error::from_reason(format!("Unsupported binary mempool snapshot version: {version}")));
+  }
+  let (content, checksum) = buf.split_at(buf.len() - CHECKSUM_LEN);
+  if Sha256::digest(content).as_slice() != checksum {
+    return Err(Error::from_reason("Binary mempool snapshot checksum mismatch"));
+  }
+
+  let mut r = Reader { buf: content, pos: HEADER_LEN, ids: Vec::new() };
+  let mut snapshot = BinarySnapshot::default();
+
+  let count = r.len()?;
+  for _ in 0..count {
+    let key = r.raw_key()?;
+    r.ids.push(key);
+  }
+  for _ in 0..r.len()? {
+    snapshot.provider_names.push(r.string()?);
+  }
+  for _ in 0..r.len()? {
+    let name = r.index()?;
+    let ids = (0..r.len()?).map(|_| r.index()).collect::<Result<Vec<_>>>()?;
+    snapshot.provider_tx.push((name, ids));
+  }
+  for _ in 0..r.len()? {
+    let id = r.index()?;
+    snapshot.metadata.push((id, r.metadata_record()?));
+  }
+  for _ in 0..r.len()? {
+    let id = r.index()?;
+    let tx = match r.u8()? {
+      TYPED_RECORD => r.transaction_record()?.into(),
+      RAW_RECORD => {
+        let key = *r.ids.get(id as usize).ok_or_else(|| Error::from_reason("Raw record references an unknown txid"))?;
+        RawTransaction::parse(key, r.bytes()?)?.0.into()
+      }
+      kind => return Err(Error::from_reason(format!("Unknown transaction record kind: {kind}"))),
+    };
+    snapshot.transactions.push((id, tx));
+  }
+  for _ in 0..r.len()? {
+    let id = r.index()?;
+    let load = LoadInfo { timestamp: r.varint()?, fee_rate: r.f64()?, provider: r.opt_u32()?.map(|id| id as u16) };
+    snapshot.load_tracker.push((id, load));
+  }
+  if r.pos != content.len() {
+    return Err(Error::from_reason("Trailing bytes in binary mempool snapshot"));
+  }
+
+  snapshot.ids = r.ids;
+  Ok(snapshot)
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+  while value >= 0x80 {
+    out.push((value as u8) | 0x80);
+    value >>= 7;
+  }
+  out.push(value as u8);
+}
+
+fn put_signed(out: &mut Vec<u8>, value: i64) {
+  put_varint(out, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+  put_varint(out, bytes.len() as u64);
+  out.extend_from_slice(bytes);
+}
+
+fn put_opt_bytes(out: &mut Vec<u8>, bytes: Option<&[u8]>) {
+  match bytes {
+    None => put_varint(out, 0),
+    Some(bytes) => {
+      put_varint(out, bytes.len() as u64 + 1);
+      out.extend_from_slice(bytes);
+    }
+  }
+}
+
+fn put_opt_u32(out: &mut Vec<u8>, value: Option<u32>) {
+  put_varint(out, value.map(|v| v as u64 + 1).unwrap_or(0));
+}
+
+fn put_opt_bool(out: &mut Vec<u8>, value: Option<bool>) {
+  out.push(match value {
+    None => 0,
+    Some(false) => 1,
+    Some(true) => 2,
+  });
+}
+
+fn put_script(out: &mut Vec<u8>, script_pub_key: Option<&LightScriptPubKey>) {
+  let Some(spk) = script_pub_key else {
+    out.push(0);
+    return;
+  };
+  out.push(1);
+  match &spk.script_type {
+    None => put_varint(out, 0),
+    Some(ScriptType::Other(name)) => {
+      put_varint(out, SCRIPT_TYPES.len() as u64 + 1);
+      put_bytes(out, name.as_bytes());
+    }
+    Some(known) => {
+      let code = SCRIPT_TYPES.iter().position(|name| *name == known.as_str()).unwrap_or(0);
+      put_varint(out, code as u64 + 1);
+    }
+  }
+  put_opt_bytes(out, spk.address.as_deref().map(str::as_bytes));
+  match &spk.addresses {
+    None => put_varint(out, 0),
+    Some(addresses) => {
+      put_varint(out, addresses.len() as u64 + 1);
+      for address in addresses.iter() {
+        put_bytes(out, address.as_bytes());
+      }
+    }
+  }
+  put_opt_bytes(out, spk.script.as_deref());
+  put_extra(out, &spk.extra);
+}
+
+fn put_extra(out: &mut Vec<u8>, extra: &ExtraFields) {
+  put_opt_bytes(out, extra.as_ref().and_then(|extra| serde_json::to_vec(extra).ok()).as_deref());
+}
+
+struct Reader<'a> {
+  buf: &'a [u8],
+  pos: usize,
+  ids: Vec<TxKey>,
+}
+
+fn truncated() -> Error {
+  Error::from_reason("Truncated binary mempool snapshot")
+}
+
+impl Reader<'_> {
+  fn take(&mut self, len: usize) -> Result<&[u8]> {
+    let end = self.pos.checked_add(len).filter(|end| *end <= self.buf.len()).ok_or_else(truncated)?;
+    let bytes = &self.buf[self.pos..end];
+    self.pos = end;
+    Ok(bytes)
+  }
+
+  fn u8(&mut self) -> Result<u8> {
+    Ok(self.take(1)?[0])
+  }
+
+  fn varint(&mut self) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+      let byte = self.u8()?;
+      value |= ((byte & 0x7f) as u64) << shift;
+      if byte & 0x80 == 0 {
+        return Ok(value);
+      }
+    }
+    Err(Error::from_reason("Invalid varint in binary mempool snapshot"))
+  }
+
+  fn u32(&mut self) -> Result<u32> {
+    u32::try_from(self.varint()?).map_err(|_| Error::from_reason("Integer overflow in binary mempool snapshot"))
+  }
+
+  fn index(&mut self) -> Result<u32> {
+    self.u32()
+  }
+
+  /// Element count, bounded by the remaining bytes so a corrupt length cannot
+  /// trigger a huge allocation.
+  fn len(&mut self) -> Result<usize> {
+    let len = self.varint()? as usize;
+    if len > self.buf.len() - self.pos {
+      return Err(truncated());
+    }
+    Ok(len)
+  }
+
+  fn signed(&mut self) -> Result<i64> {
+    let raw = self.varint()?;
+    Ok(((raw >> 1) as i64) ^ -((raw & 1) as i64))
+  }
+
+  fn f64(&mut self) -> Result<f64> {
+    let bytes = self.take(8)?;
+    Ok(f64::from_le_bytes(bytes.try_into().map_err(|_| truncated())?))
+  }
+
+  fn raw_key(&mut self) -> Result<TxKey> {
+    self.take(32)?.try_into().map(TxKey).map_err(|_| truncated())
+  }
+
+  fn key(&mut self) -> Result<Option<TxKey>> {
+    match self.varint()? {
+      0 => Ok(None),
+      1 => self.raw_key().map(Some),
+      id => self
+        .ids
+        .get(id as usize - 2)
+        .copied()
+        .map(Some)
+        .ok_or_else(|| Error::from_reason("Invalid txid reference in binary mempool snapshot")),
+    }
+  }
+
+  fn bytes(&mut self) -> Result<&[u8]> {
+    let len = self.len()?;
+    self.take(len)
+  }
+
+  fn string(&mut self) -> Result<String> {
+    String::from_utf8(self.bytes()?.to_vec())
+      .map_err(|_| Error::from_reason("Invalid UTF-8 in binary mempool snapshot"))
+  }
+
+  fn opt_bytes(&mut self) -> Result<Option<&[u8]>> {
+    match self.varint()? {
+      0 => Ok(None),
+      len => self.take(len as usize - 1).map(Some),
+    }
+  }
+
+  fn opt_str(&mut self) -> Result<Option<Box<str>>> {
+    self
+      .opt_bytes()?
+      .map(|bytes| std::str::from_utf8(bytes).map(Into::into))
+      .transpose()
+      .map_err(|_| Error::from_reason("Invalid UTF-8 in binary mempool snapshot"))
+  }
+
+  fn opt_u32(&mut self) -> Result<Option<u32>> {
+    match self.varint()? {
+      0 => Ok(None),
+      value => u32::try_from(value - 1)
+        .map(Some)
+        .map_err(|_| Error::from_reason("Integer overflow in binary mempool snapshot")),
+    }
+  }
+
+  fn opt_bool(&mut self) -> Result<Option<bool>> {
+    match self.u8()? {
+      0 => Ok(None),
+      1 => Ok(Some(false)),
+      _ => Ok(Some(true)),
+    }
+  }
+
+  fn u16(&mut self) -> Result<u16> {
+    u16::try_from(self.varint()?).map_err(|_| Error::from_reason("Integer overflow in binary mempool snapshot"))
+  }
+
+  fn extra(&mut self) -> Result<ExtraFields> {
+    self
+      .opt_bytes()?
+      .map(|json| serde_json::from_slice(json).map(Box::new))
+      .transpose()
+      .map_err(|_| Error::from_reason("Invalid extra fields in binary mempool snapshot"))
+  }
+
+  fn script(&mut self) -> Result<Option<LightScriptPubKey>> {
+    if self.u8()? == 0 {
+      return Ok(None);
+    }
+    let script_type = match self.varint()? as usize {
+      0 => None,
+      code if code == SCRIPT_TYPES.len() + 1 => Some(ScriptType::Other(self.string()?.into())),
+      code => Some(ScriptType::parse(SCRIPT_TYPES.get(code - 1).copied().unwrap_or("nonstandard"))),
+    };
+    let address = self.opt_str()?;
+    let addresses = match self.varint()? as usize {
+      0 => None,
+      count => Some(
+        (1..count).map(|_| self.string().map(String::into_boxed_str)).collect::<Result<Vec<_>>>()?.into_boxed_slice(),
+      ),
+    };
+    let script = self.opt_bytes()?.map(Box::from);
+    Ok(Some(LightScriptPubKey { script_type, address, addresses, script, extra: self.extra()? }))
+  }
+
+  fn metadata_record(&mut self) -> Result<MempoolTxMetadata> {
+    let wtxid = self.key()?;
+    let vsize = self.u32()?;
+    let weight = self.u32()?;
+    let fee = self.varint()?;
+    let modified_fee = self.signed()?;
+    let time = self.varint()?;
+    let height = self.u32()?;
+    let depends =
+      (0..self.len()?).map(|_| self.key()?.ok_or_else(truncated)).collect::<Result<Vec<_>>>()?.into_boxed_slice();
+    Ok(MempoolTxMetadata {
+      wtxid,
+      vsize,
+      weight,
+      fee,
+      modified_fee,
+      time,
+      height,
+      depends,
+      descendant_count: self.u32()?,
+      descendant_size: self.u32()?,
+      descendant_fees: self.varint()?,
+      ancestor_count: self.u32()?,
+      ancestor_size: self.u32()?,
+      ancestor_fees: self.varint()?,
+      fees: MempoolFees {
+        base: self.varint()?,
+        modified: self.signed()?,
+        ancestor: self.varint()?,
+        descendant: self.varint()?,
+        absent: self.u8()?,
+      },
+      bip125_replaceable: self.opt_bool()?.unwrap_or(false),
+      unbroadcast: self.opt_bool()?,
+      absent: self.u16()?,
+      extra: self.extra()?,
+    })
+  }
+
+  fn transaction_record(&mut self) -> Result<LightTransaction> {
+    let hash = self.key()?;
+    let wtxid = self.key()?;
+    let version = self.signed()? as i32;
+    let size = self.u32()?;
+    let stripped_size = self.u32()?;
+    let vsize = self.u32()?;
+    let weight = self.u32()?;
+    let locktime = self.u32()?;
+    let vin = (0..self.len()?)
+      .map(|_| {
+        let txid = self.key()?;
+        let vout = self.opt_u32()?;
+        let sequence = self.opt_u32()?;
+        let prevout = match self.u8()? {
+          0 => None,
+          _ => Some(Box::new(LightPrevout {
+            value_sat: self.varint()?,
+            script_pub_key: self.script()?,
+            absent: self.u8()?,
+            extra: self.extra()?,
+          })),
+        };
+        Ok(LightVin { txid, vout, sequence, prevout, extra: self.extra()? })
+      })
+      .collect::<Result<Vec<_>>>()?
+      .into_boxed_slice();
+    let vout = (0..self.len()?)
+      .map(|_| {
+        Ok(LightVout {
+          value_sat: self.varint()?,
+          n: self.u32()?,
+          script_pub_key: self.script()?,
+          absent: self.u8()?,
+          extra: self.extra()?,
+        })
+      })
+      .collect::<Result<Vec<_>>>()?
+      .into_boxed_slice();
+    let fee_rate = match self.u8()? {
+      0 => None,
+      _ => Some(self.f64()?),
+    };
+    Ok(LightTransaction {
+      hash,
+      wtxid,
+      version,
+      size,
+      stripped_size,
+      vsize,
+      weight,
+      locktime,
+      vin,
+      vout,
+      fee_rate,
+      bip125_replaceable: self.opt_bool()?,
+      absent: self.u16()?,
+      extra: self.extra()?,
+    })
+  }
+}
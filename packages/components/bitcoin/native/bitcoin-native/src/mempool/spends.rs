@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use crate::utils::memory::map_bytes;
+use crate::utils::{parse_txid, txid_to_hex, TxKey};
+
+/// Previous output reference: funding txid and output index.
+pub type Outpoint = (TxKey, u32);
+
+/// Parses the `txid:vout` notation used at the napi boundary.
+pub fn parse_outpoint(value: &str) -> Option<Outpoint> {
+  let (txid, vout) = value.split_once(':')?;
+  Some((parse_txid(txid)?, vout.parse().ok()?))
+}
+
+pub fn outpoint_to_string((txid, vout): Outpoint) -> String {
+  format!("{}:{}", txid_to_hex(txid), vout)
+}
+
+/// Maps every outpoint spent by a loaded mempool transaction to its spender.
+///
+/// Two live entries spending the same outpoint are a double spend; the store
+/// keeps only the most recently loaded spender, so the index holds exactly one
+/// handle per outpoint.
+#[derive(Default)]
+pub struct SpendIndex {
+  spenders: HashMap<Outpoint, u32>,
+}
+
+impl SpendIndex {
+  pub fn clear(&mut self) {
+    self.spenders.clear();
+  }
+
+  pub fn shrink_to_fit(&mut self) {
+    self.spenders.shrink_to_fit();
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    map_bytes(&self.spenders)
+  }
+
+  pub fn spender(&self, outpoint: &Outpoint) -> Option<u32> {
+    self.spenders.get(outpoint).copied()
+  }
+
+  pub fn insert(&mut self, outpoint: Outpoint, handle: u32) {
+    self.spenders.insert(outpoint, handle);
+  }
+
+  /// Unindexes `outpoint` only if `handle` is still its recorded spender.
+  pub fn remove(&mut self, outpoint: &Outpoint, handle: u32) {
+    if self.spenders.get(outpoint) == Some(&handle) {
+      self.spenders.remove(outpoint);
+    }
+  }
+}
@@ -1,11 +1,36 @@
-use napi::Result;
+use napi::bindgen_prelude::Buffer;
+use napi::{Error, Result};
 use napi_derive::napi;
-use serde_json::{json, Map, Value};
+use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
 
-use crate::utils::{now_ms, number_field, parse_txid, string_field, txid_to_hex, TxKey};
+use crate::utils::memory::{heap_usage, map_bytes, set_bytes, vec_bytes};
+use crate::utils::{now_ms, parse_txid, string_field, txid_to_hex, TxKey};
 
+use super::compaction::{CompactionPolicy, CompactionStats};
+use super::coverage::ProviderCoverage;
+use super::cursor::{scan_budget, CursorEpochs, CursorError, PageKind, MAX_PAGE_SIZE};
+use super::diff::{MetadataChange, MutationDiff};
+use super::fee_estimator::{combine_estimates, ConfirmationHistory, EstimateMode, MIN_FEE_RATE};
+use super::fee_index::FeeRateIndex;
+use super::graph::TxGraph;
+use super::handle_list::HandleList;
+use super::journal::ChangeJournal;
+use super::limits::{evictions_value, EvictionReason, MempoolLimits, Usage};
+use super::pending::{PendingIndex, PendingOrder};
+use super::projection::{project_blocks, PackageNode};
+use super::query::{MempoolQuery, QuerySort};
+use super::retry::{LoadFailures, RetryPolicy};
+use super::scripts::{script_key_from_hex, ScriptIndex, ScriptKey};
 use super::snapshot::{empty_snapshot, ensure_snapshot_v2};
+use super::snapshot_binary::{decode as decode_binary_snapshot, SnapshotEncoder};
+use super::spends::{outpoint_to_string, parse_outpoint, Outpoint, SpendIndex};
+use super::types::{
+  LightScriptPubKey, LightTransaction, LoadInfo, MempoolTxMetadata, ProviderId, ProviderNames, RawTransaction,
+  StoredTransaction,
+};
+use super::variants::{MetadataVariants, ReconcilePolicy};
+use super::watchlist::{DropReason, WatchHit, Watchlist};
 
 fn convert_units(units: Option<String>) -> (&'static str, f64) {
   match units.as_deref().unwrap_or("MB") {
@@ -30,11 +55,53 @@ fn convert_units(units: Option<String>) -> (&'static str, f64) {
 ///   numeric handle. The full txid is still checked by the hash map, so this
 ///   avoids the old `hashTxid32` collision problem while keeping the rest of the
 ///   store handle-based.
-/// - `provider_tx: HashMap<String, Vec<u32>>` stores provider membership as
-///   compact handles instead of repeated txid strings.
-/// - `metadata`, `transactions` and `load_tracker` are keyed by handle. Their
-///   payloads intentionally remain `serde_json::Value` for this migration step,
-///   because the aggregate API still exchanges JS objects.
+/// - `provider_tx: HashMap<String, HandleList>` stores provider membership as
+///   compact handles instead of repeated txid strings; each list carries a
+///   handle set so membership checks are O(1).
+/// - `metadata`, `transactions` and `load_tracker` are keyed by handle and hold
+///   typed records (`MempoolTxMetadata`, `LightTransaction`, `LoadInfo`) with
+///   compact numeric fields and raw 32-byte txid references. They are converted
+///   to JS objects only at the N-API boundary, so the JS-facing contracts of
+///   `applySnapshot`, `recordLoaded` and `exportSnapshot` are unchanged.
+///   Transactions from `recordLoadedRaw` are kept as their serialized bytes
+///   and decoded when read (`StoredTransaction`).
+/// - `load_failures` counts failed load attempts per handle and provider;
+///   `pendingTxids` skips txids in backoff and hands txids a provider keeps
+///   failing on to another provider that lists them (see `retry`).
+///   `fallback_tx` keeps, per provider, the handles a snapshot deduplicated
+///   away from it because an earlier provider already listed them.
+/// - `coverage` keeps the first time every provider reported each txid, for
+///   the coverage and propagation analytics (`getProviderCoverage`,
+///   `getPropagationDelays`). It is observational and not part of snapshots.
+/// - `variants` optionally keeps each provider's metadata report per handle;
+///   its `ReconcilePolicy` then decides which report `metadata` holds.
+/// - `pending` queues each provider's unloaded handles by the configured
+///   `PendingOrder` so `pendingTxids` takes the first `limit` entries without
+///   sorting the provider list. It is empty for the default insertion order.
+/// - `removed_handles` tombstones removed slots; `compact()` renumbers live
+///   handles and drops them (see `compaction::CompactionPolicy`).
+/// - `provider_names` interns provider names so load records carry a 2-byte id.
+/// - `fee_index` keeps every live handle ordered by fee rate (metadata fee rate,
+///   or the loaded transaction's `feeRate` when no metadata is known). It is
+///   maintained by every mutation so top-N, range and histogram queries never
+///   need to materialize the mempool in JS.
+/// - `graph` links loaded transactions to in-mempool parents referenced by
+///   their `vin` prevouts. References to txids the store does not know yet
+///   are parked and linked when that txid receives a handle.
+/// - `spends` maps each outpoint spent by a loaded transaction to its spender
+///   and drives double-spend detection; `scripts` indexes outputs received and
+///   inputs spent per scriptPubKey for address/script queries.
+/// - `diff` collects added/removed/re-described/loaded txids while a mutation
+///   called with `reportDiff` runs; it is `None` otherwise.
+/// - `journal` records the sequence of the latest change per txid (plus a
+///   bounded tail of removals) for `exportDelta` / `applyDelta`.
+/// - `limits` bounds entry count, vbytes and estimated bytes and sets a TTL on
+///   first-seen time; mutations that add entries evict the lowest
+///   descendant-fee-rate packages when a bound is exceeded.
+/// - `confirmations` is a bounded rolling history of (fee rate, mempool wait)
+///   samples recorded when confirmed txids are removed; it feeds
+///   `estimateFeeRate` together with `projectBlocks`. It survives snapshot
+///   applies and imports and is dropped only by `clear()` and `dispose()`.
 ///
 /// Algorithmic complexity
 /// ----------------------
@@ -49,44 +116,100 @@ fn convert_units(units: Option<String>) -> (&'static str, f64) {
 /// - `record_loaded`: O(M) for M loaded transactions.
 /// - point lookups (`hasTransaction`, `getMetadata`, `getFullTransaction`):
 ///   average O(1).
+/// - fee-rate index maintenance: O(log N) per inserted/updated/removed entry;
+///   `topByFeeRate(n)` is O(n), `feeRateRange` is O(log N + k) for k results and
+///   `feeHistogram` is O(B log N + N) for B buckets.
+/// - dependency graph: O(I) per loaded transaction with I inputs;
+///   `getAncestors`/`getDescendants`/`getPackage` walk only the related txs.
+/// - spend and script indexes: O(I + O) per loaded transaction; outpoint and
+///   script/address lookups are average O(1) plus the returned entries.
+/// - `projectBlocks(n)`: O(N log N) ancestor-score package selection over the
+///   metadata entries; see `projection::project_blocks`.
 /// - snapshot export/import: O(T + R), where T is known txid count and R is the
 ///   number of stored provider/metadata/transaction/load records.
+/// - limit enforcement: O(N) per adding mutation while a bound or TTL is set,
+///   plus O(N log N + N·D) for D average package size when a bound is
+///   exceeded.
+/// - change journal: O(log C) per mutated txid; `exportDelta` is
+///   O(D log C + P) for D changed txids and P provider list entries.
 ///
 /// Memory model
 /// ------------
 /// This store is optimized to reduce duplicated strings and JS collection
-/// overhead, not to be a fully typed mempool yet. Approximate native index cost:
+/// overhead. Approximate native index cost:
 /// - canonical txid storage: 32 bytes per txid plus `Vec` capacity overhead;
 /// - txid hash index: roughly tens of bytes per txid, depending on hash-map
 ///   capacity and allocator behavior;
-/// - provider membership: 4 bytes per handle plus `Vec` capacity overhead,
-///   instead of storing another txid string per provider reference;
-/// - load tracker: small JSON object per loaded transaction;
-/// - metadata and loaded transactions: variable-size JSON DOM values and usually
-///   the dominant part until those payloads are moved to typed Rust structs.
+/// - provider membership: 4 bytes per handle plus `Vec` capacity overhead and
+///   one membership-set slot, instead of storing another txid string per
+///   provider reference;
+/// - load tracker: fixed-size `LoadInfo` per loaded transaction;
+/// - metadata: fixed-size struct plus 32 bytes per `depends` entry;
+/// - loaded transactions: fixed-size struct plus per-input/per-output records
+///   and raw script bytes; still the dominant part of a fully loaded mempool;
+/// - spend index: ~40 bytes per spent outpoint; script index: one 16-byte entry
+///   per indexed output/input plus one 32-byte key per distinct script.
 ///
-/// `getMemoryUsage()` returns a stable heuristic useful for comparing runs. It
-/// is not an exact allocator/heap measurement.
+/// `getMemoryUsage()` reports these per component from real container
+/// capacities and record sizes; builds with the `counting-allocator` feature
+/// also report the addon's process-wide heap.
 #[derive(Default)]
 struct MempoolBackingStore {
   txid_to_handle: HashMap<TxKey, u32>,
   txids: Vec<TxKey>,
-  provider_tx: HashMap<String, Vec<u32>>,
-  metadata: HashMap<u32, Value>,
-  transactions: HashMap<u32, Value>,
-  load_tracker: HashMap<u32, Value>,
+  provider_tx: HashMap<String, HandleList>,
+  fallback_tx: HashMap<String, HandleList>,
+  pending: PendingIndex,
+  coverage: ProviderCoverage,
+  variants: MetadataVariants,
+  metadata: HashMap<u32, MempoolTxMetadata>,
+  transactions: HashMap<u32, StoredTransaction>,
+  load_tracker: HashMap<u32, LoadInfo>,
+  load_failures: HashMap<u32, LoadFailures>,
+  retry_policy: RetryPolicy,
   removed_handles: HashSet<u32>,
+  provider_names: ProviderNames,
+  fee_index: FeeRateIndex,
+  confirmations: ConfirmationHistory,
+  graph: TxGraph,
+  spends: SpendIndex,
+  scripts: ScriptIndex,
+  watchlist: Watchlist,
+  compaction: CompactionPolicy,
+  limits: MempoolLimits,
+  journal: ChangeJournal,
+  cursors: CursorEpochs,
+  diff: Option<MutationDiff>,
 }
 
 impl MempoolBackingStore {
   fn clear(&mut self) {
+    self.cursors.replaced();
     self.txid_to_handle.clear();
     self.txids.clear();
     self.provider_tx.clear();
+    self.fallback_tx.clear();
+    self.pending.clear();
+    self.coverage.clear();
+    self.variants.clear();
     self.metadata.clear();
     self.transactions.clear();
     self.load_tracker.clear();
+    self.load_failures.clear();
     self.removed_handles.clear();
+    self.fee_index.clear();
+    self.graph.clear();
+    self.spends.clear();
+    self.scripts.clear();
+    self.journal.reset();
+  }
+
+  /// `clear()` plus the confirmation history. Snapshot applies and imports
+  /// only replace the current contents, so they keep the history of past
+  /// confirmations that `estimateFeeRate` learns from.
+  fn reset(&mut self) {
+    self.clear();
+    self.confirmations.clear();
   }
 
   /// Clears and shrinks all native containers.
@@ -96,14 +219,27 @@ impl MempoolBackingStore {
   /// torn down and memory should be returned to the allocator as eagerly as
   /// Rust allows.
   fn dispose(&mut self) {
-    self.clear();
+    self.reset();
+    self.watchlist.clear();
     self.txid_to_handle.shrink_to_fit();
     self.txids.shrink_to_fit();
     self.provider_tx.shrink_to_fit();
+    self.fallback_tx.shrink_to_fit();
+    self.pending.shrink_to_fit();
+    self.coverage.shrink_to_fit();
+    self.variants.shrink_to_fit();
     self.metadata.shrink_to_fit();
     self.transactions.shrink_to_fit();
     self.load_tracker.shrink_to_fit();
+    self.load_failures.shrink_to_fit();
     self.removed_handles.shrink_to_fit();
+    self.provider_names.clear();
+    self.fee_index.shrink_to_fit();
+    self.confirmations.shrink_to_fit();
+    self.graph.shrink_to_fit();
+    self.spends.shrink_to_fit();
+    self.scripts.shrink_to_fit();
+    self.journal.shrink_to_fit();
   }
 
   /// Returns the compact handle for `key`, inserting it once if needed.
@@ -119,671 +255,4063 @@ impl MempoolBackingStore {
     let handle = self.txids.len() as u32;
     self.txid_to_handle.insert(key, handle);
     self.txids.push(key);
+    self.graph.resolve_waiting(key, handle);
+    self.journal.added(key);
+    if let Some(diff) = &mut self.diff {
+      diff.added.push(key);
+    }
     handle
   }
 
+  /// `ensure_handle` that reports a watched txid entering the store.
+  fn ensure_handle_watched(&mut self, key: TxKey, hits: &mut Vec<WatchHit>) -> u32 {
+    if !self.txid_to_handle.contains_key(&key) && self.watchlist.watches_txid(&key) {
+      hits.push(WatchHit::TxSeen { txid: key });
+    }
+    self.ensure_handle(key)
+  }
+
+  /// Drops `handle` and reports it when its txid is watched; `reason` is
+  /// `None` for confirmations.
+  fn drop_handle_watched(&mut self, handle: u32, reason: Option<DropReason>, hits: &mut Vec<WatchHit>) -> bool {
+    if !self.drop_handle(handle) {
+      return false;
+    }
+    if let Some(txid) = self.txid_of(handle).filter(|txid| self.watchlist.watches_txid(txid)) {
+      hits.push(match reason {
+        Some(reason) => WatchHit::TxDropped { txid, reason },
+        None => WatchHit::TxConfirmed { txid },
+      });
+    }
+    true
+  }
+
   fn handle_of_txid(&self, txid: &str) -> Option<u32> {
     parse_txid(txid).and_then(|key| self.txid_to_handle.get(&key).copied())
   }
 
-  fn import_pair(&mut self, entry: &Value, target: PairTarget) {
-    let Value::Array(pair) = entry else {
-      return;
-    };
+  fn txid_of(&self, handle: u32) -> Option<TxKey> {
+    self.txids.get(handle as usize).copied()
+  }
 
-    if pair.len() != 2 {
-      return;
+  /// Stores metadata for `handle` and re-keys it in the fee-rate index.
+  fn insert_metadata(&mut self, handle: u32, metadata: MempoolTxMetadata) {
+    if let (Some(diff), Some(old), Some(txid)) =
+      (&mut self.diff, self.metadata.get(&handle), self.txids.get(handle as usize))
+    {
+      if *old != metadata {
+        diff.metadata_changed.push(MetadataChange {
+          txid: *txid,
+          old_fee: old.fee_sats(),
+          new_fee: metadata.fee_sats(),
+        });
+      }
+    }
+    self.metadata.insert(handle, metadata);
+    self.reindex_fee_rate(handle);
+    if let Some(key) = self.pending_key(handle).filter(|_| self.pending.contains(handle)) {
+      self.pending.rekey(handle, key);
     }
+    self.journal_update(handle);
+  }
 
-    let Some(txid) = pair[0].as_str() else {
-      return;
-    };
+  /// Stores a loaded transaction and its load info for `handle`.
+  fn insert_loaded(&mut self, handle: u32, tx: StoredTransaction, load: LoadInfo) {
+    self.insert_transaction(handle, tx);
+    self.load_tracker.insert(handle, load);
+    self.load_failures.remove(&handle);
+    self.pending.remove(handle);
+    self.reindex_fee_rate(handle);
+    self.journal_update(handle);
+    if let (Some(diff), Some(txid)) = (&mut self.diff, self.txids.get(handle as usize)) {
+      diff.loaded.push(*txid);
+    }
+  }
 
-    let Some(key) = parse_txid(txid) else {
-      return;
-    };
+  fn journal_update(&mut self, handle: u32) {
+    if let Some(key) = self.txid_of(handle) {
+      self.journal.updated(key);
+    }
+  }
 
-    let handle = self.ensure_handle(key);
+  /// Stores a loaded transaction, links it to its in-mempool parents and
+  /// indexes the outpoints it spends.
+  fn insert_transaction(&mut self, handle: u32, tx: StoredTransaction) {
+    self.transactions.insert(handle, tx);
+    self.index_transaction(handle);
+    self.index_late_funding(handle);
+  }
 
-    match target {
-      PairTarget::Metadata => {
-        self.metadata.insert(handle, pair[1].clone());
+  fn index_transaction(&mut self, handle: u32) {
+    let Some(tx) = self.transactions.get(&handle) else {
+      return;
+    };
+    for (key, _, _) in tx.inputs() {
+      match self.txid_to_handle.get(&key) {
+        Some(parent) => self.graph.link(*parent, handle),
+        None => self.graph.wait_for(key, handle),
       }
-      PairTarget::Transaction => {
-        self.transactions.insert(handle, pair[1].clone());
+    }
+    for outpoint in tx.spent_outpoints() {
+      self.spends.insert(outpoint, handle);
+    }
+    for (_, script_pub_key, value_sat) in tx.outputs() {
+      self.scripts.add(handle, &script_pub_key, value_sat, false);
+    }
+    for (txid, vout, prevout) in tx.inputs() {
+      // Prefer the provider's `prevout`; otherwise the funding output is known
+      // only if the parent is a loaded mempool transaction.
+      let funding = match prevout {
+        Some(prevout) => prevout.script_pub_key.clone().map(|script| (script, prevout.value_sat)),
+        None => funding_output(&self.transactions, &self.txid_to_handle, txid, vout),
+      };
+      if let Some((script_pub_key, value_sat)) = funding {
+        self.scripts.add(handle, &script_pub_key, value_sat, true);
       }
-      PairTarget::LoadTracker => {
-        self.load_tracker.insert(handle, pair[1].clone());
+    }
+  }
+
+  /// Attributes spends of `handle`'s outputs by children that were loaded
+  /// before it and therefore could not resolve the spent script.
+  fn index_late_funding(&mut self, handle: u32) {
+    let (Some(key), Some(tx)) = (self.txid_of(handle), self.transactions.get(&handle)) else {
+      return;
+    };
+    for (n, script_pub_key, value_sat) in tx.outputs() {
+      let Some(spender) = self.spends.spender(&(key, n)) else {
+        continue;
+      };
+      let has_prevout = self
+        .transactions
+        .get(&spender)
+        .is_some_and(|child| child.inputs().any(|(txid, vout, prevout)| txid == key && vout == n && prevout.is_some()));
+      if !has_prevout {
+        self.scripts.add(spender, &script_pub_key, value_sat, true);
       }
     }
   }
-}
 
-#[derive(Clone, Copy)]
-enum PairTarget {
-  Metadata,
-  Transaction,
-  LoadTracker,
-}
+  /// Live entries other than `except` that spend any of `outpoints`.
+  fn conflicting_spenders(
+    &self,
+    outpoints: impl Iterator<Item = Outpoint>,
+    except: Option<u32>,
+  ) -> Vec<(Outpoint, u32)> {
+    outpoints
+      .filter_map(|outpoint| self.spends.spender(&outpoint).map(|spender| (outpoint, spender)))
+      .filter(|(_, spender)| Some(*spender) != except)
+      .collect()
+  }
 
-/// N-API wrapper exposed to JS as `NativeMempoolState` for compatibility with
-/// the TypeScript native registry. It delegates to `MempoolBackingStore` and
-/// must be used only as a private state/index engine inside `MempoolStateStore`.
-#[napi]
-pub struct NativeMempoolState {
-  store: MempoolBackingStore,
-}
+  /// Removes `handle` from every index and tombstones it. Returns `false` when
+  /// the handle was already gone. Provider lists are left for
+  /// `prune_provider_lists`, which callers run once per batch.
+  fn drop_handle(&mut self, handle: u32) -> bool {
+    let Some(key) = self.txid_of(handle) else {
+      return false;
+    };
+    if self.txid_to_handle.remove(&key).is_none() {
+      return false;
+    }
+    self.remove_handle_records(handle);
+    self.removed_handles.insert(handle);
+    self.journal.removed(key);
+    if let Some(diff) = &mut self.diff {
+      diff.removed.push(key);
+    }
+    true
+  }
 
-#[napi]
-impl NativeMempoolState {
-  #[napi(constructor)]
-  pub fn new() -> Self {
-    Self {
-      store: MempoolBackingStore::default(),
+  fn prune_provider_lists(&mut self) {
+    let removed = &self.removed_handles;
+    for handles in self.provider_tx.values_mut().chain(self.fallback_tx.values_mut()) {
+      handles.retain(|candidate| !removed.contains(candidate));
     }
+    self.provider_tx.retain(|_, handles| !handles.is_empty());
+    self.fallback_tx.retain(|_, handles| !handles.is_empty());
   }
 
-  /// Applies a provider snapshot prepared by the TS aggregate/event handler.
-  /// Existing loaded transactions/load info are preserved for txids that remain
-  /// present in the new snapshot.
+  fn maybe_compact(&mut self) -> Option<CompactionStats> {
+    self.compaction.should_compact(self.removed_handles.len(), self.txids.len()).then(|| self.compact())
+  }
+
+  /// Renumbers live handles densely and drops every tombstone.
   ///
-  /// Algorithm:
-  /// 1. Build temporary maps from old handles back to full txids for loaded
-  ///    transactions and load-tracker records.
-  /// 2. Clear all indexes that describe the current mempool snapshot.
-  /// 3. Rebuild txid handles, provider membership and metadata from the new
-  ///    snapshot, deduplicating txids globally across providers.
-  /// 4. Restore loaded transaction/load records only when their txid is still
-  ///    present in the refreshed mempool.
+  /// The handle-keyed record maps and provider lists are rewritten through an
+  /// old-to-new remap table; derived indexes are rebuilt from the records.
+  /// Snapshot exports and txid-based queries are unaffected.
   ///
-  /// Complexity: O(N + L) time, O(L) temporary memory. N is snapshot tx count;
-  /// L is the previous loaded transaction/load-tracker count.
-  #[napi(js_name = "applySnapshot")]
-  pub fn apply_snapshot(&mut self, per_provider: Value) -> Result<()> {
-    let mut old_tx: HashMap<TxKey, Value> = HashMap::new();
-    let mut old_load: HashMap<TxKey, Value> = HashMap::new();
+  /// Complexity: O(H + R) for H handles (live and tombstoned) and R records.
+  fn compact(&mut self) -> CompactionStats {
+    let handles_before = self.txids.len();
+    let bytes_before = self.handle_table_bytes();
 
-    for (handle, tx) in &self.store.transactions {
-      if let Some(key) = self.store.txids.get(*handle as usize) {
-        old_tx.insert(*key, tx.clone());
-      }
-    }
+    let (txids, remap) = self.dense_handles();
+    self
+      .cursors
+      .compacted(remap.iter().enumerate().filter(|(_, new)| **new == u32::MAX).map(|(old, _)| old as u32).collect());
+    let live = |handle: u32| remap.get(handle as usize).copied().filter(|new| *new != u32::MAX);
 
-    for (handle, info) in &self.store.load_tracker {
-      if let Some(key) = self.store.txids.get(*handle as usize) {
-        old_load.insert(*key, info.clone());
-      }
+    self.txid_to_handle = txids.iter().enumerate().map(|(handle, key)| (*key, handle as u32)).collect();
+    self.txids = txids;
+    self.metadata = remap_records(std::mem::take(&mut self.metadata), live);
+    self.transactions = remap_records(std::mem::take(&mut self.transactions), live);
+    self.load_tracker = remap_records(std::mem::take(&mut self.load_tracker), live);
+    self.load_failures = remap_records(std::mem::take(&mut self.load_failures), live);
+    self.coverage.remap(live);
+    self.variants.remap(live);
+    for handles in self.provider_tx.values_mut().chain(self.fallback_tx.values_mut()) {
+      *handles = handles.iter().filter_map(|handle| live(*handle)).collect();
     }
+    self.provider_tx.retain(|_, handles| !handles.is_empty());
+    self.fallback_tx.retain(|_, handles| !handles.is_empty());
+    self.removed_handles = HashSet::new();
 
-    self.store.clear();
-
-    let mut seen = HashSet::new();
-
-    let Value::Object(providers) = per_provider else {
-      return Ok(());
-    };
-
-    for (provider, items) in providers {
-      let mut handles = Vec::new();
+    self.rebuild_indexes();
+    self.fee_index.shrink_to_fit();
+    self.graph.shrink_to_fit();
+    self.spends.shrink_to_fit();
+    self.scripts.shrink_to_fit();
 
-      let Value::Array(arr) = items else {
-        continue;
-      };
+    CompactionStats {
+      handles_before,
+      handles_after: self.txids.len(),
+      bytes_before,
+      bytes_after: self.handle_table_bytes(),
+    }
+  }
 
-      for item in arr {
-        let Some(txid) = string_field(&item, "txid") else {
-          continue;
-        };
-        let Some(metadata) = item.get("metadata").cloned() else {
-          continue;
-        };
-        let Some(key) = parse_txid(txid) else {
-          continue;
-        };
+  /// Live txids in handle order and an old-handle to dense-index table
+  /// (`u32::MAX` for tombstones).
+  fn dense_handles(&self) -> (Vec<TxKey>, Vec<u32>) {
+    let mut remap = vec![u32::MAX; self.txids.len()];
+    let mut txids = Vec::with_capacity(self.txid_to_handle.len());
+    for (old, key) in self.txids.iter().enumerate() {
+      if self.txid_to_handle.get(key) == Some(&(old as u32)) {
+        remap[old] = txids.len() as u32;
+        txids.push(*key);
+      }
+    }
+    (txids, remap)
+  }
 
-        if !seen.insert(key) {
-          continue;
-        }
+  /// Allocated bytes of the handle table and handle-keyed record maps, from
+  /// container capacities (one control byte per hash-map slot).
+  fn handle_table_bytes(&self) -> usize {
+    self.tx_index_bytes()
+      + self.provider_tx.values().chain(self.fallback_tx.values()).map(HandleList::heap_bytes).sum::<usize>()
+      + map_bytes(&self.metadata)
+      + map_bytes(&self.transactions)
+      + map_bytes(&self.load_tracker)
+      + map_bytes(&self.load_failures)
+  }
 
-        let handle = self.store.ensure_handle(key);
-        handles.push(handle);
-        self.store.metadata.insert(handle, metadata);
+  fn tx_index_bytes(&self) -> usize {
+    vec_bytes(&self.txids) + map_bytes(&self.txid_to_handle) + set_bytes(&self.removed_handles)
+  }
 
-        if let Some(tx) = old_tx.remove(&key) {
-          self.store.transactions.insert(handle, tx);
-        }
+  /// Allocated bytes per component: container capacities plus the deep size
+  /// of stored records (scripts, inputs/outputs, strings, index lists).
+  fn memory_components(&self) -> [(&'static str, usize); 13] {
+    let provider_tx = map_bytes(&self.provider_tx)
+      + map_bytes(&self.fallback_tx)
+      + self
+        .provider_tx
+        .iter()
+        .chain(&self.fallback_tx)
+        .map(|(name, handles)| name.capacity() + handles.heap_bytes())
+        .sum::<usize>();
+    [
+      ("txIndex", self.tx_index_bytes()),
+      (
+        "metadata",
+        map_bytes(&self.metadata)
+          + self.metadata.values().map(MempoolTxMetadata::heap_bytes).sum::<usize>()
+          + self.variants.heap_bytes(),
+      ),
+      (
+        "txStore",
+        map_bytes(&self.transactions) + self.transactions.values().map(StoredTransaction::heap_bytes).sum::<usize>(),
+      ),
+      (
+        "loadTracker",
+        map_bytes(&self.load_tracker)
+          + map_bytes(&self.load_failures)
+          + self.load_failures.values().map(LoadFailures::heap_bytes).sum::<usize>(),
+      ),
+      ("providerTx", provider_tx + self.provider_names.heap_bytes()),
+      ("feeIndex", self.fee_index.heap_bytes()),
+      ("graph", self.graph.heap_bytes()),
+      ("spends", self.spends.heap_bytes()),
+      ("scripts", self.scripts.heap_bytes()),
+      ("journal", self.journal.heap_bytes()),
+      ("pendingIndex", self.pending.heap_bytes()),
+      ("coverage", self.coverage.heap_bytes()),
+      ("other", self.watchlist.heap_bytes() + self.confirmations.heap_bytes() + self.cursors.heap_bytes()),
+    ]
+  }
 
-        if let Some(load) = old_load.remove(&key) {
-          self.store.load_tracker.insert(handle, load);
-        }
-      }
+  /// Unique descendants of `handles` that are not in `handles` themselves.
+  fn descendants_of_all(&self, handles: &[u32]) -> Vec<u32> {
+    let mut seen: HashSet<u32> = handles.iter().copied().collect();
+    let mut out = Vec::new();
+    for handle in handles {
+      out.extend(self.graph.descendants(*handle).into_iter().filter(|d| seen.insert(*d)));
+    }
+    out
+  }
 
-      if !handles.is_empty() {
-        self.store.provider_tx.insert(provider, handles);
+  /// Drops every record and index entry owned by `handle`, except provider
+  /// membership which callers prune in one pass for a batch of handles.
+  fn remove_handle_records(&mut self, handle: u32) {
+    self.metadata.remove(&handle);
+    if let Some(tx) = self.transactions.remove(&handle) {
+      for outpoint in tx.spent_outpoints() {
+        self.spends.remove(&outpoint, handle);
       }
+      self.graph.remove(handle, tx.inputs().map(|(txid, _, _)| txid));
+    } else {
+      self.graph.remove(handle, std::iter::empty());
     }
-
-    Ok(())
+    self.load_tracker.remove(&handle);
+    self.load_failures.remove(&handle);
+    self.pending.remove(handle);
+    self.coverage.remove(handle);
+    self.variants.remove(handle);
+    self.fee_index.remove(handle);
+    self.scripts.remove_handle(handle);
   }
 
-  /// Additively merges a provider snapshot into the current mempool state.
+  /// Fee rate and vsize used for ordering `handle`.
   ///
-  /// This mirrors the TypeScript `JsMempoolStateStore.mergeSnapshot()` contract:
-  /// it adds new txids and refreshes metadata for existing txids, but it does
-  /// not remove transactions that are absent from the new provider snapshot.
-  /// Confirmed transaction cleanup must go through `removeTxids()`.
-  pub fn merge_snapshot(&mut self, per_provider: Value) -> Result<()> {
-    self.merge_snapshot_impl(per_provider)
+  /// Provider metadata is authoritative; a loaded transaction without metadata
+  /// falls back to its own `feeRate` (or the one captured in load info).
+  fn entry_fee_rate(&self, handle: u32) -> Option<(f64, u32)> {
+    if let Some(metadata) = self.metadata.get(&handle) {
+      return Some((metadata.fee_rate(), metadata.vsize));
+    }
+    let tx = self.transactions.get(&handle)?;
+    let load_rate = self.load_tracker.get(&handle).map(|load| load.fee_rate);
+    Some((tx.fee_rate().or(load_rate).unwrap_or(0.0), tx.vsize()))
   }
 
-  #[napi]
-  #[allow(non_snake_case)]
-  pub fn mergeSnapshot(&mut self, per_provider: Value) -> Result<()> {
-    self.merge_snapshot_impl(per_provider)
+  /// First time this store saw `handle`, in ms: the load-tracker timestamp,
+  /// falling back to the provider's `time` (seconds) from metadata.
+  fn first_seen_ms(&self, handle: u32) -> Option<u64> {
+    if let Some(load) = self.load_tracker.get(&handle) {
+      return Some(load.timestamp);
+    }
+    self.metadata.get(&handle).map(|md| md.time * 1000).filter(|ms| *ms > 0)
   }
 
-  fn merge_snapshot_impl(&mut self, per_provider: Value) -> Result<()> {
-    let Value::Object(providers) = per_provider else {
-      return Ok(());
-    };
-
-    let mut seen = HashSet::new();
+  /// When `handle` entered the mempool, in ms: the provider's `time`, falling
+  /// back to `first_seen_ms` for entries known only from a load.
+  fn entry_time_ms(&self, handle: u32) -> Option<u64> {
+    self.metadata.get(&handle).map(|md| md.time * 1000).filter(|ms| *ms > 0).or_else(|| self.first_seen_ms(handle))
+  }
 
-    for (provider, items) in providers {
-      let Value::Array(arr) = items else {
-        continue;
-      };
+  /// Records how long `handle` waited before confirmation.
+  fn record_confirmation(&mut self, handle: u32, confirmed_at_ms: u64) {
+    let Some(first_seen) = self.first_seen_ms(handle) else {
+      return;
+    };
+    if let Some((rate, _)) = self.entry_fee_rate(handle) {
+      self.confirmations.record(rate, confirmed_at_ms.saturating_sub(first_seen));
+    }
+  }
 
-      for item in arr {
-        let Some(txid) = string_field(&item, "txid") else {
-          continue;
-        };
-        let Some(metadata) = item.get("metadata").cloned() else {
-          continue;
-        };
-        let Some(key) = parse_txid(txid) else {
-          continue;
-        };
+  fn reindex_fee_rate(&mut self, handle: u32) {
+    match self.entry_fee_rate(handle) {
+      Some((rate, vsize)) => self.fee_index.upsert(handle, rate, vsize),
+      None => self.fee_index.remove(handle),
+    }
+  }
 
-        if !seen.insert(key) {
-          continue;
-        }
+  /// Rebuilds derived indexes from the primary handle-keyed records, e.g.
+  /// after a snapshot import populated the maps directly.
+  fn rebuild_indexes(&mut self) {
+    self.fee_index.clear();
+    self.graph.clear();
+    self.spends.clear();
+    self.scripts.clear();
+    let handles: Vec<u32> = self.metadata.keys().chain(self.transactions.keys()).copied().collect();
+    for handle in handles {
+      self.reindex_fee_rate(handle);
+    }
+    let loaded: Vec<u32> = self.transactions.keys().copied().collect();
+    for handle in loaded {
+      self.index_transaction(handle);
+    }
+    self.rebuild_pending();
+  }
 
-        let handle = self.store.ensure_handle(key);
-        self.store.removed_handles.remove(&handle);
-        self.store.metadata.insert(handle, metadata);
+  /// Pending-order key of `handle` while it awaits a load, `None` once it is
+  /// loaded, removed or has no metadata to order by.
+  fn pending_key(&self, handle: u32) -> Option<u64> {
+    if !self.pending.is_active() || self.load_tracker.contains_key(&handle) || self.removed_handles.contains(&handle) {
+      return None;
+    }
+    let metadata = self.metadata.get(&handle)?;
+    let order = self.pending.order();
+    let watched = order == PendingOrder::WatchedFirst
+      && (self.txids.get(handle as usize).is_some_and(|txid| self.watchlist.watches_txid(txid))
+        || metadata.depends.iter().any(|parent| self.watchlist.watches_txid(parent)));
+    Some(order.key(metadata.fee_rate(), metadata.time, watched))
+  }
 
-        let handles = self.store.provider_tx.entry(provider.clone()).or_default();
-        if !handles.contains(&handle) {
-          handles.push(handle);
-        }
+  /// Requeues every provider list under the current pending order.
+  ///
+  /// Complexity: O(K log N) for K provider list entries.
+  fn rebuild_pending(&mut self) {
+    self.pending.clear();
+    let queued: Vec<(String, Vec<(u32, u64)>)> = self
+      .provider_tx
+      .iter()
+      .map(|(provider, handles)| {
+        let keyed = handles.iter().filter_map(|handle| Some((*handle, self.pending_key(*handle)?))).collect();
+        (provider.clone(), keyed)
+      })
+      .collect();
+    for (provider, keyed) in queued {
+      for (handle, key) in keyed {
+        self.pending.push(&provider, handle, key);
       }
     }
+  }
+
+  /// Stores the metadata the reconciliation policy selects from `handle`'s
+  /// variants, when it differs from the current one.
+  fn reconcile_metadata(&mut self, handle: u32) {
+    if let Some(selected) = self.variants.resolve(handle).filter(|md| self.metadata.get(&handle) != Some(*md)) {
+      let selected = selected.clone();
+      self.insert_metadata(handle, selected);
+    }
+  }
 
-    Ok(())
+  /// Watchlist changes move entries between the two `watchedFirst` ranks.
+  fn rekey_watched_pending(&mut self) {
+    if self.pending.order() == PendingOrder::WatchedFirst {
+      self.rebuild_pending();
+    }
   }
 
-  /// Removes confirmed transactions from all native indexes.
-  ///
-  /// Handles are tombstoned instead of reindexing `txids`, matching the JS store
-  /// design where handle slots are stable and `txIds()` skips removed entries.
-  pub fn remove_txids(&mut self, txids: Vec<String>) -> Result<()> {
-    self.remove_txids_impl(txids)
+  /// Takes `handle` out of the lists (and pending queues) of every provider
+  /// not in `keep`.
+  fn retain_provider_handle(&mut self, handle: u32, keep: &[&str]) {
+    for (provider, handles) in self.provider_tx.iter_mut() {
+      if !keep.contains(&provider.as_str()) && handles.contains(&handle) {
+        handles.retain(|candidate| *candidate != handle);
+        self.pending.remove_from(provider, handle);
+      }
+    }
+    self.provider_tx.retain(|_, handles| !handles.is_empty());
   }
 
-  #[napi]
-  #[allow(non_snake_case)]
-  pub fn removeTxids(&mut self, txids: Vec<String>) -> Result<()> {
-    self.remove_txids_impl(txids)
+  /// Ids of the providers listing each live txid, sorted.
+  fn provider_sets(&mut self) -> HashMap<TxKey, Vec<ProviderId>> {
+    let mut sets: HashMap<TxKey, Vec<ProviderId>> = HashMap::new();
+    for (provider, handles) in &self.provider_tx {
+      let id = self.provider_names.intern(provider);
+      for handle in handles.iter().filter(|handle| !self.removed_handles.contains(handle)) {
+        if let Some(key) = self.txids.get(*handle as usize) {
+          sets.entry(*key).or_default().push(id);
+        }
+      }
+    }
+    for set in sets.values_mut() {
+      set.sort_unstable();
+    }
+    sets
   }
 
-  fn remove_txids_impl(&mut self, txids: Vec<String>) -> Result<()> {
-    for txid in txids {
-      let Some(key) = parse_txid(&txid) else {
-        continue;
-      };
-      let Some(handle) = self.store.txid_to_handle.remove(&key) else {
-        continue;
-      };
+  /// Adds `handle` to `provider`'s list (once) and its pending queue.
+  fn add_provider_handle(&mut self, provider: &str, handle: u32) {
+    self.provider_tx.entry(provider.to_string()).or_default().push(handle);
+    if let Some(key) = self.pending_key(handle) {
+      self.pending.push(provider, handle, key);
+    }
+  }
 
-      self.store.metadata.remove(&handle);
-      self.store.transactions.remove(&handle);
-      self.store.load_tracker.remove(&handle);
-      self.store.removed_handles.insert(handle);
+  /// Whether `pendingTxids` should offer `handle` to `provider` now: it is
+  /// unloaded, not in backoff and `provider` has not handed it off after
+  /// exhausting its attempts.
+  fn is_pending_for(&self, handle: u32, provider_name: &str, provider: Option<ProviderId>, now: u64) -> bool {
+    if self.removed_handles.contains(&handle)
+      || self.load_tracker.contains_key(&handle)
+      || !self.metadata.contains_key(&handle)
+    {
+      return false;
+    }
 
-      for handles in self.store.provider_tx.values_mut() {
-        handles.retain(|candidate| *candidate != handle);
+    match (provider, self.load_failures.get(&handle)) {
+      (Some(provider), Some(failures)) => {
+        !(failures.in_backoff(provider, now)
+          || (failures.attempts(provider) >= self.retry_policy.max_failures
+            && self.has_load_fallback(handle, provider_name, failures)))
       }
+      _ => true,
     }
+  }
 
-    self.store.provider_tx.retain(|_, handles| !handles.is_empty());
-    Ok(())
+  /// Estimated bytes held for `handle`: its txid slot and hash-map entry plus
+  /// every handle-keyed record with its heap payload.
+  fn entry_bytes(&self, handle: u32) -> u64 {
+    use std::mem::size_of;
+    let mut bytes = size_of::<TxKey>() + size_of::<(TxKey, u32)>() + 1;
+    if let Some(md) = self.metadata.get(&handle) {
+      bytes += size_of::<(u32, MempoolTxMetadata)>() + 1 + md.heap_bytes();
+    }
+    if let Some(tx) = self.transactions.get(&handle) {
+      bytes += size_of::<(u32, StoredTransaction)>() + 1 + tx.heap_bytes();
+    }
+    if self.load_tracker.contains_key(&handle) {
+      bytes += size_of::<(u32, LoadInfo)>() + 1;
+    }
+    bytes as u64
   }
 
-  #[napi]
-  pub fn providers(&self) -> Vec<String> {
-    self.store.provider_tx.keys().cloned().collect()
+  fn entry_usage(&self, handle: u32) -> Usage {
+    Usage { entries: 1, vbytes: self.entry_fee_and_vsize(handle).1, bytes: self.entry_bytes(handle) }
   }
 
-  /// Selects txids that still need a full/slim transaction load for one provider.
-  ///
-  /// Complexity: O(K) in that provider's handle list, with early stop after
-  /// `limit` pending txids. The method only checks compact handles and avoids
-  /// scanning the whole global mempool when a provider-specific list exists.
-  #[napi(js_name = "pendingTxids")]
-  pub fn pending_txids(&self, provider_name: String, limit: f64) -> Vec<String> {
-    let mut out = Vec::new();
-    let limit = limit.max(0.0) as usize;
+  fn usage(&self) -> Usage {
+    let mut usage = Usage::default();
+    for handle in self.txid_to_handle.values() {
+      let entry = self.entry_usage(*handle);
+      usage.entries += 1;
+      usage.vbytes += entry.vbytes;
+      usage.bytes += entry.bytes;
+    }
+    usage
+  }
 
-    if limit == 0 {
-      return out;
+  /// In-mempool children of every live entry, from provider `depends` and
+  /// loaded prevouts.
+  fn children_map(&self) -> HashMap<u32, Vec<u32>> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for handle in self.txid_to_handle.values().copied() {
+      let parents = match self.metadata.get(&handle) {
+        Some(md) => self.package_parents(handle, md),
+        None => self.graph.parents_of(handle).to_vec(),
+      };
+      for parent in parents {
+        children.entry(parent).or_default().push(handle);
+      }
     }
+    children
+  }
 
-    if let Some(handles) = self.store.provider_tx.get(&provider_name) {
-      for handle in handles {
-        if out.len() >= limit {
-          break;
+  /// `root` followed by its descendants in `children`, breadth first.
+  fn package_of(root: u32, children: &HashMap<u32, Vec<u32>>) -> Vec<u32> {
+    let mut seen = HashSet::from([root]);
+    let mut package = vec![root];
+    let mut next = 0;
+    while let Some(handle) = package.get(next).copied() {
+      next += 1;
+      for child in children.get(&handle).into_iter().flatten() {
+        if seen.insert(*child) {
+          package.push(*child);
         }
+      }
+    }
+    package
+  }
 
-        if self.store.removed_handles.contains(handle)
-          || self.store.load_tracker.contains_key(handle)
-          || !self.store.metadata.contains_key(handle)
-        {
-          continue;
-        }
+  /// Fee rate of `handle` together with its descendants: evicting it evicts
+  /// them too, so a low-fee parent of a high-fee child is kept.
+  fn descendant_score(&self, handle: u32, children: &HashMap<u32, Vec<u32>>) -> f64 {
+    let (fee, vsize) = Self::package_of(handle, children)
+      .into_iter()
+      .map(|member| self.entry_fee_and_vsize(member))
+      .fold((0, 0), |(fee, vsize), (f, v)| (fee + f, vsize + v));
+    fee as f64 / vsize.max(1) as f64
+  }
 
-        if let Some(key) = self.store.txids.get(*handle as usize) {
-          out.push(txid_to_hex(*key));
+  /// Evicts expired packages and then, while a size bound is exceeded, the
+  /// package with the lowest descendant fee rate (newest first on ties).
+  /// Returns every evicted txid with the bound that caused it.
+  fn enforce_limits(&mut self, now_ms: u64, hits: &mut Vec<WatchHit>) -> Vec<(TxKey, EvictionReason)> {
+    let limits = self.limits;
+    let mut evicted = Vec::new();
+    if limits.ttl_ms.is_none() && !limits.is_size_bounded() {
+      return evicted;
+    }
+    let children = self.children_map();
+    let mut usage = Usage::default();
+
+    if let Some(ttl_ms) = limits.ttl_ms {
+      let cutoff = now_ms.saturating_sub(ttl_ms);
+      let mut expired: Vec<u32> = self
+        .txid_to_handle
+        .values()
+        .copied()
+        .filter(|handle| self.first_seen_ms(*handle).is_some_and(|seen| seen < cutoff))
+        .collect();
+      expired.sort_unstable();
+      for handle in expired {
+        for member in Self::package_of(handle, &children) {
+          self.evict(member, EvictionReason::Expired, &mut usage, &mut evicted, hits);
         }
       }
     }
 
-    out
+    if !limits.is_size_bounded() {
+      return evicted;
+    }
+    usage = self.usage();
+    if limits.exceeded(&usage).is_none() {
+      return evicted;
+    }
+
+    let mut candidates: Vec<(f64, u32)> =
+      self.txid_to_handle.values().map(|handle| (self.descendant_score(*handle, &children), *handle)).collect();
+    candidates.sort_unstable_by(|a, b| a.0.total_cmp(&b.0).then(b.1.cmp(&a.1)));
+    for (_, handle) in candidates {
+      let Some(reason) = limits.exceeded(&usage) else {
+        break;
+      };
+      for member in Self::package_of(handle, &children) {
+        self.evict(member, reason, &mut usage, &mut evicted, hits);
+      }
+    }
+    evicted
   }
 
-  /// Records loaded transactions returned by TS provider calls.
+  fn evict(
+    &mut self,
+    handle: u32,
+    reason: EvictionReason,
+    usage: &mut Usage,
+    evicted: &mut Vec<(TxKey, EvictionReason)>,
+    hits: &mut Vec<WatchHit>,
+  ) {
+    let entry = self.entry_usage(handle);
+    let drop_reason = if reason == EvictionReason::Expired { DropReason::Expired } else { DropReason::Evicted };
+    if !self.drop_handle_watched(handle, Some(drop_reason), hits) {
+      return;
+    }
+    usage.entries = usage.entries.saturating_sub(entry.entries);
+    usage.vbytes = usage.vbytes.saturating_sub(entry.vbytes);
+    usage.bytes = usage.bytes.saturating_sub(entry.bytes);
+    evicted.extend(self.txid_of(handle).map(|txid| (txid, reason)));
+  }
+
+  /// Whether a provider other than `provider_name` lists `handle`, directly or
+  /// as a fallback, and has not used up its own load attempts on it.
+  fn has_load_fallback(&self, handle: u32, provider_name: &str, failures: &LoadFailures) -> bool {
+    self.provider_tx.iter().chain(&self.fallback_tx).any(|(name, handles)| {
+      name != provider_name
+        && handles.contains(&handle)
+        && self.provider_names.id_of(name).map_or(0, |id| failures.attempts(id)) < self.retry_policy.max_failures
+    })
+  }
+
+  /// Whether every provider that lists `handle` directly reached the retry
+  /// policy's `maxFailures` on it, so fallback providers may load it.
+  fn load_exhausted(&self, handle: u32) -> bool {
+    let Some(failures) = self.load_failures.get(&handle) else {
+      return false;
+    };
+    self.provider_tx.iter().filter(|(_, handles)| handles.contains(&handle)).all(|(name, _)| {
+      self.provider_names.id_of(name).is_some_and(|id| failures.attempts(id) >= self.retry_policy.max_failures)
+    })
+  }
+
+  /// Remembers that `provider` also listed `handle` after the snapshot had
+  /// already assigned it to another provider.
+  fn push_fallback(&mut self, provider: &str, handle: u32) {
+    self.fallback_tx.entry(provider.to_string()).or_default().push(handle);
+  }
+
+  /// Fee and vsize of `handle` in satoshis/vbytes, for package aggregates.
+  fn entry_fee_and_vsize(&self, handle: u32) -> (u64, u64) {
+    if let Some(md) = self.metadata.get(&handle) {
+      return (md.fee_sats(), md.vsize as u64);
+    }
+    match self.entry_fee_rate(handle) {
+      Some((rate, vsize)) => ((rate * vsize as f64).round() as u64, vsize as u64),
+      None => (0, 0),
+    }
+  }
+
+  fn hex_txids(&self, handles: &[u32]) -> Vec<String> {
+    handles.iter().filter_map(|h| self.txid_of(*h)).map(txid_to_hex).collect()
+  }
+
+  /// Distinct txids touching `key`, in indexing order.
+  fn script_txids(&self, key: Option<ScriptKey>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let handles: Vec<u32> = key
+      .iter()
+      .flat_map(|key| self.scripts.entries(key))
+      .map(|entry| entry.handle)
+      .filter(|handle| seen.insert(*handle))
+      .collect();
+    self.hex_txids(&handles)
+  }
+
+  /// Unconfirmed received/spent totals for `key`, in satoshis.
+  fn script_balance(&self, key: Option<ScriptKey>) -> Value {
+    let entries = key.as_ref().map(|key| self.scripts.entries(key)).unwrap_or(&[]);
+    let received: u64 = entries.iter().filter(|entry| !entry.spent).map(|entry| entry.value_sat).sum();
+    let spent: u64 = entries.iter().filter(|entry| entry.spent).map(|entry| entry.value_sat).sum();
+    let tx_count = entries.iter().map(|entry| entry.handle).collect::<HashSet<_>>().len();
+    json!({
+      "received": received,
+      "spent": spent,
+      "balance": received as i64 - spent as i64,
+      "txCount": tx_count,
+    })
+  }
+
+  /// Builds block-template simulation input from metadata entries. `depends`
+  /// is resolved to live handles; parents unknown to the store are dropped.
+  fn package_nodes(&self) -> Vec<PackageNode> {
+    self
+      .metadata
+      .iter()
+      .map(|(handle, md)| PackageNode {
+        handle: *handle,
+        fee: md.fee_sats(),
+        vsize: md.vsize as u64,
+        weight: md.weight_units(),
+        ancestor_fee: md.ancestor_fees,
+        ancestor_vsize: md.ancestor_size as u64,
+        parents: self.package_parents(*handle, md),
+      })
+      .collect()
+  }
+
+  /// Parents from provider `depends`, plus graph edges from loaded prevouts.
+  fn package_parents(&self, handle: u32, md: &MempoolTxMetadata) -> Vec<u32> {
+    let mut parents: Vec<u32> = md.depends.iter().filter_map(|key| self.txid_to_handle.get(key).copied()).collect();
+    for parent in self.graph.parents_of(handle) {
+      if !parents.contains(parent) {
+        parents.push(*parent);
+      }
+    }
+    parents
+  }
+
+  fn fee_entry_value(&self, handle: u32, rate: f64, vsize: u32) -> Option<Value> {
+    let key = self.txid_of(handle)?;
+    Some(json!({ "txid": txid_to_hex(key), "feeRate": rate, "vsize": vsize }))
+  }
+
+  /// Handles matching every predicate of `query`, in its sort order (before
+  /// offset and limit).
   ///
-  /// Complexity: O(M) for M loaded items. Each txid is resolved to a compact
-  /// handle and then the transaction JSON plus load info are stored by handle.
-  /// Duplicate loads are ignored, preserving the first successful load info.
-  #[napi(js_name = "recordLoaded")]
-  pub fn record_loaded(&mut self, loaded_transactions: Vec<Value>) -> Result<()> {
-    let timestamp = now_ms();
+  /// Candidates come from the fee-rate index, which holds every handle with
+  /// metadata or a loaded transaction, so a fee-rate bound narrows the scan
+  /// to O(log N + k). Metadata is authoritative for `bip125Replaceable` and
+  /// ancestor counts, the loaded transaction is the fallback; entries missing
+  /// a filtered field (or an entry time, for age bounds) do not match.
+  fn query_handles(&self, query: &MempoolQuery, now: u64) -> Vec<(u32, f64, u32)> {
+    // A provider lists a txid when it holds it in `provider_tx`, lost it to
+    // snapshot dedup (`fallback_tx`) or reported it at some point (coverage).
+    let listed: Option<(HashSet<u32>, Option<ProviderId>)> = query.provider.as_deref().map(|provider| {
+      let handles = self.provider_tx.get(provider).into_iter().chain(self.fallback_tx.get(provider)).flatten();
+      (handles.copied().collect(), self.provider_names.id_of(provider))
+    });
+    let (min_rate, max_rate) = (query.fee_rate.min.unwrap_or(0.0), query.fee_rate.max.unwrap_or(f64::MAX));
 
-    for item in loaded_transactions {
-      let Some(txid) = string_field(&item, "txid") else {
-        continue;
-      };
-      let Some(transaction) = item.get("transaction").cloned() else {
-        continue;
-      };
-      let Some(key) = parse_txid(txid) else {
+    let mut matched: Vec<(u32, f64, u32)> = self
+      .fee_index
+      .range_desc(min_rate, max_rate)
+      .filter(|(handle, rate, vsize)| {
+        let metadata = self.metadata.get(handle);
+        let tx = self.transactions.get(handle);
+        query.fee_rate.contains(*rate)
+          && query.vsize.contains(*vsize as f64)
+          && query.loaded.is_none_or(|loaded| loaded == self.load_tracker.contains_key(handle))
+          && listed.as_ref().is_none_or(|(handles, id)| {
+            handles.contains(handle)
+              || id.is_some_and(|id| self.coverage.first_seen(*handle).iter().any(|(seen, _)| *seen == id))
+          })
+          && query.bip125_replaceable.is_none_or(|wanted| {
+            metadata.map(|md| md.bip125_replaceable).or_else(|| tx.and_then(StoredTransaction::bip125_replaceable))
+              == Some(wanted)
+          })
+          && (query.ancestor_count.is_open()
+            || metadata.is_some_and(|md| query.ancestor_count.contains(md.ancestor_count as f64)))
+          && (query.age_ms.is_open()
+            || self.entry_time_ms(*handle).is_some_and(|time| query.age_ms.contains(now.saturating_sub(time) as f64)))
+          && query
+            .txid_prefix
+            .as_deref()
+            .is_none_or(|prefix| self.txid_of(*handle).is_some_and(|key| txid_to_hex(key).starts_with(prefix)))
+      })
+      .collect();
+
+    match query.sort {
+      QuerySort::Insertion => matched.sort_unstable_by_key(|(handle, _, _)| *handle),
+      QuerySort::FeeRate => matched.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0))),
+      QuerySort::Vsize => matched.sort_unstable_by_key(|(handle, _, vsize)| (*vsize, *handle)),
+      // Oldest first is descending age; entries without a time count as new.
+      QuerySort::Age => matched.sort_unstable_by_key(|(handle, _, _)| {
+        (std::cmp::Reverse(self.entry_time_ms(*handle).unwrap_or(now)), *handle)
+      }),
+      QuerySort::AncestorCount => matched
+        .sort_unstable_by_key(|(handle, _, _)| (self.metadata.get(handle).map_or(0, |md| md.ancestor_count), *handle)),
+      QuerySort::Txid => matched.sort_unstable_by_key(|(handle, _, _)| self.txid_of(*handle).map(|key| key.0)),
+    }
+    if query.descending {
+      matched.reverse();
+    }
+    matched
+  }
+
+  /// `{ txid, providers, metadata?, transaction? | rawTransaction?, load? }`
+  /// for one delta entry, in the same record shapes as `exportSnapshot`.
+  fn delta_entry_value(&self, key: TxKey, providers: &HashMap<u32, Vec<&str>>) -> Option<Value> {
+    let handle = *self.txid_to_handle.get(&key)?;
+    let mut entry = json!({
+      "txid": txid_to_hex(key),
+      "providers": providers.get(&handle).cloned().unwrap_or_default(),
+    });
+    if let Some(md) = self.metadata.get(&handle) {
+      entry["metadata"] = md.to_value(key);
+    }
+    match self.transactions.get(&handle) {
+      Some(StoredTransaction::Raw(raw)) => entry["rawTransaction"] = json!(hex::encode(raw.bytes())),
+      Some(tx) => entry["transaction"] = json!(tx.to_value(key)),
+      None => {}
+    }
+    if let Some(load) = self.load_tracker.get(&handle) {
+      entry["load"] = load.to_value(&self.provider_names);
+    }
+    Some(entry)
+  }
+
+  /// Upserts one `exportDelta` entry. Loaded transactions are immutable per
+  /// txid, so a transaction already held by this store is kept as is. The
+  /// entry's `providers` replace the providers listing the txid here.
+  fn apply_delta_entry(&mut self, entry: &Value, hits: &mut Vec<WatchHit>) {
+    let Some(key) = string_field(entry, "txid").and_then(parse_txid) else {
+      return;
+    };
+    let handle = self.ensure_handle_watched(key, hits);
+
+    if let Some(metadata) = entry.get("metadata").and_then(MempoolTxMetadata::from_value) {
+      self.insert_metadata(handle, metadata);
+    }
+
+    if !self.transactions.contains_key(&handle) {
+      if let Some(stored) = delta_transaction(entry, key, &self.watchlist, hits) {
+        match entry.get("load").and_then(|load| LoadInfo::from_value(load, &mut self.provider_names)) {
+          Some(load) => self.insert_loaded(handle, stored, load),
+          None => {
+            self.insert_transaction(handle, stored);
+            self.reindex_fee_rate(handle);
+            self.journal_update(handle);
+          }
+        }
+      }
+    }
+
+    let Some(providers) = entry.get("providers").and_then(Value::as_array) else {
+      return;
+    };
+    let providers: Vec<&str> = providers.iter().filter_map(Value::as_str).collect();
+    self.retain_provider_handle(handle, &providers);
+    let now = now_ms();
+    for provider in providers {
+      self.add_provider_handle(provider, handle);
+      let provider = self.provider_names.intern(provider);
+      self.coverage.mark(handle, provider, now);
+    }
+  }
+
+  fn import_pair(&mut self, entry: &Value, target: PairTarget) {
+    let Value::Array(pair) = entry else {
+      return;
+    };
+
+    if pair.len() != 2 {
+      return;
+    }
+
+    let Some(txid) = pair[0].as_str() else {
+      return;
+    };
+
+    let Some(key) = parse_txid(txid) else {
+      return;
+    };
+
+    match target {
+      PairTarget::Metadata => {
+        if let Some(metadata) = MempoolTxMetadata::from_value(&pair[1]) {
+          let handle = self.ensure_handle(key);
+          self.metadata.insert(handle, metadata);
+        }
+      }
+      PairTarget::Transaction => {
+        if let Some(tx) = LightTransaction::from_value(&pair[1], key) {
+          let handle = self.ensure_handle(key);
+          self.transactions.insert(handle, tx.into());
+        }
+      }
+      PairTarget::RawTransaction => {
+        let bytes = pair[1].as_str().and_then(|raw| hex::decode(raw).ok());
+        if let Some((raw, _)) = bytes.and_then(|bytes| RawTransaction::parse(key, &bytes).ok()) {
+          let handle = self.ensure_handle(key);
+          self.transactions.insert(handle, raw.into());
+        }
+      }
+      PairTarget::LoadTracker => {
+        if let Some(info) = LoadInfo::from_value(&pair[1], &mut self.provider_names) {
+          let handle = self.ensure_handle(key);
+          self.load_tracker.insert(handle, info);
+        }
+      }
+    }
+  }
+}
+
+/// Reports collected while `recordLoaded`/`recordLoadedRaw` store a batch.
+#[derive(Default)]
+struct LoadOutcome {
+  conflicts: Vec<Value>,
+  replaced: Vec<String>,
+  evicted_descendants: Vec<String>,
+  hits: Vec<WatchHit>,
+}
+
+#[derive(Clone, Copy)]
+enum PairTarget {
+  Metadata,
+  Transaction,
+  RawTransaction,
+  LoadTracker,
+}
+
+/// N-API wrapper exposed to JS as `NativeMempoolState` for compatibility with
+/// the TypeScript native registry. It delegates to `MempoolBackingStore` and
+/// must be used only as a private state/index engine inside `MempoolStateStore`.
+#[napi]
+pub struct NativeMempoolState {
+  store: MempoolBackingStore,
+}
+
+impl Default for NativeMempoolState {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[napi]
+impl NativeMempoolState {
+  #[napi(constructor)]
+  pub fn new() -> Self {
+    Self { store: MempoolBackingStore::default() }
+  }
+
+  /// Applies a provider snapshot prepared by the TS aggregate/event handler.
+  /// Existing loaded transactions/load info are preserved for txids that remain
+  /// present in the new snapshot.
+  ///
+  /// Algorithm:
+  /// 1. Build temporary maps from old handles back to full txids for loaded
+  ///    transactions and load-tracker records.
+  /// 2. Clear all indexes that describe the current mempool snapshot.
+  /// 3. Rebuild txid handles, provider membership and metadata from the new
+  ///    snapshot, deduplicating txids globally across providers. Under a
+  ///    `setMetadataPolicy` policy, metadata is then reconciled from every
+  ///    provider's report instead of the first one.
+  /// 4. Restore loaded transaction/load records only when their txid is still
+  ///    present in the refreshed mempool.
+  ///
+  /// Complexity: O(N + L) time, O(L) temporary memory. N is snapshot tx count;
+  /// L is the previous loaded transaction/load-tracker count. Txids whose
+  /// metadata or providers changed are journaled as updated.
+  ///
+  /// With `report_diff`, returns `{ diff }` comparing the refreshed mempool
+  /// with the previous one (see `MutationDiff`); this also keeps the previous
+  /// metadata, O(P) memory for P previous txids. Otherwise returns `{}`.
+  /// Entries evicted by `setLimits` bounds are listed as `evicted`.
+  #[napi(js_name = "applySnapshot")]
+  pub fn apply_snapshot(&mut self, per_provider: Value, report_diff: Option<bool>) -> Result<Value> {
+    self.apply_snapshot_at(per_provider, report_diff, now_ms())
+  }
+
+  /// `applySnapshot` observed at `now`, the first-seen time recorded for
+  /// providers reporting a txid for the first time.
+  fn apply_snapshot_at(&mut self, per_provider: Value, report_diff: Option<bool>, now: u64) -> Result<Value> {
+    let mut old_tx: HashMap<TxKey, StoredTransaction> = HashMap::new();
+    let mut old_load: HashMap<TxKey, LoadInfo> = HashMap::new();
+    let metadata = &mut self.store.metadata;
+    let mut previous: HashMap<TxKey, Option<MempoolTxMetadata>> =
+      self.store.txid_to_handle.iter().map(|(key, handle)| (*key, metadata.remove(handle))).collect();
+    let mut diff = MutationDiff::default();
+    let old_providers = self.store.provider_sets();
+
+    for (handle, tx) in self.store.transactions.drain() {
+      if let Some(key) = self.store.txids.get(handle as usize) {
+        old_tx.insert(*key, tx);
+      }
+    }
+
+    for (handle, info) in self.store.load_tracker.drain() {
+      if let Some(key) = self.store.txids.get(handle as usize) {
+        old_load.insert(*key, info);
+      }
+    }
+
+    let mut old_failures: HashMap<TxKey, LoadFailures> = HashMap::new();
+    for (handle, failures) in self.store.load_failures.drain() {
+      if let Some(key) = self.store.txids.get(handle as usize) {
+        old_failures.insert(*key, failures);
+      }
+    }
+
+    let mut old_coverage = HashMap::new();
+    for (handle, seen) in self.store.coverage.drain() {
+      if let Some(key) = self.store.txids.get(handle as usize) {
+        old_coverage.insert(*key, seen);
+      }
+    }
+
+    // The rebuild below re-adds every txid; keep the journal aside and record
+    // only the net change once it is done.
+    let journal = std::mem::take(&mut self.store.journal);
+    self.store.clear();
+
+    let mut seen = HashSet::new();
+    let mut fallbacks = Vec::new();
+    let mut kept = Vec::new();
+
+    let providers = match per_provider {
+      Value::Object(providers) => providers,
+      _ => serde_json::Map::new(),
+    };
+
+    for (provider, items) in providers {
+      let mut handles = HandleList::default();
+      let provider_id = self.store.provider_names.intern(&provider);
+
+      let Value::Array(arr) = items else {
         continue;
       };
 
-      let handle = self.store.ensure_handle(key);
+      for item in arr {
+        let Some(txid) = string_field(&item, "txid") else {
+          continue;
+        };
+        let Some(metadata) = item.get("metadata").and_then(MempoolTxMetadata::from_value) else {
+          continue;
+        };
+        let Some(key) = parse_txid(txid) else {
+          continue;
+        };
+
+        if !seen.insert(key) {
+          fallbacks.push((provider.clone(), provider_id, key, metadata));
+          continue;
+        }
 
-      if self.store.load_tracker.contains_key(&handle) {
-        continue;
+        match previous.remove(&key) {
+          None => diff.added.push(key),
+          Some(old) => kept.push((key, old)),
+        }
+
+        let handle = self.store.ensure_handle(key);
+        handles.push(handle);
+        self.store.variants.record(handle, provider_id, now, &metadata);
+        self.store.insert_metadata(handle, metadata);
+
+        if let Some(tx) = old_tx.remove(&key) {
+          self.store.insert_transaction(handle, tx);
+        }
+
+        if let Some(load) = old_load.remove(&key) {
+          self.store.load_tracker.insert(handle, load);
+        }
+
+        if let Some(failures) = old_failures.remove(&key) {
+          self.store.load_failures.insert(handle, failures);
+        }
+
+        if let Some(seen) = old_coverage.remove(&key) {
+          self.store.coverage.insert(handle, seen);
+        }
+        self.store.coverage.mark(handle, provider_id, now);
+      }
+
+      if !handles.is_empty() {
+        self.store.provider_tx.insert(provider, handles);
       }
+    }
 
-      let fee_rate = number_field(&transaction, "feeRate").unwrap_or(0.0);
-      let mut load = Map::new();
-      load.insert("timestamp".into(), json!(timestamp));
-      load.insert("feeRate".into(), json!(fee_rate));
+    for (provider, provider_id, key, metadata) in fallbacks {
+      if let Some(handle) = self.store.txid_to_handle.get(&key).copied() {
+        self.store.push_fallback(&provider, handle);
+        self.store.coverage.mark(handle, provider_id, now);
+        self.store.variants.record(handle, provider_id, now, &metadata);
+      }
+    }
+    for handle in self.store.variants.handles() {
+      self.store.reconcile_metadata(handle);
+    }
+    self.store.rebuild_pending();
 
-      if let Some(provider) = string_field(&item, "providerName") {
-        load.insert("providerName".into(), json!(provider));
+    self.store.journal = journal;
+    for key in &diff.added {
+      self.store.journal.added(*key);
+    }
+    for key in previous.keys() {
+      self.store.journal.removed(*key);
+    }
+    let new_providers = self.store.provider_sets();
+    for (key, old) in kept {
+      let current = self.store.txid_to_handle.get(&key).and_then(|handle| self.store.metadata.get(handle));
+      if old.as_ref() != current || old_providers.get(&key) != new_providers.get(&key) {
+        self.store.journal.updated(key);
       }
+      if let (Some(old), Some(current)) = (old, current) {
+        if old != *current {
+          diff.metadata_changed.push(MetadataChange {
+            txid: key,
+            old_fee: old.fee_sats(),
+            new_fee: current.fee_sats(),
+          });
+        }
+      }
+    }
 
-      self.store.transactions.insert(handle, transaction);
-      self.store.load_tracker.insert(handle, Value::Object(load));
+    if report_diff.unwrap_or(false) {
+      diff.removed.extend(previous.into_keys());
+      self.store.diff = Some(diff);
     }
 
-    Ok(())
+    // Watch hits are not reported by a full replace.
+    let evicted = self.store.enforce_limits(now, &mut Vec::new());
+    let mut report = json!({});
+    if !evicted.is_empty() {
+      report["evicted"] = evictions_value(&evicted);
+      self.finish_removals(&mut report);
+    }
+    self.finish_diff(&mut report);
+    Ok(report)
   }
 
-  #[napi(js_name = "txIds")]
-  pub fn tx_ids(&self) -> Vec<String> {
-    self
-      .store
-      .txids
-      .iter()
-      .enumerate()
-      .filter(|(handle, _)| !self.store.removed_handles.contains(&(*handle as u32)))
-      .map(|(_, key)| txid_to_hex(*key))
-      .collect()
+  /// Additively merges a provider snapshot into the current mempool state.
+  ///
+  /// This mirrors the TypeScript `JsMempoolStateStore.mergeSnapshot()` contract:
+  /// it adds new txids and refreshes metadata for existing txids, but it does
+  /// not remove transactions that are absent from the new provider snapshot.
+  /// Confirmed transaction cleanup must go through `removeTxids()`.
+  ///
+  /// Returns `{ watchHits }` with a `txSeen` hit for every watched txid the
+  /// merge added, plus `diff` with `report_diff` and `evicted` when the
+  /// merge pushed the store over its `setLimits` bounds.
+  pub fn merge_snapshot(&mut self, per_provider: Value, report_diff: Option<bool>) -> Result<Value> {
+    self.merge_snapshot_impl(per_provider, report_diff, now_ms())
+  }
+
+  #[napi]
+  #[allow(non_snake_case)]
+  pub fn mergeSnapshot(&mut self, per_provider: Value, report_diff: Option<bool>) -> Result<Value> {
+    self.merge_snapshot_impl(per_provider, report_diff, now_ms())
+  }
+
+  fn merge_snapshot_impl(&mut self, per_provider: Value, report_diff: Option<bool>, now: u64) -> Result<Value> {
+    let mut hits = Vec::new();
+    self.begin_diff(report_diff);
+    let providers = match per_provider {
+      Value::Object(providers) => providers,
+      _ => serde_json::Map::new(),
+    };
+
+    let mut seen = HashSet::new();
+
+    for (provider, items) in providers {
+      let provider_id = self.store.provider_names.intern(&provider);
+      let Value::Array(arr) = items else {
+        continue;
+      };
+
+      for item in arr {
+        let Some(txid) = string_field(&item, "txid") else {
+          continue;
+        };
+        let Some(metadata) = item.get("metadata").and_then(MempoolTxMetadata::from_value) else {
+          continue;
+        };
+        let Some(key) = parse_txid(txid) else {
+          continue;
+        };
+
+        if !seen.insert(key) {
+          if let Some(handle) = self.store.txid_to_handle.get(&key).copied() {
+            self.store.push_fallback(&provider, handle);
+            self.store.coverage.mark(handle, provider_id, now);
+            self.store.variants.record(handle, provider_id, now, &metadata);
+            self.store.reconcile_metadata(handle);
+          }
+          continue;
+        }
+
+        let handle = self.store.ensure_handle_watched(key, &mut hits);
+        self.store.variants.record(handle, provider_id, now, &metadata);
+        let metadata = self.store.variants.resolve(handle).cloned().unwrap_or(metadata);
+        self.store.insert_metadata(handle, metadata);
+        self.store.add_provider_handle(&provider, handle);
+        self.store.coverage.mark(handle, provider_id, now);
+      }
+    }
+
+    let evicted = self.store.enforce_limits(now, &mut hits);
+    let mut report = watch_report(&hits);
+    if !evicted.is_empty() {
+      report["evicted"] = evictions_value(&evicted);
+      self.finish_removals(&mut report);
+    }
+    self.finish_diff(&mut report);
+    Ok(report)
+  }
+
+  /// Removes confirmed transactions from all native indexes.
+  ///
+  /// Handles are tombstoned instead of reindexing `txids`, matching the JS store
+  /// design where handle slots are stable and `txIds()` skips removed entries.
+  /// Tombstones are reclaimed by `compact()`, automatically once they exceed
+  /// the configured share of all handles.
+  ///
+  /// With `evict_descendants`, the txids are treated as dropped or replaced
+  /// rather than confirmed: their in-mempool descendants are evicted as well,
+  /// because they can no longer be mined, and they are not sampled by the fee
+  /// estimator.
+  ///
+  /// Returns `{ watchHits }`: `txConfirmed` for watched removed txids, or
+  /// `txDropped` (`dropped`) with `evict_descendants`, and `txDropped`
+  /// (`descendant`) for watched evicted descendants, plus `diff` with
+  /// `report_diff`.
+  pub fn remove_txids(
+    &mut self,
+    txids: Vec<String>,
+    evict_descendants: Option<bool>,
+    report_diff: Option<bool>,
+  ) -> Result<Value> {
+    self.remove_txids_impl(txids, evict_descendants.unwrap_or(false), report_diff)
+  }
+
+  #[napi]
+  #[allow(non_snake_case)]
+  pub fn removeTxids(
+    &mut self,
+    txids: Vec<String>,
+    evict_descendants: Option<bool>,
+    report_diff: Option<bool>,
+  ) -> Result<Value> {
+    self.remove_txids_impl(txids, evict_descendants.unwrap_or(false), report_diff)
+  }
+
+  fn remove_txids_impl(
+    &mut self,
+    txids: Vec<String>,
+    evict_descendants: bool,
+    report_diff: Option<bool>,
+  ) -> Result<Value> {
+    let confirmed_at = now_ms();
+    let mut hits = Vec::new();
+    self.begin_diff(report_diff);
+    let handles: Vec<u32> = txids.iter().filter_map(|txid| self.store.handle_of_txid(txid)).collect();
+    let descendants = if evict_descendants { self.store.descendants_of_all(&handles) } else { Vec::new() };
+
+    let reason = evict_descendants.then_some(DropReason::Dropped);
+    for handle in handles {
+      // A repeated txid finds no records the second time, so it is sampled once.
+      if !evict_descendants {
+        self.store.record_confirmation(handle, confirmed_at);
+      }
+      self.store.drop_handle_watched(handle, reason, &mut hits);
+    }
+    for handle in descendants {
+      self.store.drop_handle_watched(handle, Some(DropReason::Descendant), &mut hits);
+    }
+
+    let mut report = watch_report(&hits);
+    self.finish_removals(&mut report);
+    self.finish_diff(&mut report);
+    Ok(report)
+  }
+
+  /// Starts collecting a `MutationDiff` for the running mutation when the
+  /// caller asked for one.
+  fn begin_diff(&mut self, report_diff: Option<bool>) {
+    self.store.diff = report_diff.unwrap_or(false).then(MutationDiff::default);
+  }
+
+  /// Adds the collected diff, if any, to a mutation report as `diff`, net of
+  /// txids the mutation both added and removed.
+  fn finish_diff(&mut self, report: &mut Value) {
+    if let Some(mut diff) = self.store.diff.take() {
+      diff.net_out();
+      report["diff"] = diff.to_value();
+    }
+  }
+
+  /// Prunes provider lists after a batch of drops and runs automatic
+  /// compaction when the tombstone ratio is exceeded, reporting it as
+  /// `compaction` in the mutation result.
+  fn finish_removals(&mut self, report: &mut Value) {
+    self.store.prune_provider_lists();
+    if let Some(stats) = self.store.maybe_compact() {
+      report["compaction"] = stats.to_value();
+    }
+  }
+
+  /// Stores one loaded transaction unless `key` is already loaded, evicting
+  /// the entries it replaces. `raw` is stored instead of `transaction` when
+  /// given.
+  fn load_transaction(
+    &mut self,
+    key: TxKey,
+    transaction: LightTransaction,
+    raw: Option<StoredTransaction>,
+    provider_name: Option<&str>,
+    timestamp: u64,
+    outcome: &mut LoadOutcome,
+  ) {
+    let handle = self.store.ensure_handle_watched(key, &mut outcome.hits);
+
+    if self.store.load_tracker.contains_key(&handle) {
+      return;
+    }
+
+    let load = LoadInfo {
+      timestamp,
+      fee_rate: transaction.fee_rate.unwrap_or(0.0),
+      provider: provider_name.map(|name| self.store.provider_names.intern(name)),
+    };
+
+    let clashes = self.store.conflicting_spenders(transaction.spent_outpoints(), Some(handle));
+    if !clashes.is_empty() {
+      let mut direct: Vec<u32> = clashes.iter().map(|(_, spender)| *spender).collect();
+      direct.sort_unstable();
+      direct.dedup();
+      let descendants = self.store.descendants_of_all(&direct);
+
+      for (outpoint, spender) in &clashes {
+        outcome.conflicts.push(json!({
+          "outpoint": outpoint_to_string(*outpoint),
+          "txid": txid_to_hex(key),
+          "replacedTxid": self.store.txid_of(*spender).map(txid_to_hex),
+        }));
+      }
+      for spender in direct {
+        if self.store.drop_handle_watched(spender, Some(DropReason::Replaced), &mut outcome.hits) {
+          outcome.replaced.extend(self.store.txid_of(spender).map(txid_to_hex));
+        }
+      }
+      for descendant in descendants.into_iter().filter(|d| *d != handle) {
+        if self.store.drop_handle_watched(descendant, Some(DropReason::Descendant), &mut outcome.hits) {
+          outcome.evicted_descendants.extend(self.store.txid_of(descendant).map(txid_to_hex));
+        }
+      }
+    }
+
+    self.store.watchlist.match_loaded(key, &transaction, &mut outcome.hits);
+    self.store.insert_loaded(handle, raw.unwrap_or_else(|| transaction.into()), load);
+  }
+
+  /// Enforces limits after a batch of loads and builds the `recordLoaded`
+  /// report.
+  fn finish_loads(&mut self, outcome: LoadOutcome, timestamp: u64) -> Value {
+    let mut hits = outcome.hits;
+    let evicted = self.store.enforce_limits(timestamp, &mut hits);
+    let removed_any = !outcome.replaced.is_empty() || !evicted.is_empty();
+    let mut report = json!({
+      "replaced": outcome.replaced,
+      "evictedDescendants": outcome.evicted_descendants,
+      "conflicts": outcome.conflicts,
+      "watchHits": watch_hits_value(&hits),
+    });
+    if !evicted.is_empty() {
+      report["evicted"] = evictions_value(&evicted);
+    }
+    if removed_any {
+      self.finish_removals(&mut report);
+    }
+    self.finish_diff(&mut report);
+    report
+  }
+
+  /// Reconciles the store with a newly connected block in one pass.
+  ///
+  /// `block` is either `{ txids, spentOutpoints }` (`txid:vout` strings) or a
+  /// block-like `{ tx: [{ txid, vin: [{ txid, vout }] }] }`. Mempool entries
+  /// included in the block are removed as confirmed; entries spending an
+  /// outpoint the block spent are evicted as conflicted, together with their
+  /// descendants. Descendants of confirmed entries stay in the mempool.
+  ///
+  /// Returns `{ confirmed, conflicted, evictedDescendants }` txids plus the
+  /// `watchHits` they produced, and `diff` with `report_diff`.
+  ///
+  /// Complexity: O(T + S + D) for T block txids, S spent outpoints and D
+  /// evicted descendants.
+  #[napi(js_name = "applyBlock")]
+  pub fn apply_block(&mut self, block: Value, report_diff: Option<bool>) -> Result<Value> {
+    let (block_txids, spent) = parse_block_spends(&block)?;
+    let confirmed_at = now_ms();
+    self.begin_diff(report_diff);
+
+    let mut confirmed: Vec<u32> =
+      block_txids.iter().filter_map(|key| self.store.txid_to_handle.get(key).copied()).collect();
+    confirmed.sort_unstable();
+    confirmed.dedup();
+    let confirmed_set: HashSet<u32> = confirmed.iter().copied().collect();
+
+    let mut conflicted: Vec<u32> = self
+      .store
+      .conflicting_spenders(spent.into_iter(), None)
+      .into_iter()
+      .map(|(_, spender)| spender)
+      .filter(|spender| !confirmed_set.contains(spender))
+      .collect();
+    conflicted.sort_unstable();
+    conflicted.dedup();
+
+    let descendants: Vec<u32> =
+      self.store.descendants_of_all(&conflicted).into_iter().filter(|handle| !confirmed_set.contains(handle)).collect();
+
+    let mut report = json!({
+      "confirmed": self.store.hex_txids(&confirmed),
+      "conflicted": self.store.hex_txids(&conflicted),
+      "evictedDescendants": self.store.hex_txids(&descendants),
+    });
+
+    let mut hits = Vec::new();
+    for handle in confirmed {
+      self.store.record_confirmation(handle, confirmed_at);
+      self.store.drop_handle_watched(handle, None, &mut hits);
+    }
+    for handle in conflicted {
+      self.store.drop_handle_watched(handle, Some(DropReason::Conflicted), &mut hits);
+    }
+    for handle in descendants {
+      self.store.drop_handle_watched(handle, Some(DropReason::Descendant), &mut hits);
+    }
+
+    report["watchHits"] = watch_hits_value(&hits);
+    self.finish_removals(&mut report);
+    self.finish_diff(&mut report);
+    Ok(report)
+  }
+
+  #[napi]
+  pub fn providers(&self) -> Vec<String> {
+    self.store.provider_tx.keys().cloned().collect()
+  }
+
+  /// Selects txids that still need a full/slim transaction load for one provider.
+  ///
+  /// Txids come in the order set by `setPendingOrder`: provider list order by
+  /// default, otherwise from the native pending queue of that provider.
+  /// Txids this provider failed to load (`recordLoadFailed`) are skipped while
+  /// in backoff, and for good once it reached the policy's `maxFailures` and
+  /// another provider that has not yet exhausted its attempts lists them.
+  ///
+  /// Complexity: O(K) in that provider's handle list, with early stop after
+  /// `limit` pending txids. The method only checks compact handles and avoids
+  /// scanning the whole global mempool when a provider-specific list exists.
+  /// With a pending order, only queued (unloaded) handles are walked, so the
+  /// cost is O(limit + S) for S skipped txids in backoff.
+  /// Each exhausted txid adds a fallback lookup over the other providers' lists.
+  #[napi(js_name = "pendingTxids")]
+  pub fn pending_txids(&self, provider_name: String, limit: f64) -> Vec<String> {
+    let mut out = Vec::new();
+    let limit = limit.max(0.0) as usize;
+
+    if limit == 0 {
+      return out;
+    }
+
+    let now = now_ms();
+    let provider = self.store.provider_names.id_of(&provider_name);
+
+    let handles: Box<dyn Iterator<Item = u32> + '_> = if self.store.pending.is_active() {
+      Box::new(self.store.pending.iter(&provider_name))
+    } else {
+      Box::new(self.store.provider_tx.get(&provider_name).into_iter().flatten().copied())
+    };
+    for handle in handles {
+      if out.len() >= limit {
+        break;
+      }
+
+      if !self.store.is_pending_for(handle, &provider_name, provider, now) {
+        continue;
+      }
+
+      if let Some(key) = self.store.txids.get(handle as usize) {
+        out.push(txid_to_hex(*key));
+      }
+    }
+
+    if let Some(handles) = self.store.fallback_tx.get(&provider_name) {
+      for handle in handles {
+        if out.len() >= limit {
+          break;
+        }
+
+        if self.store.load_tracker.contains_key(handle) || !self.store.load_exhausted(*handle) {
+          continue;
+        }
+
+        if let (Some(provider), Some(failures)) = (provider, self.store.load_failures.get(handle)) {
+          if failures.in_backoff(provider, now) || failures.attempts(provider) >= self.store.retry_policy.max_failures {
+            continue;
+          }
+        }
+
+        if let Some(key) = self.store.txids.get(*handle as usize) {
+          out.push(txid_to_hex(*key));
+        }
+      }
+    }
+
+    out
+  }
+
+  /// Records loaded transactions returned by TS provider calls.
+  ///
+  /// Complexity: O(M) for M loaded items. Each txid is resolved to a compact
+  /// handle, the transaction object is converted once into a typed
+  /// `LightTransaction` and stored by handle together with its load info.
+  /// Duplicate loads are ignored, preserving the first successful load info.
+  ///
+  /// A loaded transaction that spends an outpoint already spent by another
+  /// entry is treated as its replacement (double spend or RBF): the older
+  /// spender and its descendants are evicted and reported as
+  /// `{ replaced, evictedDescendants, conflicts: [{ outpoint, txid, replacedTxid }] }`.
+  /// `watchHits` lists payments to watched scripts, spends of watched
+  /// outpoints and watched txids that were seen or replaced. With
+  /// `report_diff`, `diff` lists the txids this call added, evicted and loaded.
+  /// Entries evicted by `setLimits` bounds are listed as `evicted`.
+  #[napi(js_name = "recordLoaded")]
+  pub fn record_loaded(&mut self, loaded_transactions: Vec<Value>, report_diff: Option<bool>) -> Result<Value> {
+    let timestamp = now_ms();
+    self.begin_diff(report_diff);
+    let mut outcome = LoadOutcome::default();
+
+    for item in loaded_transactions {
+      let Some(key) = string_field(&item, "txid").and_then(parse_txid) else {
+        continue;
+      };
+      let Some(transaction) = item.get("transaction").and_then(|tx| LightTransaction::from_value(tx, key)) else {
+        continue;
+      };
+      self.load_transaction(key, transaction, None, string_field(&item, "providerName"), timestamp, &mut outcome);
+    }
+
+    Ok(self.finish_loads(outcome, timestamp))
+  }
+
+  /// Records one transaction loaded as serialized bytes, keeping the bytes
+  /// (typically 200-400 B) and the input and output offsets the indexes need
+  /// instead of the typed record `recordLoaded` builds, which costs several
+  /// times more. `getFullTransaction` and the other readers decode it to the
+  /// `LightTransaction` shape on demand; snapshots and deltas carry the bytes
+  /// (`rawTransactions`, `rawTransaction`). Raw bytes carry no `prevout` or
+  /// `feeRate`.
+  ///
+  /// The bytes must decode to exactly one transaction (segwit or legacy
+  /// serialization) whose txid is `txid`. Returns the `recordLoaded` report.
+  ///
+  /// Complexity: O(B) for B bytes, plus `recordLoaded`'s conflict handling.
+  #[napi(js_name = "recordLoadedRaw")]
+  pub fn record_loaded_raw(
+    &mut self,
+    txid: String,
+    raw_bytes: Buffer,
+    provider_name: Option<String>,
+    report_diff: Option<bool>,
+  ) -> Result<Value> {
+    self.record_loaded_raw_bytes(&txid, &raw_bytes, provider_name.as_deref(), report_diff)
+  }
+
+  pub fn record_loaded_raw_bytes(
+    &mut self,
+    txid: &str,
+    raw_bytes: &[u8],
+    provider_name: Option<&str>,
+    report_diff: Option<bool>,
+  ) -> Result<Value> {
+    let key = parse_txid(txid).ok_or_else(|| Error::from_reason("Invalid txid"))?;
+    let (raw, decoded) = RawTransaction::parse(key, raw_bytes)?;
+
+    let timestamp = now_ms();
+    self.begin_diff(report_diff);
+    let mut outcome = LoadOutcome::default();
+    let transaction = LightTransaction::from_raw(&decoded, None);
+    self.load_transaction(key, transaction, Some(raw.into()), provider_name, timestamp, &mut outcome);
+    Ok(self.finish_loads(outcome, timestamp))
+  }
+
+  /// Sets the order `pendingTxids` offers txids in: `insertion` (default,
+  /// provider list order), `feeRate` (highest first), `firstSeen` (oldest
+  /// provider `time` first) or `watchedFirst` (watched txids and txids whose
+  /// metadata `depends` on one, then insertion order; pending transactions
+  /// are not loaded yet, so their scripts cannot be matched).
+  ///
+  /// Ties keep insertion order. Switching rebuilds the pending queues,
+  /// O(K log N) for K provider list entries.
+  #[napi(js_name = "setPendingOrder")]
+  pub fn set_pending_order(&mut self, order: String) -> Result<()> {
+    let order = PendingOrder::parse(&order).ok_or_else(|| {
+      Error::from_reason("Unsupported pending order. Expected 'insertion', 'feeRate', 'firstSeen' or 'watchedFirst'.")
+    })?;
+    self.store.pending.set_order(order);
+    self.store.rebuild_pending();
+    Ok(())
+  }
+
+  #[napi(js_name = "getPendingOrder")]
+  pub fn get_pending_order(&self) -> String {
+    self.store.pending.order().as_str().to_string()
+  }
+
+  /// `{ [provider]: firstSeenMs }` for every provider that reported `txid`,
+  /// or null for unknown txids.
+  #[napi(js_name = "getProviderFirstSeen")]
+  pub fn get_provider_first_seen(&self, txid: String) -> Option<Value> {
+    let handle = self.store.handle_of_txid(&txid)?;
+    let mut out = serde_json::Map::new();
+    for (provider, first_seen) in self.store.coverage.first_seen(handle) {
+      if let Some(name) = self.store.provider_names.name(*provider) {
+        out.insert(name.to_string(), json!(first_seen));
+      }
+    }
+    Some(Value::Object(out))
+  }
+
+  /// Coverage of the txids providers reported:
+  /// `{ providers, total, seen, unique, missing, overlap }`.
+  ///
+  /// `total` counts txids at least one provider reported; `seen`, `unique`
+  /// (reported by that provider only) and `missing` (reported by others but
+  /// not by it) map provider names to counts. `overlap[i][j]` counts txids
+  /// reported by both `providers[i]` and `providers[j]`.
+  ///
+  /// Complexity: O(N * P^2) for N txids and P providers.
+  #[napi(js_name = "getProviderCoverage")]
+  pub fn get_provider_coverage(&self) -> Value {
+    let names = self.store.provider_names.names();
+    let stats = self.store.coverage.stats(names.len());
+    let per_provider = |counts: &[u64]| -> serde_json::Map<String, Value> {
+      names.iter().zip(counts).map(|(name, count)| (name.clone(), json!(count))).collect()
+    };
+    let missing: Vec<u64> = stats.seen.iter().map(|seen| stats.total - seen).collect();
+    json!({
+      "providers": names,
+      "total": stats.total,
+      "seen": per_provider(&stats.seen),
+      "unique": per_provider(&stats.unique),
+      "missing": per_provider(&missing),
+      "overlap": stats.overlap,
+    })
+  }
+
+  /// Txids reported by `provider_name` and no other provider, up to `limit`.
+  #[napi(js_name = "getUniqueTxids")]
+  pub fn get_unique_txids(&self, provider_name: String, limit: Option<f64>) -> Vec<String> {
+    let Some(provider) = self.store.provider_names.id_of(&provider_name) else {
+      return Vec::new();
+    };
+    let limit = limit.map_or(usize::MAX, |limit| limit.max(0.0) as usize);
+    self.store.hex_txids(&self.store.coverage.unique_to(provider).take(limit).collect::<Vec<_>>())
+  }
+
+  /// Median propagation delays between providers: `{ providers, delays, lag }`.
+  ///
+  /// `delays[i][j]` is the median of `firstSeen(j) - firstSeen(i)` in
+  /// milliseconds over txids both reported (null when none); a positive value
+  /// means `providers[j]` tends to see transactions after `providers[i]`.
+  /// `lag` maps each provider to its median delay behind the first provider
+  /// that reported each of its txids. First-seen times are store clock times
+  /// of the snapshot or merge that carried the txid, so resolution is bounded
+  /// by the polling interval.
+  ///
+  /// Complexity: O(N * P^2) time and memory for N txids and P providers.
+  #[napi(js_name = "getPropagationDelays")]
+  pub fn get_propagation_delays(&self) -> Value {
+    let names = self.store.provider_names.names();
+    let stats = self.store.coverage.propagation(names.len());
+    let lag: serde_json::Map<String, Value> =
+      names.iter().zip(&stats.lag).map(|(name, lag)| (name.clone(), json!(lag))).collect();
+    json!({ "providers": names, "delays": stats.delays, "lag": lag })
+  }
+
+  /// Records that `provider_name` failed to return `txids` (e.g. the tx was
+  /// evicted from that node). Each failure pushes the txid's next attempt on
+  /// that provider out exponentially (see `setLoadRetryPolicy`); unknown or
+  /// already loaded txids are ignored.
+  ///
+  /// Returns `[{ txid, attempts, retryAt, exhausted }]`, where `exhausted`
+  /// means the provider reached `maxFailures` and `pendingTxids` will leave
+  /// the txid to another provider that lists it.
+  #[napi(js_name = "recordLoadFailed")]
+  pub fn record_load_failed(
+    &mut self,
+    txids: Vec<String>,
+    provider_name: String,
+    reason: Option<String>,
+  ) -> Vec<Value> {
+    let now = now_ms();
+    let provider = self.store.provider_names.intern(&provider_name);
+    let policy = self.store.retry_policy;
+    let mut out = Vec::new();
+
+    for txid in txids {
+      let Some(handle) = self.store.handle_of_txid(&txid) else {
+        continue;
+      };
+      if self.store.load_tracker.contains_key(&handle) {
+        continue;
+      }
+      let failures = self.store.load_failures.entry(handle).or_default();
+      let (attempts, retry_at) = failures.record(provider, reason.as_deref(), now, &policy);
+      out.push(json!({
+        "txid": txid,
+        "attempts": attempts,
+        "retryAt": retry_at,
+        "exhausted": attempts >= policy.max_failures,
+      }));
+    }
+
+    out
+  }
+
+  /// Sets `{ baseDelayMs?, maxDelayMs?, maxFailures? }`; missing fields keep
+  /// their current value. The delay after the n-th failure on a provider is
+  /// `baseDelayMs * 2^(n-1)`, capped at `maxDelayMs`.
+  #[napi(js_name = "setLoadRetryPolicy")]
+  pub fn set_load_retry_policy(&mut self, policy: Value) {
+    let current = self.store.retry_policy;
+    let field = |name: &str| policy.get(name).and_then(Value::as_f64).map(|value| value.max(0.0) as u64);
+    self.store.retry_policy = RetryPolicy {
+      base_delay_ms: field("baseDelayMs").unwrap_or(current.base_delay_ms),
+      max_delay_ms: field("maxDelayMs").unwrap_or(current.max_delay_ms),
+      max_failures: field("maxFailures")
+        .map(|max| max.clamp(1, u32::MAX as u64) as u32)
+        .unwrap_or(current.max_failures),
+    };
+  }
+
+  /// `{ reason, providers: [{ provider, attempts, retryAt }] }` for a txid
+  /// with recorded load failures, or null.
+  #[napi(js_name = "getLoadFailures")]
+  pub fn get_load_failures(&self, txid: String) -> Option<Value> {
+    let handle = self.store.handle_of_txid(&txid)?;
+    self.store.load_failures.get(&handle).map(|failures| failures.to_value(&self.store.provider_names))
+  }
+
+  /// Txid of the mempool entry spending `outpoint` (`txid:vout`), if any.
+  #[napi(js_name = "getOutpointSpender")]
+  pub fn get_outpoint_spender(&self, outpoint: String) -> Option<String> {
+    let outpoint = parse_outpoint(&outpoint)?;
+    let spender = self.store.spends.spender(&outpoint)?;
+    self.store.txid_of(spender).map(txid_to_hex)
+  }
+
+  /// Mempool txids that conflict with the given spent outpoints, e.g. the
+  /// inputs of a block transaction or of a transaction that was not loaded.
+  /// Does not mutate the store.
+  #[napi(js_name = "findConflicts")]
+  pub fn find_conflicts(&self, outpoints: Vec<String>) -> Vec<String> {
+    let mut handles: Vec<u32> = self
+      .store
+      .conflicting_spenders(outpoints.iter().filter_map(|o| parse_outpoint(o)), None)
+      .into_iter()
+      .map(|(_, spender)| spender)
+      .collect();
+    handles.sort_unstable();
+    handles.dedup();
+    self.store.hex_txids(&handles)
+  }
+
+  #[napi(js_name = "txIds")]
+  pub fn tx_ids(&self) -> Vec<String> {
+    self
+      .store
+      .txids
+      .iter()
+      .enumerate()
+      .filter(|(handle, _)| !self.store.removed_handles.contains(&(*handle as u32)))
+      .map(|(_, key)| txid_to_hex(*key))
+      .collect()
+  }
+
+  #[napi(js_name = "loadedTransactions")]
+  pub fn loaded_transactions(&self) -> Vec<Value> {
+    self
+      .store
+      .transactions
+      .iter()
+      .filter_map(|(h, tx)| self.store.txids.get(*h as usize).and_then(|key| tx.to_value(*key)))
+      .collect()
+  }
+
+  #[napi]
+  pub fn metadata(&self) -> Vec<Value> {
+    self
+      .store
+      .metadata
+      .iter()
+      .filter_map(|(h, md)| self.store.txids.get(*h as usize).map(|key| md.to_value(*key)))
+      .collect()
+  }
+
+  /// One page of `kind` (`txIds`, `metadata` or `loadedTransactions`):
+  /// `{ items, nextCursor }`, with `nextCursor` null once the end is reached.
+  /// Pass null as `cursor` to start.
+  ///
+  /// Pages walk handles in insertion order, so iteration stays consistent
+  /// across mutations between calls: an entry present for the whole
+  /// iteration is returned exactly once, removed entries are skipped and
+  /// added ones come last. Compactions are translated (see `CursorEpochs`);
+  /// a full replace such as `applySnapshot` makes older cursors fail as
+  /// stale, and the caller restarts.
+  ///
+  /// Complexity: O(pageSize) per page; each page visits at most
+  /// `scan_budget(pageSize)` handles, so a page of a sparse kind may hold
+  /// fewer than `pageSize` items while `nextCursor` is still set.
+  #[napi]
+  pub fn iterate(&self, kind: String, cursor: Option<String>, page_size: f64) -> Result<Value> {
+    let kind = PageKind::parse(&kind).ok_or_else(|| {
+      Error::from_reason("Unsupported iteration kind. Expected 'txIds', 'metadata' or 'loadedTransactions'.")
+    })?;
+    let start = match cursor.as_deref() {
+      None => 0,
+      Some(cursor) => self.store.cursors.resolve(cursor).map_err(|error| match error {
+        CursorError::Invalid => Error::from_reason("Invalid mempool cursor"),
+        CursorError::Stale => Error::from_reason("Stale mempool cursor; restart the iteration"),
+      })?,
+    };
+    let page_size = (page_size.max(1.0) as usize).min(MAX_PAGE_SIZE);
+    let end = self.store.txids.len() as u32;
+    let stop = start.saturating_add(scan_budget(page_size) as u32).min(end);
+
+    let mut items = Vec::new();
+    let mut handle = start;
+    while handle < stop && items.len() < page_size {
+      let key = self.store.txids[handle as usize];
+      let item = match kind {
+        PageKind::TxIds => (self.store.txid_to_handle.get(&key) == Some(&handle)).then(|| json!(txid_to_hex(key))),
+        PageKind::Metadata => self.store.metadata.get(&handle).map(|md| md.to_value(key)),
+        PageKind::LoadedTransactions => self.store.transactions.get(&handle).and_then(|tx| tx.to_value(key)),
+      };
+      items.extend(item);
+      handle += 1;
+    }
+
+    let next_cursor = (handle < end).then(|| self.store.cursors.cursor(handle));
+    Ok(json!({ "items": items, "nextCursor": next_cursor }))
+  }
+
+  #[napi(js_name = "hasTransaction")]
+  pub fn has_transaction(&self, txid: String) -> bool {
+    self.store.handle_of_txid(&txid).is_some()
+  }
+
+  #[napi(js_name = "isTransactionLoaded")]
+  pub fn is_transaction_loaded(&self, txid: String) -> bool {
+    self.store.handle_of_txid(&txid).map(|h| self.store.load_tracker.contains_key(&h)).unwrap_or(false)
+  }
+
+  #[napi(js_name = "getTransactionMetadata")]
+  pub fn get_transaction_metadata(&self, txid: String) -> Option<Value> {
+    let key = parse_txid(&txid)?;
+    let handle = self.store.txid_to_handle.get(&key)?;
+    self.store.metadata.get(handle).map(|md| md.to_value(key))
+  }
+
+  /// Configures per-provider metadata variants:
+  /// `{ policy?: 'default' | 'latest' | 'quorum' | 'highestFee', retainVariants? }`.
+  ///
+  /// `default` keeps today's behaviour (first provider wins in `applySnapshot`,
+  /// `mergeSnapshot` overwrites) and retains variants only with
+  /// `retainVariants`, e.g. to inspect disagreements. The other policies
+  /// always retain them and pick each txid's metadata from its variants:
+  /// the latest report, the report most providers agree on (node-local
+  /// fields such as `time` and descendant stats are ignored), or the highest
+  /// fee. Variants are collected from later snapshots and merges; switching
+  /// policy reconciles those already kept, O(V) for V variants.
+  #[napi(js_name = "setMetadataPolicy")]
+  pub fn set_metadata_policy(&mut self, policy: Value) -> Result<()> {
+    let reconcile = match policy.get("policy").and_then(Value::as_str) {
+      Some(name) => ReconcilePolicy::parse(name).ok_or_else(|| {
+        Error::from_reason("Unsupported metadata policy. Expected 'default', 'latest', 'quorum' or 'highestFee'.")
+      })?,
+      None => self.store.variants.policy(),
+    };
+    let retain = policy.get("retainVariants").and_then(Value::as_bool).unwrap_or(false);
+    self.store.variants.configure(reconcile, retain);
+    for handle in self.store.variants.handles() {
+      self.store.reconcile_metadata(handle);
+    }
+    Ok(())
+  }
+
+  /// `{ policy, retainVariants }`.
+  #[napi(js_name = "getMetadataPolicy")]
+  pub fn get_metadata_policy(&self) -> Value {
+    self.store.variants.to_value()
+  }
+
+  /// `{ txid, policy, distinct, variants: [{ provider, reportedAt, metadata }] }`
+  /// with every provider's last metadata report, oldest first, or null for
+  /// unknown txids. `distinct` > 1 means the providers disagree. Empty unless
+  /// variants are retained (see `setMetadataPolicy`).
+  #[napi(js_name = "getMetadataVariants")]
+  pub fn get_metadata_variants(&self, txid: String) -> Option<Value> {
+    let key = parse_txid(&txid)?;
+    let handle = *self.store.txid_to_handle.get(&key)?;
+    Some(self.store.variants.variants_value(handle, key, &self.store.provider_names))
+  }
+
+  #[napi(js_name = "getFullTransaction")]
+  pub fn get_full_transaction(&self, txid: String) -> Option<Value> {
+    let key = parse_txid(&txid)?;
+    let handle = self.store.txid_to_handle.get(&key)?;
+    self.store.transactions.get(handle).and_then(|tx| tx.to_value(key))
+  }
+
+  /// Returns the `n` highest fee-rate entries as `[{ txid, feeRate, vsize }]`.
+  ///
+  /// Complexity: O(n) over the native fee-rate index.
+  #[napi(js_name = "topByFeeRate")]
+  pub fn top_by_fee_rate(&self, n: u32) -> Vec<Value> {
+    self
+      .store
+      .fee_index
+      .iter_desc()
+      .take(n as usize)
+      .filter_map(|(handle, rate, vsize)| self.store.fee_entry_value(handle, rate, vsize))
+      .collect()
+  }
+
+  /// Returns entries with `min <= feeRate <= max` (sat/vB), highest first,
+  /// optionally capped by `limit`.
+  ///
+  /// Complexity: O(log N + k) for k returned entries.
+  #[napi(js_name = "feeRateRange")]
+  pub fn fee_rate_range(&self, min: f64, max: f64, limit: Option<u32>) -> Vec<Value> {
+    let limit = limit.map(|l| l as usize).unwrap_or(usize::MAX);
+    self
+      .store
+      .fee_index
+      .range_desc(min, max)
+      .take(limit)
+      .filter_map(|(handle, rate, vsize)| self.store.fee_entry_value(handle, rate, vsize))
+      .collect()
+  }
+
+  /// Filters entries with native predicates and returns one sorted page:
+  /// `{ total, items: [{ txid, feeRate, vsize, loaded, metadata, transaction? }] }`.
+  ///
+  /// `filter` takes `{ minFeeRate?, maxFeeRate?, minVsize?, maxVsize?,
+  /// minAncestorCount?, maxAncestorCount?, minAgeMs?, maxAgeMs?,
+  /// bip125Replaceable?, loaded?, provider?, txidPrefix?, sort?, order?,
+  /// offset?, limit?, includeTransactions? }`. Ranges are inclusive; age is
+  /// measured from the provider's `time`, or from the load for entries
+  /// without metadata. `sort` is one of
+  /// `insertion` (default), `feeRate`, `vsize`, `age`, `ancestorCount` or
+  /// `txid`; `order` defaults to `desc` for `feeRate` and `asc` otherwise.
+  /// `total` counts every match before `offset` and `limit` (default 100,
+  /// at most 10000) are applied. `metadata` is null for entries known only
+  /// from a loaded transaction; `transaction` is added with
+  /// `includeTransactions`.
+  ///
+  /// Complexity: O(k log k) for k candidates, where a fee-rate bound limits
+  /// the candidates to that range of the fee-rate index and a `provider`
+  /// filter adds O(P) to collect that provider's list.
+  #[napi]
+  pub fn query(&self, filter: Value) -> Result<Value> {
+    let query = MempoolQuery::from_value(&filter)?;
+    let matched = self.store.query_handles(&query, now_ms());
+    let items: Vec<Value> = matched
+      .iter()
+      .skip(query.offset)
+      .take(query.limit)
+      .filter_map(|(handle, rate, vsize)| {
+        let key = self.store.txid_of(*handle)?;
+        let mut item = json!({
+          "txid": txid_to_hex(key),
+          "feeRate": rate,
+          "vsize": vsize,
+          "loaded": self.store.load_tracker.contains_key(handle),
+          "metadata": self.store.metadata.get(handle).map(|md| md.to_value(key)),
+        });
+        if query.include_transactions {
+          item["transaction"] = json!(self.store.transactions.get(handle).and_then(|tx| tx.to_value(key)));
+        }
+        Some(item)
+      })
+      .collect();
+    Ok(json!({ "total": matched.len(), "items": items }))
+  }
+
+  /// Groups the mempool into fee-rate buckets delimited by `bucket_boundaries`
+  /// (sat/vB) and returns `[{ minFeeRate, maxFeeRate, count, vsize }]`.
+  /// The last bucket is open-ended (`maxFeeRate: null`).
+  #[napi(js_name = "feeHistogram")]
+  pub fn fee_histogram(&self, bucket_boundaries: Vec<f64>) -> Vec<Value> {
+    self
+      .store
+      .fee_index
+      .histogram(&bucket_boundaries)
+      .into_iter()
+      .map(|bucket| {
+        json!({
+          "minFeeRate": bucket.min,
+          "maxFeeRate": bucket.max,
+          "count": bucket.count,
+          "vsize": bucket.vsize,
+        })
+      })
+      .collect()
+  }
+
+  /// Simulates the next `n` blocks from mempool metadata using ancestor-score
+  /// package selection under the 4M weight limit (minus coinbase reserve).
+  ///
+  /// Returns `[{ index, txids, txCount, totalFees, weight, vsize, minFeeRate,
+  /// medianFeeRate, maxFeeRate }]`. Fee rates are effective package rates in
+  /// sat/vB, comparable to mempool.space projected blocks.
+  #[napi(js_name = "projectBlocks")]
+  pub fn project_blocks(&self, n: u32) -> Vec<Value> {
+    project_blocks(self.store.package_nodes(), n as usize)
+      .into_iter()
+      .enumerate()
+      .map(|(index, block)| {
+        let (min, median, max) = block.fee_range();
+        let txids: Vec<String> = block.handles.iter().filter_map(|h| self.store.txid_of(*h)).map(txid_to_hex).collect();
+        json!({
+          "index": index,
+          "txids": txids,
+          "txCount": block.handles.len(),
+          "totalFees": block.total_fees,
+          "weight": block.weight,
+          "vsize": block.vsize,
+          "minFeeRate": min,
+          "medianFeeRate": median,
+          "maxFeeRate": max,
+        })
+      })
+      .collect()
+  }
+
+  /// Estimates the fee rate (sat/vB) needed to confirm within `target_blocks`.
+  ///
+  /// Two signals are combined:
+  /// - projected: the simulated block at position `target_blocks` (via
+  ///   `projectBlocks`). If the mempool spills past that block, its minimum
+  ///   (economical) or median (conservative) package rate is required;
+  ///   otherwise the current mempool clears in time and the minimum relay rate
+  ///   is enough;
+  /// - historical: the lowest fee-rate range whose confirmed txs waited at most
+  ///   `target_blocks * 10min` in the rolling confirmation history.
+  ///
+  /// `mode` is `"economical"` (default, lower of both) or `"conservative"`
+  /// (higher of both). Returns `{ feeRate, targetBlocks, mode, projected,
+  /// historical, samples }`.
+  #[napi(js_name = "estimateFeeRate")]
+  pub fn estimate_fee_rate(&self, target_blocks: u32, mode: Option<String>) -> Result<Value> {
+    let mode = EstimateMode::parse(mode.as_deref())
+      .ok_or_else(|| Error::from_reason("Unsupported fee estimate mode. Expected 'economical' or 'conservative'."))?;
+    let target = target_blocks.max(1);
+
+    // One extra block tells whether the target block is actually contended.
+    let blocks = project_blocks(self.store.package_nodes(), target as usize + 1);
+    let projected = if blocks.len() > target as usize {
+      let (min, median, _) = blocks[target as usize - 1].fee_range();
+      match mode {
+        EstimateMode::Economical => min,
+        EstimateMode::Conservative => median,
+      }
+    } else {
+      MIN_FEE_RATE
+    };
+    let historical = self.store.confirmations.estimate(target, mode);
+
+    Ok(json!({
+      "feeRate": combine_estimates(projected, historical, mode),
+      "targetBlocks": target,
+      "mode": mode.as_str(),
+      "projected": projected,
+      "historical": historical,
+      "samples": self.store.confirmations.len(),
+    }))
+  }
+
+  /// In-mempool ancestors of `txid` derived from loaded transaction prevouts,
+  /// nearest first.
+  #[napi(js_name = "getAncestors")]
+  pub fn get_ancestors(&self, txid: String) -> Vec<String> {
+    self.store.handle_of_txid(&txid).map(|h| self.store.hex_txids(&self.store.graph.ancestors(h))).unwrap_or_default()
+  }
+
+  /// In-mempool descendants of `txid`, nearest first.
+  #[napi(js_name = "getDescendants")]
+  pub fn get_descendants(&self, txid: String) -> Vec<String> {
+    self.store.handle_of_txid(&txid).map(|h| self.store.hex_txids(&self.store.graph.descendants(h))).unwrap_or_default()
+  }
+
+  /// Returns the package `txid` belongs to: its ancestors, itself and its
+  /// descendants, with aggregate fee (sats), vsize and fee rate (sat/vB).
+  /// `ancestorFeeRate` covers ancestors plus `txid` only, which is the rate a
+  /// miner evaluates for CPFP.
+  #[napi(js_name = "getPackage")]
+  pub fn get_package(&self, txid: String) -> Option<Value> {
+    let handle = self.store.handle_of_txid(&txid)?;
+    let ancestors = self.store.graph.ancestors(handle);
+    let descendants = self.store.graph.descendants(handle);
+
+    let sum = |handles: &[u32]| {
+      handles.iter().fold((0u64, 0u64), |(fee, vsize), h| {
+        let (f, v) = self.store.entry_fee_and_vsize(*h);
+        (fee + f, vsize + v)
+      })
+    };
+    let rate = |(fee, vsize): (u64, u64)| if vsize == 0 { 0.0 } else { fee as f64 / vsize as f64 };
+
+    let own = self.store.entry_fee_and_vsize(handle);
+    let (ancestor_fee, ancestor_vsize) = sum(&ancestors);
+    let (descendant_fee, descendant_vsize) = sum(&descendants);
+    let with_ancestors = (ancestor_fee + own.0, ancestor_vsize + own.1);
+    let total = (with_ancestors.0 + descendant_fee, with_ancestors.1 + descendant_vsize);
+
+    Some(json!({
+      "txid": txid_to_hex(self.store.txid_of(handle)?),
+      "ancestors": self.store.hex_txids(&ancestors),
+      "descendants": self.store.hex_txids(&descendants),
+      "fee": total.0,
+      "vsize": total.1,
+      "feeRate": rate(total),
+      "ancestorFeeRate": rate(with_ancestors),
+    }))
+  }
+
+  /// Loaded mempool txids that pay to or spend from `script_hex`.
+  ///
+  /// Spends are attributed when the input carries `prevout` or its funding
+  /// transaction is itself loaded in the store.
+  #[napi(js_name = "getTransactionsByScript")]
+  pub fn get_transactions_by_script(&self, script_hex: String) -> Vec<String> {
+    self.store.script_txids(script_key_from_hex(&script_hex))
+  }
+
+  #[napi(js_name = "getTransactionsByAddress")]
+  pub fn get_transactions_by_address(&self, address: String) -> Vec<String> {
+    self.store.script_txids(self.store.scripts.key_for_address(&address))
+  }
+
+  /// Unconfirmed `{ received, spent, balance, txCount }` for `script_hex`, in
+  /// satoshis. `balance` is `received - spent` and may be negative.
+  #[napi(js_name = "getScriptBalance")]
+  pub fn get_script_balance(&self, script_hex: String) -> Value {
+    self.store.script_balance(script_key_from_hex(&script_hex))
+  }
+
+  #[napi(js_name = "getAddressBalance")]
+  pub fn get_address_balance(&self, address: String) -> Value {
+    self.store.script_balance(self.store.scripts.key_for_address(&address))
+  }
+
+  /// Registers watched `{ scripts, addresses, outpoints, txids }` (script hex,
+  /// `txid:vout` outpoints). Mutations report matches as `watchHits`.
+  #[napi]
+  pub fn watch(&mut self, list: Value) {
+    self.store.watchlist.update(&list, false);
+    self.store.rekey_watched_pending();
+  }
+
+  #[napi]
+  pub fn unwatch(&mut self, list: Value) {
+    self.store.watchlist.update(&list, true);
+    self.store.rekey_watched_pending();
+  }
+
+  #[napi(js_name = "clearWatchlist")]
+  pub fn clear_watchlist(&mut self) {
+    self.store.watchlist.clear();
+    self.store.rekey_watched_pending();
+  }
+
+  #[napi(js_name = "getWatchlist")]
+  pub fn get_watchlist(&self) -> Value {
+    self.store.watchlist.to_value()
+  }
+
+  #[napi(js_name = "getStats")]
+  pub fn get_stats(&self) -> Value {
+    json!({
+      "txids": self.store.txids.len(),
+      "metadata": self.store.metadata.len(),
+      "transactions": self.store.transactions.len(),
+      "providers": self.store.provider_tx.len(),
+      "tombstones": self.store.removed_handles.len(),
+    })
+  }
+
+  /// Renumbers handles and drops tombstones left by removals, returning
+  /// `{ handlesBefore, handlesAfter, tombstonesRemoved, bytesBefore,
+  /// bytesAfter, reclaimedBytes }`.
+  #[napi]
+  pub fn compact(&mut self) -> Value {
+    self.store.compact().to_value()
+  }
+
+  /// Configures automatic compaction after removals: it runs once tombstones
+  /// reach `tombstone_ratio` of all handles and number at least
+  /// `min_tombstones`. A missing or non-positive ratio disables it.
+  #[napi(js_name = "setAutoCompaction")]
+  pub fn set_auto_compaction(&mut self, tombstone_ratio: Option<f64>, min_tombstones: Option<u32>) {
+    self.store.compaction = CompactionPolicy {
+      tombstone_ratio: tombstone_ratio.filter(|ratio| *ratio > 0.0),
+      min_tombstones: min_tombstones.map(|min| min as usize).unwrap_or(self.store.compaction.min_tombstones),
+    };
+  }
+
+  /// Sets capacity limits from `{ maxEntries?, maxVbytes?, maxBytes?, ttlMs? }`;
+  /// missing or zero values disable a bound. `maxBytes` applies to the
+  /// store's per-entry estimate (`usage.bytes` in `getLimits`).
+  ///
+  /// Bounds are enforced by mutations that add entries (`applySnapshot`,
+  /// `mergeSnapshot`, `recordLoaded`) and by `enforceLimits`: the package
+  /// with the lowest descendant fee rate is evicted together with its
+  /// descendants until every bound holds, so a cheap parent paid for by its
+  /// child survives longer than the cheap tx alone. Entries first seen more
+  /// than `ttlMs` ago expire together with their descendants; entries
+  /// without a load timestamp or provider `time` never expire.
+  #[napi(js_name = "setLimits")]
+  pub fn set_limits(&mut self, limits: Value) {
+    self.store.limits = MempoolLimits::from_value(&limits);
+  }
+
+  /// Configured limits plus current `usage: { entries, vbytes, bytes }`.
+  #[napi(js_name = "getLimits")]
+  pub fn get_limits(&self) -> Value {
+    let usage = self.store.usage();
+    let mut out = self.store.limits.to_value();
+    out["usage"] = json!({ "entries": usage.entries, "vbytes": usage.vbytes, "bytes": usage.bytes });
+    out
+  }
+
+  /// Applies TTL expiry and capacity bounds now, e.g. from a timer. An
+  /// explicit time in ms overrides the clock. Returns `{ evicted: [{ txid, reason }], watchHits }`.
+  #[napi(js_name = "enforceLimits")]
+  pub fn enforce_limits(&mut self, now_ms_arg: Option<f64>) -> Value {
+    let now = now_ms_arg.map(|v| v.max(0.0) as u64).unwrap_or_else(now_ms);
+    let mut hits = Vec::new();
+    let evicted = self.store.enforce_limits(now, &mut hits);
+    let mut report = watch_report(&hits);
+    report["evicted"] = evictions_value(&evicted);
+    if !evicted.is_empty() {
+      self.finish_removals(&mut report);
+    }
+    report
+  }
+
+  /// Memory held by this store, per component, in `units` (default MB).
+  ///
+  /// `bytes` is tracked from real container capacities (hash-table buckets,
+  /// `Vec` capacity) plus the deep size of stored records, so it follows the
+  /// actual allocation rather than per-entry constants; allocator headers and
+  /// fragmentation are not included. `processHeap` is the live/peak heap of
+  /// the whole addon from the counting allocator, shared by every store and
+  /// component in the process rather than attributed to this store; it is
+  /// null unless the addon was built with the `counting-allocator` feature.
+  ///
+  /// Complexity: O(N) over stored records.
+  #[napi(js_name = "getMemoryUsage")]
+  pub fn get_memory_usage(&self, units: Option<String>) -> Value {
+    let (unit, factor) = convert_units(units);
+    let conv = |b: usize| (b as f64 / factor * 100.0).round() / 100.0;
+
+    let components = self.store.memory_components();
+    let mut bytes = serde_json::Map::new();
+    for (name, size) in components {
+      bytes.insert(name.into(), json!(conv(size)));
+    }
+    bytes.insert("total".into(), json!(conv(components.iter().map(|(_, size)| size).sum())));
+
+    json!({
+      "unit": unit,
+      "counts": {
+        "txids": self.store.txids.len(),
+        "metadata": self.store.metadata.len(),
+        "transactions": self.store.transactions.len(),
+        "rawTransactions": self.store.transactions.values().filter(|tx| tx.is_raw()).count(),
+        "loaded": self.store.load_tracker.len(),
+        "providers": self.store.provider_tx.len(),
+      },
+      "bytes": bytes,
+      "processHeap": heap_usage().map(|(allocated, peak)| json!({ "allocated": conv(allocated), "peak": conv(peak) })),
+    })
+  }
+
+  #[napi(js_name = "exportSnapshot")]
+  pub fn export_snapshot(&self) -> Value {
+    let txids: Vec<String> = self
+      .store
+      .txids
+      .iter()
+      .enumerate()
+      .filter(|(handle, _)| !self.store.removed_handles.contains(&(*handle as u32)))
+      .map(|(_, key)| txid_to_hex(*key))
+      .collect();
+
+    let provider_tx: Vec<Value> = self
+      .store
+      .provider_tx
+      .iter()
+      .map(|(provider, handles)| {
+        let ids: Vec<String> = handles
+          .iter()
+          .filter(|h| !self.store.removed_handles.contains(h))
+          .filter_map(|h| self.store.txids.get(*h as usize))
+          .map(|key| txid_to_hex(*key))
+          .collect();
+        json!([provider, ids])
+      })
+      .collect();
+
+    let metadata: Vec<Value> = self
+      .store
+      .metadata
+      .iter()
+      .filter(|(h, _)| !self.store.removed_handles.contains(h))
+      .filter_map(|(h, md)| self.store.txids.get(*h as usize).map(|key| json!([txid_to_hex(*key), md.to_value(*key)])))
+      .collect();
+
+    let mut transactions: Vec<Value> = Vec::new();
+    let mut raw_transactions: Vec<Value> = Vec::new();
+    for (handle, tx) in &self.store.transactions {
+      let Some(key) = self.store.txids.get(*handle as usize).filter(|_| !self.store.removed_handles.contains(handle))
+      else {
+        continue;
+      };
+      match tx {
+        StoredTransaction::Raw(raw) => raw_transactions.push(json!([txid_to_hex(*key), hex::encode(raw.bytes())])),
+        tx => transactions.extend(tx.to_value(*key).map(|value| json!([txid_to_hex(*key), value]))),
+      }
+    }
+
+    let load_tracker: Vec<Value> = self
+      .store
+      .load_tracker
+      .iter()
+      .filter(|(h, _)| !self.store.removed_handles.contains(h))
+      .filter_map(|(h, info)| {
+        let info = info.to_value(&self.store.provider_names);
+        self.store.txids.get(*h as usize).map(|key| json!([txid_to_hex(*key), info]))
+      })
+      .collect();
+
+    json!({
+      "version": 2,
+      "txids": txids,
+      "providerTx": provider_tx,
+      "metadata": metadata,
+      "transactions": transactions,
+      "rawTransactions": raw_transactions,
+      "loadTracker": load_tracker,
+    })
+  }
+
+  /// Exports the same state as `exportSnapshot` in the compact binary format
+  /// described in `snapshot_binary`: each txid is stored once as 32 raw bytes
+  /// and records reference it by index.
+  ///
+  /// Complexity: O(T + R), like the JSON export, without per-txid hex strings.
+  #[napi(js_name = "exportSnapshotBinary")]
+  pub fn export_snapshot_binary(&self) -> Buffer {
+    self.export_snapshot_bytes().into()
+  }
+
+  pub fn export_snapshot_bytes(&self) -> Vec<u8> {
+    let (ids, remap) = self.store.dense_handles();
+    let dense = |handle: &u32| remap.get(*handle as usize).copied().filter(|id| *id != u32::MAX);
+
+    // Load records carry interned provider ids; provider lists are keyed by
+    // name and may include providers that never loaded anything.
+    let mut names: Vec<&str> = self.store.provider_names.names().iter().map(String::as_str).collect();
+    let mut provider_tx = Vec::with_capacity(self.store.provider_tx.len());
+    for (provider, handles) in &self.store.provider_tx {
+      let name = match names.iter().position(|name| name == provider) {
+        Some(position) => position,
+        None => {
+          names.push(provider);
+          names.len() - 1
+        }
+      };
+      provider_tx.push((name as u32, handles.iter().filter_map(dense).collect::<Vec<_>>()));
+    }
+
+    let mut metadata: Vec<(u32, &MempoolTxMetadata)> =
+      self.store.metadata.iter().filter_map(|(h, md)| dense(h).map(|id| (id, md))).collect();
+    let mut transactions: Vec<(u32, &StoredTransaction)> =
+      self.store.transactions.iter().filter_map(|(h, tx)| dense(h).map(|id| (id, tx))).collect();
+    let mut load_tracker: Vec<(u32, LoadInfo)> =
+      self.store.load_tracker.iter().filter_map(|(h, load)| dense(h).map(|id| (id, *load))).collect();
+    metadata.sort_unstable_by_key(|(id, _)| *id);
+    transactions.sort_unstable_by_key(|(id, _)| *id);
+    load_tracker.sort_unstable_by_key(|(id, _)| *id);
+
+    let mut encoder = SnapshotEncoder::new(&ids);
+    encoder.provider_names(names.into_iter());
+    encoder.provider_tx(&provider_tx);
+    encoder.metadata(&metadata);
+    encoder.transactions(&transactions);
+    encoder.load_tracker(&load_tracker);
+    encoder.finish()
+  }
+
+  /// Replaces the store with a buffer produced by `exportSnapshotBinary`.
+  ///
+  /// The header, version and checksum are verified and the whole buffer is
+  /// decoded before the current state is touched, so a damaged buffer leaves
+  /// the store unchanged.
+  #[napi(js_name = "importSnapshotBinary")]
+  pub fn import_snapshot_binary(&mut self, buf: Buffer) -> Result<()> {
+    self.import_snapshot_bytes(&buf)
+  }
+
+  pub fn import_snapshot_bytes(&mut self, buf: &[u8]) -> Result<()> {
+    let snapshot = decode_binary_snapshot(buf)?;
+    self.store.clear();
+
+    let handles: Vec<u32> = snapshot.ids.iter().map(|key| self.store.ensure_handle(*key)).collect();
+    let handle_of = |id: u32| handles.get(id as usize).copied();
+    let provider_ids: Vec<ProviderId> =
+      snapshot.provider_names.iter().map(|name| self.store.provider_names.intern(name)).collect();
+
+    for (name, ids) in snapshot.provider_tx {
+      let Some(provider) = snapshot.provider_names.get(name as usize) else {
+        continue;
+      };
+      let list: HandleList = ids.into_iter().filter_map(handle_of).collect();
+      if !list.is_empty() {
+        self.store.provider_tx.insert(provider.clone(), list);
+      }
+    }
+    for (id, md) in snapshot.metadata {
+      if let Some(handle) = handle_of(id) {
+        self.store.metadata.insert(handle, md);
+      }
+    }
+    for (id, tx) in snapshot.transactions {
+      if let Some(handle) = handle_of(id) {
+        self.store.transactions.insert(handle, tx);
+      }
+    }
+    for (id, mut load) in snapshot.load_tracker {
+      if let Some(handle) = handle_of(id) {
+        load.provider = load.provider.and_then(|provider| provider_ids.get(provider as usize).copied());
+        self.store.load_tracker.insert(handle, load);
+      }
+    }
+
+    self.store.rebuild_indexes();
+    Ok(())
+  }
+
+  #[napi(js_name = "importSnapshot")]
+  pub fn import_snapshot(&mut self, state: Value) -> Result<()> {
+    if state.is_null() {
+      self.store.clear();
+      return Ok(());
+    }
+
+    ensure_snapshot_v2(&state)?;
+    self.store.clear();
+
+    if state == empty_snapshot() {
+      return Ok(());
+    }
+
+    if let Some(Value::Array(txids)) = state.get("txids") {
+      for txid in txids.iter().filter_map(Value::as_str) {
+        if let Some(key) = parse_txid(txid) {
+          self.store.ensure_handle(key);
+        }
+      }
+    }
+
+    if let Some(Value::Array(entries)) = state.get("providerTx") {
+      for entry in entries {
+        let Value::Array(pair) = entry else {
+          continue;
+        };
+        if pair.len() != 2 {
+          continue;
+        }
+
+        let provider = pair[0].as_str().unwrap_or_default().to_string();
+        if provider.is_empty() {
+          continue;
+        }
+
+        let mut handles = HandleList::default();
+
+        if let Value::Array(ids) = &pair[1] {
+          for txid in ids.iter().filter_map(Value::as_str) {
+            if let Some(key) = parse_txid(txid) {
+              handles.push(self.store.ensure_handle(key));
+            }
+          }
+        }
+
+        if !handles.is_empty() {
+          self.store.provider_tx.insert(provider, handles);
+        }
+      }
+    }
+
+    if let Some(Value::Array(entries)) = state.get("metadata") {
+      for entry in entries {
+        self.store.import_pair(entry, PairTarget::Metadata);
+      }
+    }
+
+    if let Some(Value::Array(entries)) = state.get("transactions") {
+      for entry in entries {
+        self.store.import_pair(entry, PairTarget::Transaction);
+      }
+    }
+
+    if let Some(Value::Array(entries)) = state.get("rawTransactions") {
+      for entry in entries {
+        self.store.import_pair(entry, PairTarget::RawTransaction);
+      }
+    }
+
+    if let Some(Value::Array(entries)) = state.get("loadTracker") {
+      for entry in entries {
+        self.store.import_pair(entry, PairTarget::LoadTracker);
+      }
+    }
+
+    self.store.rebuild_indexes();
+
+    Ok(())
+  }
+
+  /// Sequence of the latest change recorded by the store. Pass it to
+  /// `exportDelta` later to get everything that changed since.
+  #[napi(js_name = "getChangeSeq")]
+  pub fn get_change_seq(&self) -> i64 {
+    self.store.journal.seq() as i64
+  }
+
+  /// Txids added, updated and removed after `since_seq`, with their current
+  /// payloads:
+  /// `{ fromSeq, toSeq, added: [entry], updated: [entry], removed: [txid] }`
+  /// where each entry is `{ txid, providers, metadata?, transaction?, load? }`.
+  ///
+  /// Snapshot import and `clear()` reset the journal, and only the most
+  /// recent removals are retained, so a `since_seq` older than the journal
+  /// floor is rejected; the caller must persist a full snapshot instead.
+  ///
+  /// Complexity: O(D log C + P) for D changed txids, C journal entries and P
+  /// provider list entries.
+  #[napi(js_name = "exportDelta")]
+  pub fn export_delta(&self, since_seq: i64) -> Result<Value> {
+    let since = u64::try_from(since_seq).map_err(|_| Error::from_reason("sinceSeq must be non-negative"))?;
+    let journal = &self.store.journal;
+    if since > journal.seq() {
+      return Err(Error::from_reason(format!("sinceSeq {} is ahead of the change journal ({})", since, journal.seq())));
+    }
+    let Some(delta) = journal.since(since) else {
+      return Err(Error::from_reason(format!(
+        "sinceSeq {} predates the change journal (floor {}); export a full snapshot",
+        since,
+        journal.floor()
+      )));
+    };
+
+    let changed: HashSet<u32> = delta
+      .added
+      .iter()
+      .chain(delta.updated.iter())
+      .filter_map(|key| self.store.txid_to_handle.get(key).copied())
+      .collect();
+    let mut providers: HashMap<u32, Vec<&str>> = HashMap::new();
+    for (provider, handles) in &self.store.provider_tx {
+      for handle in handles.iter().filter(|handle| changed.contains(handle)) {
+        providers.entry(*handle).or_default().push(provider);
+      }
+    }
+    let entries =
+      |keys: &[TxKey]| keys.iter().filter_map(|key| self.store.delta_entry_value(*key, &providers)).collect::<Vec<_>>();
+
+    Ok(json!({
+      "fromSeq": since,
+      "toSeq": journal.seq(),
+      "added": entries(&delta.added),
+      "updated": entries(&delta.updated),
+      "removed": delta.removed.iter().map(|key| txid_to_hex(*key)).collect::<Vec<_>>(),
+    }))
+  }
+
+  /// Applies a delta produced by another store's `exportDelta`, e.g. to let a
+  /// replica follow a leader or to replay increments saved after a snapshot.
+  ///
+  /// Removals are applied first, then added and updated entries are upserted
+  /// without conflict eviction: the leader already resolved double spends and
+  /// the evicted txids are part of `removed`. Removals carry no reason and so
+  /// produce no watch hits.
+  ///
+  /// Returns `{ watchHits }` like `mergeSnapshot`, plus `compaction` when the
+  /// removals triggered it.
+  #[napi(js_name = "applyDelta")]
+  pub fn apply_delta(&mut self, delta: Value) -> Result<Value> {
+    if !delta.is_object() {
+      return Err(Error::from_reason("applyDelta expects an exportDelta object"));
+    }
+    let list = |field: &str| delta.get(field).and_then(Value::as_array).into_iter().flatten();
+
+    let mut removed_any = false;
+    let removed: Vec<u32> =
+      list("removed").filter_map(Value::as_str).filter_map(|txid| self.store.handle_of_txid(txid)).collect();
+    for handle in removed {
+      removed_any |= self.store.drop_handle(handle);
+    }
+
+    let mut hits = Vec::new();
+    for entry in list("added").chain(list("updated")) {
+      self.store.apply_delta_entry(entry, &mut hits);
+    }
+
+    let mut report = watch_report(&hits);
+    if removed_any {
+      self.finish_removals(&mut report);
+    }
+    Ok(report)
+  }
+
+  /// Explicitly clears only the native backing store. Domain reset/rollback decisions
+  /// must still be made by the TS aggregate/event-store layer.
+  #[napi]
+  pub fn clear(&mut self) {
+    self.store.reset();
+  }
+
+  /// Releases native memory held by the backing store.
+  ///
+  /// This is a lifecycle/cleanup hook called by the TS owner when the aggregate
+  /// is no longer used. It does not emit events, does not affect persistence and
+  /// does not unload the `.node` addon itself; it only drops/shrinks Rust data
+  /// structures owned by this state store instance.
+  #[napi]
+  pub fn dispose(&mut self) {
+    self.store.dispose();
+  }
+
+  #[napi(js_name = "assertStoreOnly")]
+  pub fn assert_store_only(&self) -> bool {
+    true
+  }
+}
+
+fn watch_hits_value(hits: &[WatchHit]) -> Value {
+  Value::Array(hits.iter().map(WatchHit::to_value).collect())
+}
+
+fn watch_report(hits: &[WatchHit]) -> Value {
+  json!({ "watchHits": watch_hits_value(hits) })
+}
+
+fn remap_records<V>(records: HashMap<u32, V>, live: impl Fn(u32) -> Option<u32>) -> HashMap<u32, V> {
+  records.into_iter().filter_map(|(handle, record)| live(handle).map(|handle| (handle, record))).collect()
+}
+
+/// Stored form of a delta entry's `transaction` or `rawTransaction`, matched
+/// against `watchlist`. Raw bytes that do not decode to `key` are skipped.
+fn delta_transaction(
+  entry: &Value,
+  key: TxKey,
+  watchlist: &Watchlist,
+  hits: &mut Vec<WatchHit>,
+) -> Option<StoredTransaction> {
+  if let Some(raw) = string_field(entry, "rawTransaction") {
+    let (raw, decoded) = RawTransaction::parse(key, &hex::decode(raw).ok()?).ok()?;
+    watchlist.match_loaded(key, &LightTransaction::from_raw(&decoded, None), hits);
+    return Some(raw.into());
+  }
+  let tx = entry.get("transaction").and_then(|tx| LightTransaction::from_value(tx, key))?;
+  watchlist.match_loaded(key, &tx, hits);
+  Some(tx.into())
+}
+
+/// Script and value of output `txid:vout` when `txid` is a loaded mempool tx.
+fn funding_output(
+  transactions: &HashMap<u32, StoredTransaction>,
+  txid_to_handle: &HashMap<TxKey, u32>,
+  txid: TxKey,
+  vout: u32,
+) -> Option<(LightScriptPubKey, u64)> {
+  transactions.get(txid_to_handle.get(&txid)?)?.output(vout)
+}
+
+/// Extracts block txids and spent outpoints from either accepted `applyBlock`
+/// input shape. Coinbase inputs carry no prevout and are skipped.
+fn parse_block_spends(block: &Value) -> Result<(Vec<TxKey>, Vec<Outpoint>)> {
+  if !block.is_object() {
+    return Err(Error::from_reason("applyBlock expects a block object"));
+  }
+
+  let mut txids = Vec::new();
+  let mut spent = Vec::new();
+  let strings =
+    |field: &str| block.get(field).and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str);
+
+  txids.extend(strings("txids").filter_map(parse_txid));
+  spent.extend(strings("spentOutpoints").filter_map(parse_outpoint));
+
+  for tx in block.get("tx").and_then(Value::as_array).into_iter().flatten() {
+    if let Some(key) = string_field(tx, "txid").and_then(parse_txid) {
+      txids.push(key);
+    }
+    for vin in tx.get("vin").and_then(Value::as_array).into_iter().flatten() {
+      let prevout = string_field(vin, "txid").and_then(parse_txid);
+      let vout = vin.get("vout").and_then(Value::as_u64).and_then(|vout| u32::try_from(vout).ok());
+      if let (Some(prevout), Some(vout)) = (prevout, vout) {
+        spent.push((prevout, vout));
+      }
+    }
+  }
+
+  Ok((txids, spent))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// One 64-hex txid per letter: `ids(["a", "b"])`.
+  fn ids<const N: usize>(letters: [&str; N]) -> [String; N] {
+    letters.map(|letter| letter.repeat(64))
+  }
+
+  /// Distinct txid for index `i`.
+  fn txid(i: u32) -> String {
+    format!("{:064x}", i + 1)
+  }
+
+  fn meta(txid: &str, fee: u64) -> Value {
+    json!({
+      "txid": txid,
+      "fee": fee,
+      "fees": { "base": fee }
+    })
+  }
+
+  /// Snapshot item for `txid` with `meta(txid, fee)`.
+  fn entry(txid: &str, fee: u64) -> Value {
+    json!({ "txid": txid, "metadata": meta(txid, fee) })
+  }
+
+  /// Snapshot items for `txid(i)` over `range`, 1000 sats each.
+  fn entries(range: std::ops::Range<u32>) -> Vec<Value> {
+    range.map(|i| entry(&txid(i), 1000)).collect()
+  }
+
+  /// Per-provider snapshot with every item listed by `providerA`.
+  fn snapshot(items: impl IntoIterator<Item = Value>) -> Value {
+    json!({ "providerA": items.into_iter().collect::<Vec<_>>() })
+  }
+
+  /// `recordLoaded` item from `providerA` for a 100 vB, 10 sat/vB transaction
+  /// spending `prevouts`.
+  fn spend(txid: &str, prevouts: &[(&str, u32)]) -> Value {
+    let vin: Vec<Value> = prevouts.iter().map(|(prev, vout)| json!({ "txid": prev, "vout": vout })).collect();
+    json!({
+      "txid": txid,
+      "transaction": { "txid": txid, "vsize": 100, "feeRate": 10, "vin": vin },
+      "providerName": "providerA"
+    })
+  }
+
+  /// Legacy one-input spend of `parent:0` paying 50_000 sats to a P2WPKH script.
+  fn raw_spend(parent: &str, sequence: &str) -> Vec<u8> {
+    let mut prevout = hex::decode(parent).unwrap();
+    prevout.reverse();
+    hex::decode(format!(
+      "0200000001{}0000000000{sequence}0150c3000000000000160014{}00000000",
+      hex::encode(prevout),
+      "22".repeat(20)
+    ))
+    .unwrap()
+  }
+
+  fn raw_txid(raw: &[u8]) -> String {
+    txid_to_hex(crate::transaction::Transaction::parse(raw).unwrap().txid)
+  }
+
+  /// `txid` fields of an array of entries.
+  fn txids_of(items: &Value) -> Vec<String> {
+    items.as_array().unwrap().iter().map(|item| item["txid"].as_str().unwrap().to_string()).collect()
+  }
+
+  /// Snapshot with order-independent sections sorted, for equality checks.
+  fn normalized(mut snapshot: Value) -> Value {
+    for field in ["txids", "providerTx", "metadata", "transactions", "rawTransactions", "loadTracker"] {
+      snapshot[field].as_array_mut().unwrap().sort_by_key(|item| item.to_string());
+    }
+    snapshot
+  }
+
+  /// One `iterate` page as `(items, nextCursor)`.
+  fn page(store: &NativeMempoolState, kind: &str, cursor: Option<String>, size: f64) -> (Vec<Value>, Option<String>) {
+    let page = store.iterate(kind.into(), cursor, size).unwrap();
+    (page["items"].as_array().unwrap().clone(), page["nextCursor"].as_str().map(str::to_string))
+  }
+
+  #[test]
+  fn native_mempool_merge_snapshot_is_additive() {
+    let [a, b, c] = ids(["a", "b", "c"]);
+    let mut store = NativeMempoolState::new();
+
+    store.apply_snapshot(snapshot([entry(&a, 1000), entry(&b, 2000)]), None).unwrap();
+
+    store
+      .record_loaded(
+        vec![json!({
+          "txid": a,
+          "transaction": { "txid": a, "feeRate": 10 },
+          "providerName": "providerA"
+        })],
+        None,
+      )
+      .unwrap();
+
+    store.merge_snapshot(snapshot([entry(&b, 2500), entry(&c, 3000)]), None).unwrap();
+
+    assert!(store.has_transaction(a.clone()));
+    assert!(store.has_transaction(b.clone()));
+    assert!(store.has_transaction(c.clone()));
+    assert!(store.is_transaction_loaded(a.clone()));
+    assert_eq!(store.get_transaction_metadata(b.clone()).unwrap()["fee"], json!(2500));
+
+    let pending = store.pending_txids("providerA".to_string(), 10.0);
+    assert!(!pending.contains(&a));
+    assert!(pending.contains(&b));
+    assert!(pending.contains(&c));
+  }
+
+  #[test]
+  fn native_mempool_remove_txids_cleans_indexes() {
+    let [a, b] = ids(["a", "b"]);
+    let mut store = NativeMempoolState::new();
+
+    store.apply_snapshot(snapshot([entry(&a, 1000), entry(&b, 2000)]), None).unwrap();
+
+    store
+      .record_loaded(
+        vec![json!({
+          "txid": b,
+          "transaction": { "txid": b, "feeRate": 20 },
+          "providerName": "providerA"
+        })],
+        None,
+      )
+      .unwrap();
+
+    store.remove_txids(vec![b.clone()], None, None).unwrap();
+
+    assert!(store.has_transaction(a.clone()));
+    assert!(!store.has_transaction(b.clone()));
+    assert!(!store.tx_ids().contains(&b));
+    assert!(store.get_transaction_metadata(b.clone()).is_none());
+    assert!(store.get_full_transaction(b.clone()).is_none());
+    assert!(!store.pending_txids("providerA".to_string(), 10.0).contains(&b));
+
+    let snapshot = store.export_snapshot();
+    assert!(!snapshot["txids"].as_array().unwrap().iter().any(|v| v.as_str() == Some(b.as_str())));
+  }
+
+  #[test]
+  fn native_mempool_typed_records_round_trip_through_snapshot() {
+    let [a, b] = ids(["a", "b"]);
+    let mut store = NativeMempoolState::new();
+
+    store
+      .apply_snapshot(
+        json!({
+          "providerA": [{
+            "txid": a,
+            "metadata": {
+              "txid": a,
+              "vsize": 141,
+              "weight": 561,
+              "fee": 2820,
+              "modifiedfee": 2820,
+              "time": 1_700_000_000u64,
+              "height": 820_000,
+              "depends": [b],
+              "ancestorcount": 2,
+              "fees": { "base": 2820, "modified": 2820, "ancestor": 5000, "descendant": 2820 },
+              "bip125_replaceable": true
+            }
+          }]
+        }),
+        None,
+      )
+      .unwrap();
+
+    store
+      .record_loaded(
+        vec![json!({
+          "txid": a,
+          "transaction": {
+            "txid": a,
+            "hash": a,
+            "version": 2,
+            "size": 222,
+            "strippedsize": 113,
+            "vsize": 141,
+            "weight": 561,
+            "locktime": 0,
+            "vin": [{ "txid": b, "vout": 1, "sequence": 4294967293u32 }],
+            "vout": [{
+              "value": 0.0001,
+              "n": 0,
+              "scriptPubKey": { "type": "witness_v0_keyhash", "hex": format!("0014{}", "11".repeat(20)) }
+            }],
+            "feeRate": 20
+          },
+          "providerName": "providerA"
+        })],
+        None,
+      )
+      .unwrap();
+
+    let snapshot = store.export_snapshot();
+    let mut restored = NativeMempoolState::new();
+    restored.import_snapshot(snapshot.clone()).unwrap();
+    assert_eq!(restored.export_snapshot(), snapshot);
+
+    let metadata = restored.get_transaction_metadata(a.clone()).unwrap();
+    assert_eq!(metadata["fee"], json!(2820));
+    assert_eq!(metadata["depends"], json!([b]));
+    assert_eq!(metadata["bip125_replaceable"], json!(true));
+
+    let tx = restored.get_full_transaction(a.clone()).unwrap();
+    assert_eq!(tx["vin"][0]["sequence"], json!(4294967293u32));
+    assert_eq!(tx["vout"][0]["value"], json!(0.0001));
+    assert_eq!(tx["vout"][0]["scriptPubKey"]["type"], json!("witness_v0_keyhash"));
+    assert_eq!(snapshot["loadTracker"][0][1]["providerName"], json!("providerA"));
+  }
+
+  #[test]
+  fn native_mempool_fee_index_tracks_mutations() {
+    let [a, b, c, d] = ids(["a", "b", "c", "d"]);
+    let sized = |txid: &str, fee: u64| json!({ "txid": txid, "fee": fee, "vsize": 100 });
+    let mut store = NativeMempoolState::new();
+
+    store
+      .apply_snapshot(
+        json!({
+          "providerA": [
+            { "txid": a, "metadata": sized(&a, 100) },
+            { "txid": b, "metadata": sized(&b, 500) },
+            { "txid": c, "metadata": sized(&c, 2000) }
+          ]
+        }),
+        None,
+      )
+      .unwrap();
+
+    store.merge_snapshot(snapshot([json!({ "txid": a, "metadata": sized(&a, 3000) })]), None).unwrap();
+    store.remove_txids(vec![c.clone()], None, None).unwrap();
+    store
+      .record_loaded(
+        vec![json!({
+          "txid": d,
+          "transaction": { "txid": d, "vsize": 200, "feeRate": 8 }
+        })],
+        None,
+      )
+      .unwrap();
+
+    let top: Vec<Value> = store.top_by_fee_rate(2).iter().map(|e| e["txid"].clone()).collect();
+    assert_eq!(top, vec![json!(a), json!(d)]);
+
+    let range = store.fee_rate_range(5.0, 10.0, None);
+    assert_eq!(range.len(), 2);
+    assert_eq!(range[0]["txid"], json!(d));
+    assert_eq!(range[1]["txid"], json!(b));
+
+    let histogram = store.fee_histogram(vec![5.0, 10.0]);
+    assert_eq!(histogram.len(), 3);
+    assert_eq!(histogram[0]["count"], json!(0));
+    assert_eq!(histogram[1]["count"], json!(2));
+    assert_eq!(histogram[1]["vsize"], json!(300));
+    assert_eq!(histogram[2]["count"], json!(1));
+    assert_eq!(histogram[2]["maxFeeRate"], Value::Null);
+  }
+
+  #[test]
+  fn native_mempool_fee_rate_range_tolerates_unbounded_and_inverted_bounds() {
+    let [a, b] = ids(["a", "b"]);
+    let sized = |txid: &str, fee: u64| json!({ "txid": txid, "metadata": { "fee": fee, "vsize": 100 } });
+    let mut store = NativeMempoolState::new();
+    store.apply_snapshot(snapshot([sized(&a, 1000), sized(&b, 2000)]), None).unwrap();
+    let range = |min: f64, max: f64| txids_of(&Value::Array(store.fee_rate_range(min, max, None)));
+
+    assert_eq!(range(1.0, f64::INFINITY), vec![b.clone(), a.clone()]);
+    assert_eq!(range(15.0, f64::INFINITY), vec![b.clone()]);
+    assert_eq!(range(f64::NEG_INFINITY, 15.0), vec![a.clone()]);
+    assert!(range(f64::NAN, 15.0).is_empty());
+    assert!(range(1.0, f64::NAN).is_empty());
+    assert!(range(20.0, 10.0).is_empty());
+    assert!(range(f64::INFINITY, f64::INFINITY).is_empty());
+    assert!(range(1.0, f64::NEG_INFINITY).is_empty());
+    assert_eq!(store.query(json!({ "minFeeRate": 15, "maxFeeRate": 5 })).unwrap()["total"], json!(0));
+  }
+
+  #[test]
+  fn native_mempool_project_blocks_uses_ancestor_score_and_weight_limit() {
+    let [parent, child, mid] = ids(["a", "b", "c"]);
+    let mut entries = vec![
+      json!({ "txid": parent, "metadata": {
+        "fee": 100_000, "vsize": 100_000, "weight": 400_000,
+        "ancestorfees": 100_000, "ancestorsize": 100_000
+      }}),
+      json!({ "txid": child, "metadata": {
+        "fee": 5_000_000, "vsize": 100_000, "weight": 400_000, "depends": [parent],
+        "ancestorfees": 5_100_000, "ancestorsize": 200_000
+      }}),
+      json!({ "txid": mid, "metadata": {
+        "fee": 2_000_000, "vsize": 100_000, "weight": 400_000,
+        "ancestorfees": 2_000_000, "ancestorsize": 100_000
+      }}),
+    ];
+    for i in 0..10u8 {
+      let txid = format!("{:02x}", i + 0xd0).repeat(32);
+      entries.push(json!({ "txid": txid, "metadata": {
+        "fee": 1_000_000, "vsize": 100_000, "weight": 400_000,
+        "ancestorfees": 1_000_000, "ancestorsize": 100_000
+      }}));
+    }
+
+    let mut store = NativeMempoolState::new();
+    store.apply_snapshot(snapshot(entries), None).unwrap();
+
+    let blocks = store.project_blocks(3);
+    assert_eq!(blocks.len(), 2);
+
+    // CPFP package (25.5 sat/vB) beats the 20 sat/vB standalone tx, parent first.
+    let first: Vec<Value> = blocks[0]["txids"].as_array().unwrap().clone();
+    assert_eq!(first[0], json!(parent));
+    assert_eq!(first[1], json!(child));
+    assert_eq!(first[2], json!(mid));
+    assert_eq!(blocks[0]["txCount"], json!(9));
+    assert_eq!(blocks[0]["weight"], json!(3_600_000));
+    assert_eq!(blocks[0]["maxFeeRate"], json!(25.5));
+    assert_eq!(blocks[0]["minFeeRate"], json!(10.0));
+    assert_eq!(blocks[1]["txCount"], json!(4));
+  }
+
+  #[test]
+  fn native_mempool_estimate_fee_rate_learns_from_confirmations() {
+    let now_secs = now_ms() / 1000;
+    let mut entries = Vec::new();
+    let mut confirmed = Vec::new();
+    for i in 0..40u32 {
+      let txid = txid(i);
+      // Fast lane: 50 sat/vB seen a minute ago. Slow lane: 2 sat/vB seen 10h ago.
+      let (fee, age) = if i < 20 { (5_000, 60) } else { (200, 36_000) };
+      entries.push(json!({ "txid": txid, "metadata": { "fee": fee, "vsize": 100, "time": now_secs - age } }));
+      confirmed.push(txid);
+    }
+
+    let mut store = NativeMempoolState::new();
+    store.apply_snapshot(snapshot(entries), None).unwrap();
+    store.remove_txids(confirmed, None, None).unwrap();
+
+    let conservative = store.estimate_fee_rate(2, Some("conservative".to_string())).unwrap();
+    assert_eq!(conservative["samples"], json!(40));
+    assert_eq!(conservative["projected"], json!(1.0));
+    let historical = conservative["historical"].as_f64().unwrap();
+    assert!((40.0..=50.0).contains(&historical));
+    assert_eq!(conservative["feeRate"].as_f64().unwrap(), historical);
+
+    let economical = store.estimate_fee_rate(2, None).unwrap();
+    assert_eq!(economical["mode"], json!("economical"));
+    assert_eq!(economical["feeRate"], json!(1.0));
+
+    assert!(store.estimate_fee_rate(2, Some("fastest".to_string())).is_err());
+  }
+
+  #[test]
+  fn native_mempool_dependency_graph_links_prevouts_and_evicts_descendants() {
+    let [a, b, c, d] = ids(["a", "b", "c", "d"]);
+    let mut store = NativeMempoolState::new();
+
+    store.apply_snapshot(snapshot([entry(&a, 1000)]), None).unwrap();
+    // `c` spends `b` before the store knows `b`; the edge is linked once `b` arrives.
+    store.record_loaded(vec![spend(&c, &[(&b, 0)])], None).unwrap();
+    store.merge_snapshot(snapshot([entry(&b, 500)]), None).unwrap();
+    store.record_loaded(vec![spend(&b, &[(&a, 0)]), spend(&d, &[(&"e".repeat(64), 0)])], None).unwrap();
+
+    assert_eq!(store.get_ancestors(c.clone()), vec![b.clone(), a.clone()]);
+    assert_eq!(store.get_descendants(a.clone()), vec![b.clone(), c.clone()]);
+    assert!(store.get_ancestors(d.clone()).is_empty());
+
+    let package = store.get_package(b.clone()).unwrap();
+    assert_eq!(package["ancestors"], json!([a]));
+    assert_eq!(package["descendants"], json!([c]));
+    assert_eq!(package["fee"], json!(1000 + 500 + 1000));
+
+    store.remove_txids(vec![a.clone()], None, None).unwrap();
+    assert!(store.get_ancestors(b.clone()).is_empty());
+    assert!(store.has_transaction(c.clone()));
+
+    store.remove_txids(vec![b.clone()], Some(true), None).unwrap();
+    assert!(!store.has_transaction(b.clone()));
+    assert!(!store.has_transaction(c.clone()));
+    assert!(store.has_transaction(d.clone()));
+    assert!(store.get_package(c).is_none());
+  }
+
+  #[test]
+  fn native_mempool_record_loaded_reports_double_spend_replacements() {
+    let [funding, original, child, replacement] = ids(["f", "a", "b", "c"]);
+    let mut store = NativeMempoolState::new();
+
+    let report =
+      store.record_loaded(vec![spend(&original, &[(&funding, 0)]), spend(&child, &[(&original, 0)])], None).unwrap();
+    assert_eq!(report["replaced"], json!([]));
+    assert_eq!(store.get_outpoint_spender(format!("{funding}:0")), Some(original.clone()));
+    assert_eq!(store.find_conflicts(vec![format!("{funding}:0"), format!("{funding}:1")]), vec![original.clone()]);
+
+    let report = store.record_loaded(vec![spend(&replacement, &[(&funding, 0)])], None).unwrap();
+    assert_eq!(report["replaced"], json!([original]));
+    assert_eq!(report["evictedDescendants"], json!([child]));
+    assert_eq!(
+      report["conflicts"],
+      json!([{ "outpoint": format!("{funding}:0"), "txid": replacement, "replacedTxid": original }])
+    );
+
+    assert!(!store.has_transaction(original.clone()));
+    assert!(!store.has_transaction(child.clone()));
+    assert_eq!(store.get_outpoint_spender(format!("{funding}:0")), Some(replacement.clone()));
+    assert_eq!(store.get_outpoint_spender(format!("{original}:0")), None);
+    assert_eq!(store.tx_ids(), vec![replacement]);
+  }
+
+  #[test]
+  fn native_mempool_apply_block_confirms_and_evicts_conflicts() {
+    let [funding, confirmed, confirmed_child, conflicted, conflicted_child, block_spender] =
+      ids(["f", "a", "b", "c", "d", "e"]);
+    let mut store = NativeMempoolState::new();
+    store
+      .record_loaded(
+        vec![
+          spend(&confirmed, &[(&funding, 0)]),
+          spend(&confirmed_child, &[(&confirmed, 0)]),
+          spend(&conflicted, &[(&funding, 1)]),
+          spend(&conflicted_child, &[(&conflicted, 0)]),
+        ],
+        None,
+      )
+      .unwrap();
+
+    let report = store
+      .apply_block(
+        json!({
+          "tx": [
+            { "txid": "0".repeat(64), "vin": [{ "coinbase": "03" }] },
+            { "txid": confirmed, "vin": [{ "txid": funding, "vout": 0 }] },
+            { "txid": block_spender, "vin": [{ "txid": funding, "vout": 1 }] }
+          ]
+        }),
+        Some(true),
+      )
+      .unwrap();
+
+    assert_eq!(report["confirmed"], json!([confirmed]));
+    assert_eq!(report["conflicted"], json!([conflicted]));
+    assert_eq!(report["evictedDescendants"], json!([conflicted_child]));
+    assert_eq!(report["diff"]["removed"], json!([confirmed, conflicted, conflicted_child]));
+    assert_eq!(store.tx_ids(), vec![confirmed_child.clone()]);
+    assert!(store.get_ancestors(confirmed_child).is_empty());
+
+    let report = store.apply_block(json!({ "txids": [], "spentOutpoints": [format!("{confirmed}:0")] }), None).unwrap();
+    assert_eq!(report["conflicted"], json!(["b".repeat(64)]));
+    assert!(report.get("diff").is_none());
+    assert!(store.apply_block(json!([]), Some(true)).is_err());
+  }
+
+  #[test]
+  fn native_mempool_script_index_tracks_received_and_spent() {
+    let [funding, child, external] = ids(["a", "b", "c"]);
+    let watched = format!("0014{}", "11".repeat(20));
+    let other = format!("0014{}", "22".repeat(20));
+    let output = |value: f64, n: u32, hex: &str, address: &str| json!({ "value": value, "n": n, "scriptPubKey": { "hex": hex, "address": address, "type": "witness_v0_keyhash" } });
+    let loaded = |txid: &str, vin: Value, vout: Value| json!({ "txid": txid, "transaction": { "txid": txid, "vin": vin, "vout": vout }, "providerName": "providerA" });
+    let mut store = NativeMempoolState::new();
+
+    // The child arrives before its funding tx; its spend is attributed once the parent loads.
+    store
+      .record_loaded(vec![
+        loaded(&child, json!([{ "txid": funding, "vout": 0 }]), json!([output(0.5, 0, &other, "bc1qother")])),
+        loaded(&funding, json!([]), json!([output(1.0, 0, &watched, "bc1qwatched")])),
+        loaded(
+          &external,
+          json!([{ "txid": "f".repeat(64), "vout": 3, "prevout": { "value": 0.2, "scriptPubKey": { "hex": watched } } }]),
+          json!([]),
+        ),
+      ], None)
+      .unwrap();
+
+    assert_eq!(
+      store.get_transactions_by_script(watched.clone()),
+      vec![funding.clone(), child.clone(), external.clone()]
+    );
+    assert_eq!(store.get_transactions_by_address("bc1qwatched".to_string()).len(), 3);
+    assert_eq!(
+      store.get_script_balance(watched.clone()),
+      json!({ "received": 100_000_000u64, "spent": 120_000_000u64, "balance": -20_000_000i64, "txCount": 3 })
+    );
+    assert_eq!(store.get_address_balance("bc1qother".to_string())["received"], json!(50_000_000u64));
+
+    store.remove_txids(vec![funding.clone(), external.clone()], None, None).unwrap();
+    assert_eq!(store.get_transactions_by_script(watched.clone()), vec![child.clone()]);
+    assert_eq!(store.get_script_balance(watched.clone())["spent"], json!(100_000_000u64));
+
+    store.remove_txids(vec![child], None, None).unwrap();
+    assert!(store.get_transactions_by_address("bc1qwatched".to_string()).is_empty());
+    assert_eq!(store.get_script_balance(watched)["txCount"], json!(0));
+  }
+
+  #[test]
+  fn native_mempool_watchlist_reports_hits_from_mutations() {
+    let [funding, payment, replacement] = ids(["f", "a", "b"]);
+    let watched_script = format!("0014{}", "11".repeat(20));
+    let loaded = |txid: &str, vout: u32| {
+      json!({
+        "txid": txid,
+        "transaction": {
+          "txid": txid,
+          "vin": [{ "txid": funding, "vout": vout }],
+          "vout": [{ "value": 0.001, "n": 1, "scriptPubKey": { "hex": watched_script } }]
+        }
+      })
+    };
+    let mut store = NativeMempoolState::new();
+    store.watch(json!({ "scripts": [watched_script], "outpoints": [format!("{funding}:0")], "txids": [payment] }));
+    assert_eq!(store.get_watchlist()["txids"], json!([payment]));
+
+    let report = store.merge_snapshot(snapshot([entry(&payment, 100)]), None).unwrap();
+    assert_eq!(report["watchHits"], json!([{ "kind": "txSeen", "txid": payment }]));
+
+    let report = store.record_loaded(vec![loaded(&payment, 0)], None).unwrap();
+    assert_eq!(
+      report["watchHits"],
+      json!([
+        { "kind": "payment", "txid": payment, "vout": 1, "value": 100_000 },
+        { "kind": "outpointSpent", "outpoint": format!("{funding}:0"), "txid": payment }
+      ])
+    );
+
+    let report = store.record_loaded(vec![loaded(&replacement, 0)], None).unwrap();
+    let kinds: Vec<&str> =
+      report["watchHits"].as_array().unwrap().iter().map(|hit| hit["kind"].as_str().unwrap()).collect();
+    assert_eq!(kinds, vec!["txDropped", "payment", "outpointSpent"]);
+    assert_eq!(report["watchHits"][0]["reason"], json!("replaced"));
+
+    store.watch(json!({ "txids": [replacement] }));
+    let report = store.remove_txids(vec![replacement.clone()], None, None).unwrap();
+    assert_eq!(report["watchHits"], json!([{ "kind": "txConfirmed", "txid": replacement }]));
+
+    store.clear_watchlist();
+    let report = store.record_loaded(vec![loaded(&payment, 0)], None).unwrap();
+    assert_eq!(report["watchHits"], json!([]));
+  }
+
+  #[test]
+  fn native_mempool_compaction_renumbers_handles_and_keeps_indexes() {
+    let mut store = NativeMempoolState::new();
+    store.set_auto_compaction(None, None);
+    let entries: Vec<Value> =
+      (0..6).map(|i| json!({ "txid": txid(i), "metadata": { "fee": 1000 * (i + 1), "vsize": 100 } })).collect();
+    store.apply_snapshot(snapshot(entries), None).unwrap();
+    store
+      .record_loaded(
+        vec![json!({
+          "txid": txid(5),
+          "transaction": { "txid": txid(5), "vin": [{ "txid": txid(4), "vout": 0 }] },
+          "providerName": "providerA"
+        })],
+        None,
+      )
+      .unwrap();
+
+    let report = store.remove_txids(vec![txid(0), txid(1), txid(2)], None, None).unwrap();
+    assert!(report.get("compaction").is_none());
+    assert_eq!(store.get_stats()["tombstones"], json!(3));
+
+    let stats = store.compact();
+    assert_eq!(stats["handlesBefore"], json!(6));
+    assert_eq!(stats["handlesAfter"], json!(3));
+    assert_eq!(stats["tombstonesRemoved"], json!(3));
+    assert!(stats["reclaimedBytes"].as_u64().unwrap() > 0);
+    assert_eq!(store.get_stats()["tombstones"], json!(0));
+
+    assert_eq!(store.tx_ids(), vec![txid(3), txid(4), txid(5)]);
+    assert_eq!(store.get_ancestors(txid(5)), vec![txid(4)]);
+    assert_eq!(store.top_by_fee_rate(1)[0]["txid"], json!(txid(5)));
+    assert_eq!(store.pending_txids("providerA".to_string(), 10.0), vec![txid(3), txid(4)]);
+    assert!(store.is_transaction_loaded(txid(5)));
+
+    store.set_auto_compaction(Some(0.5), Some(2));
+    let report = store.remove_txids(vec![txid(3), txid(4)], None, None).unwrap();
+    assert_eq!(report["compaction"]["handlesAfter"], json!(1));
+    assert_eq!(store.tx_ids(), vec![txid(5)]);
+  }
+
+  #[test]
+  fn native_mempool_binary_snapshot_round_trips_and_rejects_corruption() {
+    let [a, b] = ids(["a", "b"]);
+    let mut store = NativeMempoolState::new();
+    store
+      .apply_snapshot(
+        json!({
+          "providerA": [{ "txid": a, "metadata": { "fee": 2820, "vsize": 141, "depends": [b], "modifiedfee": -5 } }],
+          "providerB": [entry(&b, 1000)]
+        }),
+        None,
+      )
+      .unwrap();
+    store
+      .record_loaded(
+        vec![json!({
+          "txid": a,
+          "transaction": {
+            "txid": a,
+            "version": 2,
+            "vin": [
+              { "txid": b, "vout": 0, "sequence": 4294967295u32 },
+              { "txid": "c".repeat(64), "vout": 7, "prevout": { "value": 0.5, "scriptPubKey": { "hex": "51" } } }
+            ],
+            "vout": [{
+              "value": 0.0001,
+              "n": 0,
+              "scriptPubKey": { "type": "future_type", "address": "bc1qx", "addresses": ["bc1qx"], "hex": "0014" }
+            }],
+            "feeRate": 20
+          },
+          "providerName": "providerB"
+        })],
+        None,
+      )
+      .unwrap();
+
+    let buf = store.export_snapshot_bytes();
+    let json_len = store.export_snapshot().to_string().len();
+    assert!(buf.len() < json_len / 2, "binary {} vs json {}", buf.len(), json_len);
+
+    let mut restored = NativeMempoolState::new();
+    restored.import_snapshot_bytes(&buf).unwrap();
+    assert_eq!(normalized(restored.export_snapshot()), normalized(store.export_snapshot()));
+    assert_eq!(restored.get_ancestors(a.clone()), vec![b.clone()]);
+    assert_eq!(restored.get_outpoint_spender(format!("{}:7", "c".repeat(64))), Some(a.clone()));
+
+    let mut corrupted = buf.clone();
+    corrupted[20] ^= 1;
+    assert!(restored.import_snapshot_bytes(&corrupted).is_err());
+    assert!(restored.has_transaction(a.clone()));
+
+    let mut newer = buf;
+    newer[4] = 9;
+    assert!(restored.import_snapshot_bytes(&newer).is_err());
+
+    // JSON v2 snapshots remain importable alongside the binary format.
+    restored.import_snapshot(store.export_snapshot()).unwrap();
+    assert!(restored.is_transaction_loaded(a));
+  }
+
+  #[test]
+  fn native_mempool_delta_replays_changes_since_sequence() {
+    let [a, b, c, d] = ids(["a", "b", "c", "d"]);
+
+    let mut leader = NativeMempoolState::new();
+    leader.apply_snapshot(snapshot([entry(&a, 1000), entry(&b, 2000)]), None).unwrap();
+    let mut replica = NativeMempoolState::new();
+    replica.import_snapshot(leader.export_snapshot()).unwrap();
+    let synced = leader.get_change_seq();
+    assert!(replica.export_delta(0).is_err());
+    assert!(leader.export_delta(synced + 1).is_err());
+
+    leader.merge_snapshot(json!({ "providerB": [entry(&c, 500), entry(&d, 1)] }), None).unwrap();
+    leader
+      .record_loaded(
+        vec![json!({
+          "txid": a,
+          "transaction": { "txid": a, "vin": [{ "txid": b, "vout": 0 }], "vout": [], "feeRate": 7 },
+          "providerName": "providerA"
+        })],
+        None,
+      )
+      .unwrap();
+    leader.remove_txids(vec![b.clone(), d.clone()], None, None).unwrap();
+
+    let delta = leader.export_delta(synced).unwrap();
+    assert_eq!(delta["fromSeq"], json!(synced));
+    assert_eq!(delta["toSeq"], json!(leader.get_change_seq()));
+    assert_eq!(txids_of(&delta["added"]), vec![c.clone()]);
+    assert_eq!(txids_of(&delta["updated"]), vec![a.clone()]);
+    assert_eq!(delta["removed"], json!([b]));
+    assert_eq!(delta["added"][0]["providers"], json!(["providerB"]));
+
+    replica.apply_delta(delta).unwrap();
+    assert_eq!(normalized(replica.export_snapshot()), normalized(leader.export_snapshot()));
+    assert!(replica.is_transaction_loaded(a.clone()));
+
+    let idle = leader.export_delta(leader.get_change_seq()).unwrap();
+    assert_eq!(idle["added"], json!([]));
+    assert_eq!(idle["removed"], json!([]));
+  }
+
+  #[test]
+  fn native_mempool_mutations_report_diffs_on_request() {
+    let [a, b, c, d] = ids(["a", "b", "c", "d"]);
+    let mut store = NativeMempoolState::new();
+    let first = store.apply_snapshot(snapshot([entry(&a, 1000), entry(&b, 1)]), None).unwrap();
+    assert!(first.get("diff").is_none());
+
+    let refreshed = store.apply_snapshot(snapshot([entry(&a, 1500), entry(&c, 1)]), Some(true)).unwrap();
+    assert_eq!(
+      refreshed["diff"],
+      json!({ "added": [c], "removed": [b], "metadataChanged": [{ "txid": a, "oldFee": 1000, "newFee": 1500 }], "loaded": [] })
+    );
+
+    let merged = store.merge_snapshot(json!({ "providerB": [entry(&c, 1), entry(&d, 1)] }), Some(true)).unwrap();
+    assert_eq!(merged["diff"]["added"], json!([d]));
+    assert_eq!(merged["diff"]["metadataChanged"], json!([]));
+
+    let loaded = store
+      .record_loaded(vec![json!({ "txid": a, "transaction": { "txid": a, "vin": [], "vout": [] } })], Some(true))
+      .unwrap();
+    assert_eq!(loaded["diff"]["loaded"], json!([a]));
+    assert_eq!(loaded["diff"]["added"], json!([]));
+
+    let removed = store.remove_txids(vec![a.clone(), "f".repeat(64)], None, Some(true)).unwrap();
+    assert_eq!(removed["diff"]["removed"], json!([a]));
+    assert!(store.remove_txids(vec![c], None, None).unwrap().get("diff").is_none());
+  }
+
+  #[test]
+  fn native_mempool_diff_nets_out_entries_evicted_by_the_same_mutation() {
+    let [a, b, c, d] = ids(["a", "b", "c", "d"]);
+    let entry = |txid: &str, fee: u64| json!({ "txid": txid, "metadata": { "fee": fee, "vsize": 100 } });
+    let mut store = NativeMempoolState::new();
+    store.apply_snapshot(snapshot([entry(&a, 300), entry(&b, 400)]), None).unwrap();
+    store.set_limits(json!({ "maxEntries": 2 }));
+
+    // `c` is new and evicted at once, so the refresh changes nothing.
+    let report = store.apply_snapshot_at(snapshot([entry(&a, 300), entry(&b, 400), entry(&c, 100)]), Some(true), 0);
+    let report = report.unwrap();
+    assert_eq!(report["evicted"], json!([{ "txid": c, "reason": "maxEntries" }]));
+    assert_eq!(report["diff"]["added"], json!([]));
+    assert_eq!(report["diff"]["removed"], json!([]));
+
+    // A new `c` nets out again; `a` was already there and is reported removed.
+    let report = store.merge_snapshot_impl(snapshot([entry(&c, 100), entry(&d, 900)]), Some(true), 0).unwrap();
+    assert_eq!(
+      report["evicted"],
+      json!([{ "txid": c, "reason": "maxEntries" }, { "txid": a, "reason": "maxEntries" }])
+    );
+    assert_eq!(report["diff"]["added"], json!([d]));
+    assert_eq!(report["diff"]["removed"], json!([a]));
+  }
+
+  #[test]
+  fn native_mempool_limits_evict_lowest_descendant_fee_rate_packages() {
+    let [a, b, c, d, e] = ids(["a", "b", "c", "d", "e"]);
+    let entry = |txid: &str, fee: u64, depends: &[&String], time: u64| json!({ "txid": txid, "metadata": { "fee": fee, "vsize": 100, "depends": depends, "time": time } });
+    let mut store = NativeMempoolState::new();
+    store.set_limits(json!({ "maxEntries": 3, "ttlMs": 60_000 }));
+    store.apply_snapshot(snapshot([entry(&a, 2000, &[], 0), entry(&b, 100, &[], 0)]), None).unwrap();
+
+    // `b` alone pays 1 sat/vB, but its child lifts the package to 10.5 sat/vB,
+    // above `c` at 5 sat/vB.
+    let report =
+      store.merge_snapshot(snapshot([entry(&c, 500, &[], 0), entry(&d, 2000, &[&b], 0)]), Some(true)).unwrap();
+    assert_eq!(report["evicted"], json!([{ "txid": c, "reason": "maxEntries" }]));
+    assert_eq!((report["diff"]["added"].clone(), report["diff"]["removed"].clone()), (json!([d]), json!([])));
+    assert!(store.has_transaction(b.clone()));
+
+    store.set_limits(json!({ "maxEntries": 2 }));
+    store.watch(json!({ "txids": [d] }));
+    let report = store.enforce_limits(None);
+    assert_eq!(
+      report["evicted"],
+      json!([{ "txid": b, "reason": "maxEntries" }, { "txid": d, "reason": "maxEntries" }])
+    );
+    assert_eq!(report["watchHits"], json!([{ "kind": "txDropped", "txid": d, "reason": "evicted" }]));
+    assert_eq!(store.get_limits()["usage"]["entries"], json!(1));
+
+    store.set_limits(json!({ "ttlMs": 60_000 }));
+    // Provider `time` is in seconds.
+    let seen_ms = now_ms() / 1000 * 1000;
+    store.merge_snapshot(snapshot([entry(&e, 100, &[], seen_ms / 1000)]), None).unwrap();
+    let report = store.enforce_limits(Some((seen_ms + 59_000) as f64));
+    assert_eq!(report["evicted"], json!([]));
+    let report = store.enforce_limits(Some((seen_ms + 60_001) as f64));
+    assert_eq!(report["evicted"], json!([{ "txid": e, "reason": "expired" }]));
+    assert_eq!(store.get_limits()["maxEntries"], Value::Null);
+  }
+
+  #[test]
+  fn native_mempool_memory_usage_follows_allocations() {
+    let bytes = |store: &NativeMempoolState, component: &str| {
+      store.get_memory_usage(Some("B".into()))["bytes"][component].as_f64().unwrap()
+    };
+    let loaded = |txid: &str, script_len: usize| {
+      json!({
+        "txid": txid,
+        "transaction": { "txid": txid, "vin": [], "vout": [{ "value": 0.1, "n": 0, "scriptPubKey": { "hex": "00".repeat(script_len) } }] }
+      })
+    };
+    let mut store = NativeMempoolState::new();
+    assert_eq!(bytes(&store, "total"), 0.0);
+    assert_eq!(store.get_memory_usage(None)["processHeap"].is_null(), !cfg!(feature = "counting-allocator"));
+
+    store.record_loaded(vec![loaded(&"a".repeat(64), 10)], None).unwrap();
+    let small = bytes(&store, "txStore");
+    store.record_loaded(vec![loaded(&"b".repeat(64), 10_000)], None).unwrap();
+    // Script payloads are counted, not a fixed per-transaction constant.
+    assert!(bytes(&store, "txStore") >= small + 10_000.0);
+
+    let usage = store.get_memory_usage(Some("B".into()));
+    let components: f64 = usage["bytes"]
+      .as_object()
+      .unwrap()
+      .iter()
+      .filter(|(name, _)| *name != "total")
+      .map(|(_, v)| v.as_f64().unwrap())
+      .sum();
+    assert_eq!(usage["bytes"]["total"].as_f64().unwrap(), components);
+
+    store.dispose();
+    assert_eq!(bytes(&store, "total"), 0.0);
+  }
+
+  #[test]
+  fn native_mempool_load_failures_back_off_and_fall_back_to_other_providers() {
+    let [a, b] = ids(["a", "b"]);
+    let mut store = NativeMempoolState::new();
+    store
+      .apply_snapshot(
+        json!({
+          "providerA": [
+            entry(&a, 1000),
+            entry(&b, 2000)
+          ],
+          "providerB": [entry(&a, 1000)]
+        }),
+        None,
+      )
+      .unwrap();
+
+    // `a` is assigned to providerA; providerB only becomes its fallback.
+    // Default policy: one failure puts the txid in backoff for that provider.
+    let report = store.record_load_failed(vec![a.clone()], "providerA".into(), Some("not found".into()));
+    assert_eq!(report[0]["attempts"], json!(1));
+    assert_eq!(report[0]["exhausted"], json!(false));
+    assert_eq!(store.pending_txids("providerA".into(), 10.0), vec![b.clone()]);
+    assert!(store.pending_txids("providerB".into(), 10.0).is_empty());
+    let failures = store.get_load_failures(a.clone()).unwrap();
+    assert_eq!(failures["reason"], json!("not found"));
+    assert_eq!(failures["providers"][0]["provider"], json!("providerA"));
+
+    // Without delay, exhausted txids move to a provider that also lists them;
+    // txids no other provider knows keep being retried on the same one.
+    store.set_load_retry_policy(json!({ "baseDelayMs": 0, "maxFailures": 2 }));
+    store.record_load_failed(vec![a.clone(), b.clone()], "providerA".into(), None);
+    let report = store.record_load_failed(vec![b.clone()], "providerA".into(), None);
+    assert_eq!(report[0]["exhausted"], json!(true));
+    assert_eq!(store.pending_txids("providerA".into(), 10.0), vec![b.clone()]);
+    assert_eq!(store.pending_txids("providerB".into(), 10.0), vec![a.clone()]);
+
+    // Once every provider is exhausted the txid is offered again.
+    store.record_load_failed(vec![a.clone(), a.clone()], "providerB".into(), None);
+    assert_eq!(store.pending_txids("providerA".into(), 10.0).len(), 2);
+    assert!(store.pending_txids("providerB".into(), 10.0).is_empty());
+
+    store
+      .record_loaded(vec![json!({ "txid": a, "transaction": { "txid": a }, "providerName": "providerB" })], None)
+      .unwrap();
+    assert!(store.get_load_failures(a.clone()).is_none());
+    assert!(store.record_load_failed(vec![a.clone(), "f".repeat(64)], "providerA".into(), None).is_empty());
   }
 
-  #[napi(js_name = "loadedTransactions")]
-  pub fn loaded_transactions(&self) -> Vec<Value> {
-    self.store.transactions.values().cloned().collect()
-  }
+  #[test]
+  fn native_mempool_pending_order_follows_priority_queues() {
+    let [a, b, c, d] = ids(["a", "b", "c", "d"]);
+    let entry = |txid: &str, fee: u64, time: u64, depends: &[&str]| {
+      json!({
+        "txid": txid,
+        "metadata": { "txid": txid, "fee": fee, "vsize": 100, "time": time, "depends": depends }
+      })
+    };
+    let mut store = NativeMempoolState::new();
+    store
+      .apply_snapshot(
+        json!({
+          "providerA": [
+            entry(&a, 1000, 30, &[]),
+            entry(&b, 5000, 10, &[]),
+            entry(&c, 3000, 20, &[&a]),
+            entry(&d, 2000, 40, &[])
+          ]
+        }),
+        None,
+      )
+      .unwrap();
+    let pending = |store: &NativeMempoolState, limit: f64| store.pending_txids("providerA".into(), limit);
 
-  #[napi]
-  pub fn metadata(&self) -> Vec<Value> {
-    self.store.metadata.values().cloned().collect()
-  }
+    assert_eq!(pending(&store, 10.0), vec![a.clone(), b.clone(), c.clone(), d.clone()]);
+    assert!(store.set_pending_order("random".into()).is_err());
 
-  #[napi(js_name = "hasTransaction")]
-  pub fn has_transaction(&self, txid: String) -> bool {
-    self.store.handle_of_txid(&txid).is_some()
-  }
+    store.set_pending_order("feeRate".into()).unwrap();
+    assert_eq!(store.get_pending_order(), "feeRate");
+    assert_eq!(pending(&store, 2.0), vec![b.clone(), c.clone()]);
+    // Metadata refreshes re-key, loads dequeue, merged txids are queued.
+    store.merge_snapshot(snapshot([entry(&d, 9000, 40, &[])]), None).unwrap();
+    store.record_loaded(vec![json!({ "txid": b, "transaction": { "txid": b } })], None).unwrap();
+    assert_eq!(pending(&store, 10.0), vec![d.clone(), c.clone(), a.clone()]);
 
-  #[napi(js_name = "isTransactionLoaded")]
-  pub fn is_transaction_loaded(&self, txid: String) -> bool {
-    self
-      .store
-      .handle_of_txid(&txid)
-      .map(|h| self.store.load_tracker.contains_key(&h))
-      .unwrap_or(false)
-  }
+    store.set_pending_order("firstSeen".into()).unwrap();
+    assert_eq!(pending(&store, 10.0), vec![c.clone(), a.clone(), d.clone()]);
 
-  #[napi(js_name = "getTransactionMetadata")]
-  pub fn get_transaction_metadata(&self, txid: String) -> Option<Value> {
-    self.store.handle_of_txid(&txid).and_then(|h| self.store.metadata.get(&h).cloned())
-  }
+    // Watched txids and children of watched txids come first.
+    store.set_pending_order("watchedFirst".into()).unwrap();
+    assert_eq!(pending(&store, 10.0), vec![a.clone(), c.clone(), d.clone()]);
+    store.watch(json!({ "txids": [a] }));
+    assert_eq!(pending(&store, 10.0), vec![a.clone(), c.clone(), d.clone()]);
+    store.watch(json!({ "txids": [d] }));
+    store.unwatch(json!({ "txids": [a] }));
+    assert_eq!(pending(&store, 10.0), vec![d.clone(), a.clone(), c.clone()]);
 
-  #[napi(js_name = "getFullTransaction")]
-  pub fn get_full_transaction(&self, txid: String) -> Option<Value> {
-    self.store.handle_of_txid(&txid).and_then(|h| self.store.transactions.get(&h).cloned())
+    store.remove_txids(vec![d.clone()], None, None).unwrap();
+    assert_eq!(pending(&store, 10.0), vec![a.clone(), c.clone()]);
   }
 
-  #[napi(js_name = "getStats")]
-  pub fn get_stats(&self) -> Value {
-    json!({
-      "txids": self.store.txids.len(),
-      "metadata": self.store.metadata.len(),
-      "transactions": self.store.transactions.len(),
-      "providers": self.store.provider_tx.len(),
-    })
-  }
+  #[test]
+  fn native_mempool_provider_coverage_and_propagation_analytics() {
+    let [a, b, c, d] = ids(["a", "b", "c", "d"]);
+    let mut store = NativeMempoolState::new();
+    store
+      .apply_snapshot_at(
+        json!({ "A": [entry(&a, 1000), entry(&b, 1000), entry(&c, 1000)], "B": [entry(&a, 1000)] }),
+        None,
+        1_000,
+      )
+      .unwrap();
+    store.merge_snapshot_impl(json!({ "B": [entry(&b, 1000)], "C": [entry(&a, 1000)] }), None, 1_500).unwrap();
+    store.merge_snapshot_impl(json!({ "A": [entry(&d, 1000)], "B": [entry(&c, 1000)] }), None, 3_000).unwrap();
+    // Reporting a txid again keeps the first time.
+    store.merge_snapshot_impl(json!({ "B": [entry(&a, 1000)] }), None, 4_000).unwrap();
 
-  #[napi(js_name = "getMemoryUsage")]
-  pub fn get_memory_usage(&self, units: Option<String>) -> Value {
-    let (unit, factor) = convert_units(units);
-    let txids = self.store.txids.len() as f64;
-    let metadata = self.store.metadata.len() as f64;
-    let transactions = self.store.transactions.len() as f64;
-    let loaded = self.store.load_tracker.len() as f64;
-    let providers = self.store.provider_tx.len();
-
-    let tx_index_bytes = txids * 40.0;
-    let metadata_bytes = metadata * 350.0;
-    let tx_store_bytes = transactions * 2000.0;
-    let load_tracker_bytes = loaded * 48.0;
-    let provider_map_bytes = txids * 12.0;
-    let total = tx_index_bytes + metadata_bytes + tx_store_bytes + load_tracker_bytes + provider_map_bytes;
-    let conv = |b: f64| (b / factor * 100.0).round() / 100.0;
+    assert_eq!(store.get_provider_first_seen(a.clone()).unwrap(), json!({ "A": 1000, "B": 1000, "C": 1500 }));
+    assert!(store.get_provider_first_seen("e".repeat(64)).is_none());
 
-    json!({
-      "unit": unit,
-      "counts": {
-        "txids": self.store.txids.len(),
-        "metadata": self.store.metadata.len(),
-        "transactions": self.store.transactions.len(),
-        "loaded": self.store.load_tracker.len(),
-        "providers": providers,
-      },
-      "bytes": {
-        "txIndex": conv(tx_index_bytes),
-        "metadata": conv(metadata_bytes),
-        "txStore": conv(tx_store_bytes),
-        "loadTracker": conv(load_tracker_bytes),
-        "providerTx": conv(provider_map_bytes),
-        "total": conv(total),
-      }
-    })
+    let coverage = store.get_provider_coverage();
+    assert_eq!(coverage["providers"], json!(["A", "B", "C"]));
+    assert_eq!(coverage["total"], json!(4));
+    assert_eq!(coverage["seen"], json!({ "A": 4, "B": 3, "C": 1 }));
+    assert_eq!(coverage["unique"], json!({ "A": 1, "B": 0, "C": 0 }));
+    assert_eq!(coverage["missing"], json!({ "A": 0, "B": 1, "C": 3 }));
+    assert_eq!(coverage["overlap"], json!([[4, 3, 1], [3, 3, 1], [1, 1, 1]]));
+    assert_eq!(store.get_unique_txids("A".into(), None), vec![d.clone()]);
+    assert!(store.get_unique_txids("B".into(), None).is_empty());
+
+    let propagation = store.get_propagation_delays();
+    assert_eq!(propagation["delays"][0], json!([null, 500.0, 500.0]));
+    assert_eq!(propagation["delays"][1], json!([-500.0, null, 500.0]));
+    assert_eq!(propagation["lag"], json!({ "A": 0.0, "B": 500.0, "C": 500.0 }));
+
+    // A full replace keeps first-seen times of surviving txids only.
+    store.apply_snapshot_at(json!({ "B": [entry(&a, 1000)] }), None, 5_000).unwrap();
+    assert_eq!(store.get_provider_first_seen(a.clone()).unwrap(), json!({ "A": 1000, "B": 1000, "C": 1500 }));
+    store.remove_txids(vec![a.clone()], None, None).unwrap();
+    assert_eq!(store.get_provider_coverage()["total"], json!(0));
   }
 
-  #[napi(js_name = "exportSnapshot")]
-  pub fn export_snapshot(&self) -> Value {
-    let txids: Vec<String> = self
-      .store
-      .txids
-      .iter()
-      .enumerate()
-      .filter(|(handle, _)| !self.store.removed_handles.contains(&(*handle as u32)))
-      .map(|(_, key)| txid_to_hex(*key))
-      .collect();
+  #[test]
+  fn native_mempool_metadata_variants_follow_reconciliation_policy() {
+    let [a] = ids(["a"]);
+    let item =
+      |fee: u64, time: u64| json!({ "txid": a, "metadata": { "txid": a, "fee": fee, "vsize": 100, "time": time } });
+    let fee = |store: &NativeMempoolState| store.get_transaction_metadata(a.clone()).unwrap()["fee"].clone();
+    let mut store = NativeMempoolState::new();
 
-    let provider_tx: Vec<Value> = self
-      .store
-      .provider_tx
-      .iter()
-      .map(|(provider, handles)| {
-        let ids: Vec<String> = handles
-          .iter()
-          .filter(|h| !self.store.removed_handles.contains(h))
-          .filter_map(|h| self.store.txids.get(*h as usize))
-          .map(|key| txid_to_hex(*key))
-          .collect();
-        json!([provider, ids])
-      })
-      .collect();
+    // Default: merges overwrite and no variants are kept.
+    store.merge_snapshot_impl(json!({ "A": [item(1000, 1)] }), None, 10).unwrap();
+    store.merge_snapshot_impl(json!({ "B": [item(2000, 1)] }), None, 20).unwrap();
+    assert_eq!(fee(&store), json!(2000));
+    assert_eq!(store.get_metadata_variants(a.clone()).unwrap()["variants"], json!([]));
+    assert!(store.set_metadata_policy(json!({ "policy": "median" })).is_err());
 
-    let metadata: Vec<Value> = self
-      .store
-      .metadata
-      .iter()
-      .filter(|(h, _)| !self.store.removed_handles.contains(h))
-      .filter_map(|(h, md)| self.store.txids.get(*h as usize).map(|key| json!([txid_to_hex(*key), md])))
-      .collect();
+    store.set_metadata_policy(json!({ "policy": "highestFee" })).unwrap();
+    assert_eq!(store.get_metadata_policy(), json!({ "policy": "highestFee", "retainVariants": true }));
+    store
+      .apply_snapshot_at(json!({ "A": [item(1000, 1)], "B": [item(3000, 2)], "C": [item(1000, 3)] }), None, 30)
+      .unwrap();
+    assert_eq!(fee(&store), json!(3000));
+    let variants = store.get_metadata_variants(a.clone()).unwrap();
+    assert_eq!(variants["distinct"], json!(2));
+    assert_eq!(variants["variants"][1]["provider"], json!("B"));
+    assert_eq!(variants["variants"][1]["metadata"]["fee"], json!(3000));
 
-    let transactions: Vec<Value> = self
-      .store
-      .transactions
-      .iter()
-      .filter(|(h, _)| !self.store.removed_handles.contains(h))
-      .filter_map(|(h, tx)| self.store.txids.get(*h as usize).map(|key| json!([txid_to_hex(*key), tx])))
-      .collect();
+    // A and C agree although their node-local `time` differs.
+    store.set_metadata_policy(json!({ "policy": "quorum" })).unwrap();
+    assert_eq!(fee(&store), json!(1000));
 
-    let load_tracker: Vec<Value> = self
-      .store
-      .load_tracker
-      .iter()
-      .filter(|(h, _)| !self.store.removed_handles.contains(h))
-      .filter_map(|(h, info)| self.store.txids.get(*h as usize).map(|key| json!([txid_to_hex(*key), info])))
-      .collect();
+    store.set_metadata_policy(json!({ "policy": "latest" })).unwrap();
+    store.merge_snapshot_impl(json!({ "B": [item(4000, 2)] }), None, 40).unwrap();
+    assert_eq!(fee(&store), json!(4000));
+    assert_eq!(store.get_metadata_variants(a.clone()).unwrap()["variants"][2]["reportedAt"], json!(40));
 
-    json!({
-      "version": 2,
-      "txids": txids,
-      "providerTx": provider_tx,
-      "metadata": metadata,
-      "transactions": transactions,
-      "loadTracker": load_tracker,
-    })
+    store.set_metadata_policy(json!({ "policy": "default" })).unwrap();
+    assert_eq!(store.get_metadata_variants(a.clone()).unwrap()["distinct"], json!(0));
   }
 
-  #[napi(js_name = "importSnapshot")]
-  pub fn import_snapshot(&mut self, state: Value) -> Result<()> {
-    if state.is_null() {
-      self.store.clear();
-      return Ok(());
-    }
+  #[test]
+  fn native_mempool_iterate_pages_stay_consistent_across_mutations() {
+    let mut store = NativeMempoolState::new();
+    store.set_auto_compaction(None, None);
+    store.apply_snapshot(snapshot(entries(0..6)), None).unwrap();
+    assert!(store.iterate("hashes".into(), None, 10.0).is_err());
+    assert!(store.iterate("txIds".into(), Some("x".into()), 10.0).is_err());
 
-    ensure_snapshot_v2(&state)?;
-    self.store.clear();
+    let (first, cursor) = page(&store, "txIds", None, 2.0);
+    assert_eq!(first, vec![json!(txid(0)), json!(txid(1))]);
 
-    if state == empty_snapshot() {
-      return Ok(());
-    }
+    // Removals behind and ahead of the cursor, a compaction and an addition.
+    store.remove_txids(vec![txid(1), txid(3)], None, None).unwrap();
+    store.compact();
+    store.merge_snapshot(snapshot(entries(6..7)), None).unwrap();
 
-    if let Some(Value::Array(txids)) = state.get("txids") {
-      for txid in txids.iter().filter_map(Value::as_str) {
-        if let Some(key) = parse_txid(txid) {
-          self.store.ensure_handle(key);
-        }
-      }
+    let mut rest = Vec::new();
+    let mut cursor = cursor;
+    while let Some(current) = cursor {
+      let (items, next) = page(&store, "txIds", Some(current), 2.0);
+      rest.extend(items);
+      cursor = next;
     }
+    assert_eq!(rest, vec![json!(txid(2)), json!(txid(4)), json!(txid(5)), json!(txid(6))]);
 
-    if let Some(Value::Array(entries)) = state.get("providerTx") {
-      for entry in entries {
-        let Value::Array(pair) = entry else {
-          continue;
-        };
-        if pair.len() != 2 {
-          continue;
-        }
-
-        let provider = pair[0].as_str().unwrap_or_default().to_string();
-        if provider.is_empty() {
-          continue;
-        }
+    let (metadata, _) = page(&store, "metadata", None, 10.0);
+    assert_eq!(metadata.len(), 5);
+    assert_eq!(metadata[0]["txid"], json!(txid(0)));
+    let (loaded, next) = page(&store, "loadedTransactions", None, 10.0);
+    assert!(loaded.is_empty() && next.is_none());
 
-        let mut handles = Vec::new();
+    // A full replace invalidates outstanding cursors.
+    let (_, cursor) = page(&store, "txIds", None, 1.0);
+    store.apply_snapshot(snapshot(entries(0..3)), None).unwrap();
+    assert!(store.iterate("txIds".into(), cursor, 1.0).is_err());
+  }
 
-        if let Value::Array(ids) = &pair[1] {
-          for txid in ids.iter().filter_map(Value::as_str) {
-            if let Some(key) = parse_txid(txid) {
-              handles.push(self.store.ensure_handle(key));
-            }
-          }
+  #[test]
+  fn native_mempool_query_filters_sorts_and_pages() {
+    let entry = |i: u32, fee: u64, vsize: u32, ancestors: u32, replaceable: bool, time: u64| {
+      json!({
+        "txid": txid(i),
+        "metadata": {
+          "txid": txid(i),
+          "fee": fee,
+          "vsize": vsize,
+          "ancestorcount": ancestors,
+          "bip125_replaceable": replaceable,
+          "time": time
         }
+      })
+    };
+    let txids = |result: &Value| txids_of(&result["items"]);
+    let now = now_ms() / 1000;
+    let mut store = NativeMempoolState::new();
+    store
+      .apply_snapshot(
+        json!({
+          "providerA": [entry(0, 1000, 100, 1, true, now - 3600), entry(1, 5000, 250, 3, false, now - 60)],
+          "providerB": [entry(2, 3000, 100, 1, true, now - 600), entry(3, 200, 200, 2, false, now - 10)]
+        }),
+        None,
+      )
+      .unwrap();
+    store.record_loaded(vec![json!({ "txid": txid(2), "transaction": { "txid": txid(2) } })], None).unwrap();
 
-        if !handles.is_empty() {
-          self.store.provider_tx.insert(provider, handles);
-        }
-      }
-    }
+    let all = store.query(json!({})).unwrap();
+    assert_eq!(all["total"], json!(4));
+    assert_eq!(txids(&all), vec![txid(0), txid(1), txid(2), txid(3)]);
+    assert!(all["items"][0].get("transaction").is_none());
 
-    if let Some(Value::Array(entries)) = state.get("metadata") {
-      for entry in entries {
-        self.store.import_pair(entry, PairTarget::Metadata);
-      }
-    }
+    let by_fee = store.query(json!({ "sort": "feeRate", "minFeeRate": 5 })).unwrap();
+    assert_eq!(txids(&by_fee), vec![txid(2), txid(1), txid(0)]);
+    let paged = store.query(json!({ "sort": "feeRate", "order": "asc", "offset": 1, "limit": 2 })).unwrap();
+    assert_eq!(paged["total"], json!(4));
+    assert_eq!(txids(&paged), vec![txid(0), txid(1)]);
 
-    if let Some(Value::Array(entries)) = state.get("transactions") {
-      for entry in entries {
-        self.store.import_pair(entry, PairTarget::Transaction);
-      }
-    }
+    assert_eq!(txids(&store.query(json!({ "minVsize": 150, "maxVsize": 220 })).unwrap()), vec![txid(3)]);
+    assert_eq!(txids(&store.query(json!({ "bip125Replaceable": true })).unwrap()), vec![txid(0), txid(2)]);
+    assert_eq!(
+      txids(&store.query(json!({ "minAncestorCount": 2, "sort": "ancestorCount" })).unwrap()),
+      vec![txid(3), txid(1)]
+    );
+    assert_eq!(txids(&store.query(json!({ "maxAgeMs": 120_000, "sort": "age" })).unwrap()), vec![txid(3), txid(1)]);
+    assert_eq!(txids(&store.query(json!({ "provider": "providerB" })).unwrap()), vec![txid(2), txid(3)]);
+    assert_eq!(txids(&store.query(json!({ "txidPrefix": txid(1) })).unwrap()), vec![txid(1)]);
 
-    if let Some(Value::Array(entries)) = state.get("loadTracker") {
-      for entry in entries {
-        self.store.import_pair(entry, PairTarget::LoadTracker);
-      }
-    }
+    let loaded = store.query(json!({ "loaded": true, "includeTransactions": true })).unwrap();
+    assert_eq!(txids(&loaded), vec![txid(2)]);
+    assert_eq!(loaded["items"][0]["transaction"]["txid"], json!(txid(2)));
 
-    Ok(())
+    assert!(store.query(json!({ "sort": "size" })).is_err());
+    assert!(store.query(json!({ "order": "up" })).is_err());
+    assert!(store.query(json!({ "txidPrefix": "xyz" })).is_err());
   }
 
-  /// Explicitly clears only the native backing store. Domain reset/rollback decisions
-  /// must still be made by the TS aggregate/event-store layer.
-  #[napi]
-  pub fn clear(&mut self) {
-    self.store.clear();
-  }
+  #[test]
+  fn native_mempool_record_loaded_raw_keeps_bytes_and_decodes_on_demand() {
+    let [parent] = ids(["1"]);
+    let raw = raw_spend(&parent, "fdffffff");
+    let txid = raw_txid(&raw);
+    let mut store = NativeMempoolState::new();
+    store.apply_snapshot(snapshot([entry(&txid, 1000)]), None).unwrap();
 
-  /// Releases native memory held by the backing store.
-  ///
-  /// This is a lifecycle/cleanup hook called by the TS owner when the aggregate
-  /// is no longer used. It does not emit events, does not affect persistence and
-  /// does not unload the `.node` addon itself; it only drops/shrinks Rust data
-  /// structures owned by this state store instance.
-  #[napi]
-  pub fn dispose(&mut self) {
-    self.store.dispose();
-  }
+    assert!(store.record_loaded_raw_bytes(&"0".repeat(64), &raw, None, None).is_err());
+    assert!(store.record_loaded_raw_bytes(&txid, &raw[1..], None, None).is_err());
+    let report = store.record_loaded_raw_bytes(&txid, &raw, Some("providerA"), Some(true)).unwrap();
+    assert_eq!(report["diff"]["loaded"], json!([txid]));
+    assert!(store.is_transaction_loaded(txid.clone()));
+    assert_eq!(store.get_memory_usage(Some("B".into()))["counts"]["rawTransactions"], json!(1));
 
-  #[napi(js_name = "assertStoreOnly")]
-  pub fn assert_store_only(&self) -> bool {
-    true
+    let tx = store.get_full_transaction(txid.clone()).unwrap();
+    assert_eq!(tx["size"], json!(raw.len()));
+    assert_eq!(tx["vsize"], json!(raw.len()));
+    assert_eq!(tx["bip125_replaceable"], json!(true));
+    assert_eq!(tx["vin"][0], json!({ "txid": parent, "vout": 0, "sequence": 0xffff_fffdu32 }));
+    assert_eq!(tx["vout"][0]["value"], json!(0.0005));
+    assert_eq!(tx["vout"][0]["scriptPubKey"]["hex"], json!(format!("0014{}", "22".repeat(20))));
+    assert_eq!(store.get_outpoint_spender(format!("{parent}:0")), Some(txid.clone()));
+    assert_eq!(store.get_transactions_by_script(format!("0014{}", "22".repeat(20))), vec![txid.clone()]);
+
+    // A conflicting raw load replaces the first spend.
+    let replacement = raw_spend(&parent, "feffffff");
+    let replacement_txid = raw_txid(&replacement);
+    let report = store.record_loaded_raw_bytes(&replacement_txid, &replacement, None, None).unwrap();
+    assert_eq!(report["replaced"], json!([txid]));
+
+    // Snapshots carry the bytes, not the decoded form.
+    let mut restored = NativeMempoolState::new();
+    restored.import_snapshot_bytes(&store.export_snapshot_bytes()).unwrap();
+    assert_eq!(restored.get_memory_usage(Some("B".into()))["counts"]["rawTransactions"], json!(1));
+    assert_eq!(
+      restored.get_full_transaction(replacement_txid.clone()),
+      store.get_full_transaction(replacement_txid.clone())
+    );
   }
-}
 
+  #[test]
+  fn native_mempool_raw_records_stay_raw_across_snapshots_and_deltas() {
+    let [parent, loaded] = ids(["1", "2"]);
+    let raw = raw_spend(&parent, "fdffffff");
+    let txid = raw_txid(&raw);
+    let script = format!("0014{}", "22".repeat(20));
+    let raw_count =
+      |store: &NativeMempoolState| store.get_memory_usage(Some("B".into()))["counts"]["rawTransactions"].clone();
+    let mut leader = NativeMempoolState::new();
+    leader.apply_snapshot(snapshot([entry(&txid, 1000), entry(&loaded, 500)]), None).unwrap();
+    let seq = leader.get_change_seq();
+    leader.record_loaded_raw_bytes(&txid, &raw, Some("providerA"), None).unwrap();
+    leader.record_loaded(vec![json!({ "txid": loaded, "transaction": { "txid": loaded } })], None).unwrap();
 
-#[cfg(test)]
-mod tests {
-  use super::*;
+    let json_snapshot = leader.export_snapshot();
+    assert_eq!(json_snapshot["rawTransactions"], json!([[txid, hex::encode(&raw)]]));
+    assert_eq!(json_snapshot["transactions"].as_array().unwrap().len(), 1);
+    assert_eq!(json_snapshot["transactions"][0][0], json!(loaded));
 
-  fn meta(txid: &str, fee: u64) -> Value {
-    json!({
-      "txid": txid,
-      "fee": fee,
-      "fees": { "base": fee }
-    })
+    let mut from_json = NativeMempoolState::new();
+    from_json.import_snapshot(json_snapshot.clone()).unwrap();
+    let mut from_bytes = NativeMempoolState::new();
+    from_bytes.import_snapshot_bytes(&leader.export_snapshot_bytes()).unwrap();
+    let delta = leader.export_delta(seq).unwrap();
+    let raw_entry = delta["updated"].as_array().unwrap().iter().find(|item| item["txid"] == json!(txid)).unwrap();
+    assert_eq!(raw_entry["rawTransaction"], json!(hex::encode(&raw)));
+    assert!(raw_entry.get("transaction").is_none());
+    let mut from_delta = NativeMempoolState::new();
+    from_delta.apply_snapshot(snapshot([entry(&txid, 1000), entry(&loaded, 500)]), None).unwrap();
+    from_delta.apply_delta(delta).unwrap();
+
+    for replica in [&from_json, &from_bytes, &from_delta] {
+      assert_eq!(raw_count(replica), json!(1));
+      assert_eq!(replica.get_full_transaction(txid.clone()), leader.get_full_transaction(txid.clone()));
+      assert_eq!(replica.get_outpoint_spender(format!("{parent}:0")), Some(txid.clone()));
+      assert_eq!(replica.get_transactions_by_script(script.clone()), vec![txid.clone()]);
+    }
+    assert_eq!(normalized(from_json.export_snapshot()), normalized(json_snapshot.clone()));
+
+    // Raw records that do not decode to their txid are not imported.
+    let mut tampered = json_snapshot;
+    tampered["rawTransactions"] = json!([[txid, hex::encode(&raw[1..])], [loaded, hex::encode(&raw)]]);
+    let mut rejected = NativeMempoolState::new();
+    rejected.import_snapshot(tampered).unwrap();
+    assert_eq!(raw_count(&rejected), json!(0));
+    assert_eq!(rejected.get_full_transaction(txid.clone()), None);
   }
 
   #[test]
-  fn native_mempool_merge_snapshot_is_additive() {
-    let a = "a".repeat(64);
-    let b = "b".repeat(64);
-    let c = "c".repeat(64);
+  fn native_mempool_confirmation_history_survives_apply_snapshot() {
+    let now_secs = now_ms() / 1000;
+    let entries: Vec<Value> = (0..10u32)
+      .map(|i| json!({ "txid": txid(i), "metadata": { "fee": 3_000, "vsize": 100, "time": now_secs - 60 } }))
+      .collect();
     let mut store = NativeMempoolState::new();
+    store.apply_snapshot(snapshot(entries), None).unwrap();
+    store.remove_txids((0..10).map(txid).collect(), None, None).unwrap();
+    let before = store.estimate_fee_rate(2, Some("conservative".to_string())).unwrap();
+    assert_eq!(before["samples"], json!(10));
 
-    store
-      .apply_snapshot(json!({
-        "providerA": [
-          { "txid": a, "metadata": meta(&a, 1000) },
-          { "txid": b, "metadata": meta(&b, 2000) }
-        ]
-      }))
-      .unwrap();
+    store.apply_snapshot(snapshot([entry(&txid(99), 100)]), None).unwrap();
+    let after = store.estimate_fee_rate(2, Some("conservative".to_string())).unwrap();
+    assert_eq!(after["samples"], json!(10));
+    assert_eq!(after["historical"], before["historical"]);
 
-    store
-      .record_loaded(vec![json!({
-        "txid": a,
-        "transaction": { "txid": a, "feeRate": 10 },
-        "providerName": "providerA"
-      })])
-      .unwrap();
+    let snapshot = store.export_snapshot_bytes();
+    store.import_snapshot_bytes(&snapshot).unwrap();
+    assert_eq!(store.estimate_fee_rate(2, None).unwrap()["samples"], json!(10));
 
-    store
-      .merge_snapshot(json!({
-        "providerA": [
-          { "txid": b, "metadata": meta(&b, 2500) },
-          { "txid": c, "metadata": meta(&c, 3000) }
-        ]
-      }))
+    store.clear();
+    assert_eq!(store.estimate_fee_rate(2, None).unwrap()["samples"], json!(0));
+  }
+
+  #[test]
+  fn native_mempool_delta_spans_apply_snapshot() {
+    let [a, b, c] = ids(["a", "b", "c"]);
+    let mut leader = NativeMempoolState::new();
+    leader.apply_snapshot(snapshot([entry(&a, 1000), entry(&b, 2000)]), None).unwrap();
+    let mut replica = NativeMempoolState::new();
+    replica.import_snapshot(leader.export_snapshot()).unwrap();
+    let synced = leader.get_change_seq();
+
+    // `a` is re-described, `b` dropped and `c` new; an unchanged refresh adds nothing.
+    let refreshed = snapshot([entry(&a, 1500), entry(&c, 500)]);
+    leader.apply_snapshot(refreshed.clone(), None).unwrap();
+    let delta = leader.export_delta(synced).unwrap();
+    assert_eq!(txids_of(&delta["added"]), vec![c.clone()]);
+    assert_eq!(txids_of(&delta["updated"]), vec![a.clone()]);
+    assert_eq!(delta["removed"], json!([b]));
+
+    let seq = leader.get_change_seq();
+    leader.apply_snapshot(refreshed, None).unwrap();
+    let idle = leader.export_delta(seq).unwrap();
+    assert_eq!(
+      (idle["added"].clone(), idle["updated"].clone(), idle["removed"].clone()),
+      (json!([]), json!([]), json!([]))
+    );
+
+    replica.apply_delta(delta).unwrap();
+    assert_eq!(replica.get_transaction_metadata(a.clone()).unwrap()["fee"], json!(1500));
+    assert!(replica.has_transaction(c.clone()) && !replica.has_transaction(b.clone()));
+  }
+
+  #[test]
+  fn native_mempool_delta_replaces_provider_membership() {
+    let [a, b] = ids(["a", "b"]);
+    let mut leader = NativeMempoolState::new();
+    leader
+      .apply_snapshot(json!({ "providerA": [entry(&a, 1000)], "providerB": [entry(&a, 1000), entry(&b, 2000)] }), None)
       .unwrap();
+    let mut replica = NativeMempoolState::new();
+    replica.import_snapshot(leader.export_snapshot()).unwrap();
+    replica.set_pending_order("feeRate".into()).unwrap();
+    assert_eq!(replica.pending_txids("providerA".into(), 10.0), vec![a.clone()]);
+    let synced = leader.get_change_seq();
 
-    assert!(store.has_transaction(a.clone()));
-    assert!(store.has_transaction(b.clone()));
-    assert!(store.has_transaction(c.clone()));
-    assert!(store.is_transaction_loaded(a.clone()));
-    assert_eq!(store.get_transaction_metadata(b.clone()).unwrap()["fee"], json!(2500));
+    // providerA drops `a` with unchanged metadata, so providerB now lists it.
+    leader.apply_snapshot(json!({ "providerB": [entry(&a, 1000), entry(&b, 2000)] }), None).unwrap();
+    let delta = leader.export_delta(synced).unwrap();
+    assert_eq!(txids_of(&delta["updated"]), vec![a.clone()]);
+    assert_eq!(delta["updated"][0]["providers"], json!(["providerB"]));
 
-    let pending = store.pending_txids("providerA".to_string(), 10.0);
-    assert!(!pending.contains(&a));
-    assert!(pending.contains(&b));
-    assert!(pending.contains(&c));
+    // Deltas append to provider lists, so compare membership, not order.
+    let members = |store: &NativeMempoolState| {
+      let mut lists = store.export_snapshot()["providerTx"].clone();
+      for pair in lists.as_array_mut().unwrap() {
+        pair[1].as_array_mut().unwrap().sort_by_key(Value::to_string);
+      }
+      lists
+    };
+    replica.apply_delta(delta).unwrap();
+    assert_eq!(members(&replica), members(&leader));
+    assert!(replica.pending_txids("providerA".into(), 10.0).is_empty());
+    assert_eq!(replica.pending_txids("providerB".into(), 10.0), vec![a.clone(), b.clone()]);
   }
 
   #[test]
-  fn native_mempool_remove_txids_cleans_indexes() {
-    let a = "a".repeat(64);
-    let b = "b".repeat(64);
+  fn native_mempool_partial_records_round_trip_unchanged() {
+    let [a] = ids(["a"]);
+    let metadata = json!({ "txid": a, "fee": 1000, "fees": { "base": 1000 }, "source": "esplora" });
+    let transaction = json!({
+      "txid": a,
+      "vsize": 110,
+      "vin": [{ "coinbase": "03a0bb0d", "sequence": 4294967295u32 }],
+      "vout": [{ "value": 0.5, "scriptPubKey": { "hex": "51", "asm": "OP_TRUE" } }],
+      "blockhash": "00".repeat(32)
+    });
     let mut store = NativeMempoolState::new();
+    store.apply_snapshot(snapshot([json!({ "txid": a, "metadata": metadata })]), None).unwrap();
+    store.record_loaded(vec![json!({ "txid": a, "transaction": transaction })], None).unwrap();
 
-    store
-      .apply_snapshot(json!({
-        "providerA": [
-          { "txid": a, "metadata": meta(&a, 1000) },
-          { "txid": b, "metadata": meta(&b, 2000) }
-        ]
-      }))
-      .unwrap();
+    assert_eq!(store.get_transaction_metadata(a.clone()).unwrap(), metadata);
+    assert_eq!(store.get_full_transaction(a.clone()).unwrap(), transaction);
 
-    store
-      .record_loaded(vec![json!({
-        "txid": b,
-        "transaction": { "txid": b, "feeRate": 20 },
-        "providerName": "providerA"
-      })])
-      .unwrap();
+    let snapshot = store.export_snapshot();
+    assert_eq!(snapshot["metadata"][0][1], metadata);
+    let mut restored = NativeMempoolState::new();
+    restored.import_snapshot(snapshot.clone()).unwrap();
+    assert_eq!(restored.export_snapshot(), snapshot);
 
-    store.remove_txids(vec![b.clone()]).unwrap();
+    let mut from_bytes = NativeMempoolState::new();
+    from_bytes.import_snapshot_bytes(&store.export_snapshot_bytes()).unwrap();
+    assert_eq!(from_bytes.get_transaction_metadata(a.clone()).unwrap(), metadata);
+    assert_eq!(from_bytes.get_full_transaction(a).unwrap(), transaction);
+  }
 
-    assert!(store.has_transaction(a.clone()));
-    assert!(!store.has_transaction(b.clone()));
-    assert!(!store.tx_ids().contains(&b));
-    assert!(store.get_transaction_metadata(b.clone()).is_none());
-    assert!(store.get_full_transaction(b.clone()).is_none());
-    assert!(!store.pending_txids("providerA".to_string(), 10.0).contains(&b));
+  #[test]
+  fn native_mempool_remove_txids_separates_drops_from_confirmations() {
+    let [dropped, child, confirmed] = ids(["a", "b", "c"]);
+    let now_secs = now_ms() / 1000;
+    let timed = |txid: &str| json!({ "txid": txid, "metadata": { "fee": 1000, "vsize": 100, "time": now_secs - 60 } });
+    let samples = |store: &NativeMempoolState| store.estimate_fee_rate(2, None).unwrap()["samples"].clone();
+    let mut store = NativeMempoolState::new();
+    store.apply_snapshot(snapshot([timed(&dropped), timed(&confirmed)]), None).unwrap();
+    store.record_loaded(vec![spend(&child, &[(&dropped, 0)])], None).unwrap();
+    store.watch(json!({ "txids": [dropped, child, confirmed] }));
 
-    let snapshot = store.export_snapshot();
-    assert!(!snapshot["txids"].as_array().unwrap().iter().any(|v| v.as_str() == Some(b.as_str())));
+    let report = store.remove_txids(vec![dropped.clone()], Some(true), None).unwrap();
+    assert_eq!(
+      report["watchHits"],
+      json!([
+        { "kind": "txDropped", "txid": dropped, "reason": "dropped" },
+        { "kind": "txDropped", "txid": child, "reason": "descendant" }
+      ])
+    );
+    assert_eq!(samples(&store), json!(0));
+
+    let report = store.remove_txids(vec![confirmed.clone()], None, None).unwrap();
+    assert_eq!(report["watchHits"], json!([{ "kind": "txConfirmed", "txid": confirmed }]));
+    assert_eq!(samples(&store), json!(1));
   }
 }
@@ -0,0 +1,932 @@
+use napi::{Error, Result};
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+use std::mem::size_of;
+
+use crate::transaction::script::{address, script_type, Network};
+use crate::transaction::{hash_to_key, Reader, Transaction};
+use crate::utils::memory::vec_bytes;
+use crate::utils::{
+  bool_field, i64_field, number_field, parse_txid, string_field, txid_to_hex, u32_field, u64_field, TxKey,
+};
+
+const SATS_PER_BTC: f64 = 100_000_000.0;
+
+fn txid_field(value: &Value, key: &str) -> Option<TxKey> {
+  string_field(value, key).and_then(parse_txid)
+}
+
+fn btc_to_sats(btc: f64) -> u64 {
+  (btc * SATS_PER_BTC).max(0.0).round() as u64
+}
+
+fn decode_hex(hex_str: &str) -> Option<Box<[u8]>> {
+  hex::decode(hex_str).ok().map(Vec::into_boxed_slice)
+}
+
+/// Input fields a typed record does not model, kept verbatim so they survive
+/// the trip back to JS. `None` for the usual normalized payloads.
+pub type ExtraFields = Option<Box<Map<String, Value>>>;
+
+fn extra_fields(value: &Value, known: &[&str]) -> ExtraFields {
+  let extra: Map<String, Value> = value
+    .as_object()?
+    .iter()
+    .filter(|(key, _)| !known.contains(&key.as_str()))
+    .map(|(k, v)| (k.clone(), v.clone()))
+    .collect();
+  (!extra.is_empty()).then(|| Box::new(extra))
+}
+
+fn put_extra_fields(out: &mut Map<String, Value>, extra: &ExtraFields) {
+  for (key, value) in extra.iter().flat_map(|extra| extra.iter()) {
+    out.entry(key.clone()).or_insert_with(|| value.clone());
+  }
+}
+
+fn extra_bytes(extra: &ExtraFields) -> usize {
+  extra.as_ref().map_or(0, |extra| serde_json::to_vec(extra).map_or(0, |json| json.len()))
+}
+
+/// Mask with bit `i` set for every `fields[i]` missing from `value`.
+fn absent_fields(value: &Value, fields: &[&str]) -> u16 {
+  fields.iter().enumerate().filter(|(_, field)| value.get(**field).is_none()).fold(0, |mask, (bit, _)| mask | 1 << bit)
+}
+
+/// Writes `values[i]` under `fields[i]` unless bit `i` of `absent` is set.
+fn put_fields<const N: usize>(out: &mut Map<String, Value>, fields: &[&str; N], absent: u16, values: [Value; N]) {
+  for (bit, (field, value)) in fields.iter().zip(values).enumerate() {
+    if absent & (1 << bit) == 0 {
+      out.insert((*field).into(), value);
+    }
+  }
+}
+
+const METADATA_FIELDS: [&str; 15] = [
+  "vsize",
+  "weight",
+  "fee",
+  "modifiedfee",
+  "time",
+  "height",
+  "depends",
+  "descendantcount",
+  "descendantsize",
+  "descendantfees",
+  "ancestorcount",
+  "ancestorsize",
+  "ancestorfees",
+  "fees",
+  "bip125_replaceable",
+];
+const FEES_FIELDS: [&str; 4] = ["base", "modified", "ancestor", "descendant"];
+const TRANSACTION_FIELDS: [&str; 10] =
+  ["hash", "version", "size", "strippedsize", "sizeWithoutWitnesses", "vsize", "weight", "locktime", "vin", "vout"];
+const VOUT_FIELDS: [&str; 2] = ["value", "n"];
+const PREVOUT_FIELDS: [&str; 1] = ["value"];
+
+/// Typed form of the `MempoolTxMetadata` object produced by the TS normalizer
+/// (`getrawmempool true` / `getmempoolentry` shape).
+///
+/// The txid itself is not stored: entries are keyed by handle and the txid is
+/// restored from the store's txid table when converting back to JS. Fees are
+/// kept in satoshis, sizes and counts as `u32`, and txid references as raw
+/// 32-byte keys. `modified` fees are signed because `prioritisetransaction`
+/// may apply a negative delta.
+///
+/// Fields missing from the input are flagged in `absent` and left out again
+/// on output; unmodeled fields are kept in `extra`. Records built in Rust have
+/// every field and nothing extra.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MempoolTxMetadata {
+  pub wtxid: Option<TxKey>,
+  pub vsize: u32,
+  pub weight: u32,
+  pub fee: u64,
+  pub modified_fee: i64,
+  pub time: u64,
+  pub height: u32,
+  pub depends: Box<[TxKey]>,
+  pub descendant_count: u32,
+  pub descendant_size: u32,
+  pub descendant_fees: u64,
+  pub ancestor_count: u32,
+  pub ancestor_size: u32,
+  pub ancestor_fees: u64,
+  pub fees: MempoolFees,
+  pub bip125_replaceable: bool,
+  pub unbroadcast: Option<bool>,
+  /// Bit per `METADATA_FIELDS` entry missing from the input.
+  pub absent: u16,
+  pub extra: ExtraFields,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MempoolFees {
+  pub base: u64,
+  pub modified: i64,
+  pub ancestor: u64,
+  pub descendant: u64,
+  /// Bit per `FEES_FIELDS` entry missing from the input.
+  pub absent: u8,
+}
+
+impl MempoolTxMetadata {
+  /// Parses a JS metadata object. Returns `None` for non-objects; missing
+  /// numeric fields read as zero in fee and size calculations but are not
+  /// written back by `to_value`.
+  pub fn from_value(value: &Value) -> Option<Self> {
+    if !value.is_object() {
+      return None;
+    }
+
+    let depends = value
+      .get("depends")
+      .and_then(Value::as_array)
+      .map(|ids| ids.iter().filter_map(Value::as_str).filter_map(parse_txid).collect())
+      .unwrap_or_default();
+
+    let fees = value
+      .get("fees")
+      .map(|fees| MempoolFees {
+        base: u64_field(fees, "base"),
+        modified: i64_field(fees, "modified"),
+        ancestor: u64_field(fees, "ancestor"),
+        descendant: u64_field(fees, "descendant"),
+        absent: absent_fields(fees, &FEES_FIELDS) as u8,
+      })
+      .unwrap_or_default();
+
+    Some(Self {
+      wtxid: txid_field(value, "wtxid"),
+      vsize: u32_field(value, "vsize"),
+      weight: u32_field(value, "weight"),
+      fee: u64_field(value, "fee"),
+      modified_fee: i64_field(value, "modifiedfee"),
+      time: u64_field(value, "time"),
+      height: u32_field(value, "height"),
+      depends,
+      descendant_count: u32_field(value, "descendantcount"),
+      descendant_size: u32_field(value, "descendantsize"),
+      descendant_fees: u64_field(value, "descendantfees"),
+      ancestor_count: u32_field(value, "ancestorcount"),
+      ancestor_size: u32_field(value, "ancestorsize"),
+      ancestor_fees: u64_field(value, "ancestorfees"),
+      fees,
+      bip125_replaceable: bool_field(value, "bip125_replaceable").unwrap_or(false),
+      unbroadcast: bool_field(value, "unbroadcast"),
+      absent: absent_fields(value, &METADATA_FIELDS),
+      extra: extra_fields(value, &[&["txid", "wtxid", "unbroadcast"], &METADATA_FIELDS[..]].concat()),
+    })
+  }
+
+  pub fn to_value(&self, txid: TxKey) -> Value {
+    let mut out = Map::new();
+    out.insert("txid".into(), json!(txid_to_hex(txid)));
+    if let Some(wtxid) = self.wtxid {
+      out.insert("wtxid".into(), json!(txid_to_hex(wtxid)));
+    }
+    let mut fees = Map::new();
+    put_fields(
+      &mut fees,
+      &FEES_FIELDS,
+      self.fees.absent as u16,
+      [json!(self.fees.base), json!(self.fees.modified), json!(self.fees.ancestor), json!(self.fees.descendant)],
+    );
+    put_fields(
+      &mut out,
+      &METADATA_FIELDS,
+      self.absent,
+      [
+        json!(self.vsize),
+        json!(self.weight),
+        json!(self.fee),
+        json!(self.modified_fee),
+        json!(self.time),
+        json!(self.height),
+        Value::Array(self.depends.iter().map(|key| json!(txid_to_hex(*key))).collect()),
+        json!(self.descendant_count),
+        json!(self.descendant_size),
+        json!(self.descendant_fees),
+        json!(self.ancestor_count),
+        json!(self.ancestor_size),
+        json!(self.ancestor_fees),
+        Value::Object(fees),
+        json!(self.bip125_replaceable),
+      ],
+    );
+    if let Some(unbroadcast) = self.unbroadcast {
+      out.insert("unbroadcast".into(), json!(unbroadcast));
+    }
+    put_extra_fields(&mut out, &self.extra);
+    Value::Object(out)
+  }
+
+  /// Absolute fee in satoshis, using the same fallback order as the TS
+  /// aggregate: `fee`, then `modifiedfee`, then `fees.modified`/`fees.base`.
+  pub fn fee_sats(&self) -> u64 {
+    if self.fee > 0 {
+      self.fee
+    } else if self.modified_fee > 0 {
+      self.modified_fee as u64
+    } else if self.fees.modified > 0 {
+      self.fees.modified as u64
+    } else {
+      self.fees.base
+    }
+  }
+
+  /// Fee rate in sat/vB derived from `fee_sats()`.
+  pub fn fee_rate(&self) -> f64 {
+    if self.vsize == 0 {
+      return 0.0;
+    }
+    self.fee_sats() as f64 / self.vsize as f64
+  }
+
+  /// BIP141 weight, falling back to `vsize * 4` when a provider omits it.
+  pub fn weight_units(&self) -> u64 {
+    if self.weight > 0 {
+      self.weight as u64
+    } else {
+      self.vsize as u64 * 4
+    }
+  }
+
+  /// Heap bytes owned by this record beyond its inline size.
+  pub fn heap_bytes(&self) -> usize {
+    self.depends.len() * size_of::<TxKey>() + extra_bytes(&self.extra)
+  }
+}
+
+/// Bitcoin Core `scriptPubKey.type` names. Unknown names are preserved as-is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptType {
+  PubKey,
+  PubKeyHash,
+  ScriptHash,
+  Multisig,
+  NullData,
+  WitnessV0KeyHash,
+  WitnessV0ScriptHash,
+  WitnessV1Taproot,
+  WitnessUnknown,
+  Anchor,
+  NonStandard,
+  Other(Box<str>),
+}
+
+impl ScriptType {
+  pub fn parse(name: &str) -> Self {
+    match name {
+      "pubkey" => Self::PubKey,
+      "pubkeyhash" => Self::PubKeyHash,
+      "scripthash" => Self::ScriptHash,
+      "multisig" => Self::Multisig,
+      "nulldata" => Self::NullData,
+      "witness_v0_keyhash" => Self::WitnessV0KeyHash,
+      "witness_v0_scripthash" => Self::WitnessV0ScriptHash,
+      "witness_v1_taproot" => Self::WitnessV1Taproot,
+      "witness_unknown" => Self::WitnessUnknown,
+      "anchor" => Self::Anchor,
+      "nonstandard" => Self::NonStandard,
+      other => Self::Other(other.into()),
+    }
+  }
+
+  pub fn as_str(&self) -> &str {
+    match self {
+      Self::PubKey => "pubkey",
+      Self::PubKeyHash => "pubkeyhash",
+      Self::ScriptHash => "scripthash",
+      Self::Multisig => "multisig",
+      Self::NullData => "nulldata",
+      Self::WitnessV0KeyHash => "witness_v0_keyhash",
+      Self::WitnessV0ScriptHash => "witness_v0_scripthash",
+      Self::WitnessV1Taproot => "witness_v1_taproot",
+      Self::WitnessUnknown => "witness_unknown",
+      Self::Anchor => "anchor",
+      Self::NonStandard => "nonstandard",
+      Self::Other(name) => name,
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LightScriptPubKey {
+  pub script_type: Option<ScriptType>,
+  pub address: Option<Box<str>>,
+  pub addresses: Option<Box<[Box<str>]>>,
+  /// Raw script bytes; exposed to JS as the `hex` field.
+  pub script: Option<Box<[u8]>>,
+  pub extra: ExtraFields,
+}
+
+impl LightScriptPubKey {
+  fn heap_bytes(&self) -> usize {
+    let addresses = self.addresses.iter().flatten();
+    self.address.as_ref().map_or(0, |address| address.len())
+      + addresses.map(|address| size_of::<Box<str>>() + address.len()).sum::<usize>()
+      + self.script.as_ref().map_or(0, |script| script.len())
+      + extra_bytes(&self.extra)
+  }
+
+  fn from_value(value: &Value) -> Self {
+    Self {
+      script_type: string_field(value, "type").map(ScriptType::parse),
+      address: string_field(value, "address").map(Into::into),
+      addresses: value
+        .get("addresses")
+        .and_then(Value::as_array)
+        .map(|items| items.iter().filter_map(Value::as_str).map(Into::into).collect()),
+      script: string_field(value, "hex").and_then(decode_hex),
+      extra: extra_fields(value, &["type", "address", "addresses", "hex"]),
+    }
+  }
+
+  fn to_value(&self) -> Value {
+    let mut out = Map::new();
+    if let Some(script_type) = &self.script_type {
+      out.insert("type".into(), json!(script_type.as_str()));
+    }
+    if let Some(address) = &self.address {
+      out.insert("address".into(), json!(address));
+    }
+    if let Some(addresses) = &self.addresses {
+      out.insert("addresses".into(), json!(addresses));
+    }
+    if let Some(script) = &self.script {
+      out.insert("hex".into(), json!(hex::encode(script)));
+    }
+    put_extra_fields(&mut out, &self.extra);
+    Value::Object(out)
+  }
+}
+
+/// Spent output carried by `vin[].prevout` (`getrawtransaction` verbosity 2).
+/// Optional: providers that do not send it cost nothing extra.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LightPrevout {
+  pub value_sat: u64,
+  pub script_pub_key: Option<LightScriptPubKey>,
+  /// Bit per `PREVOUT_FIELDS` entry missing from the input.
+  pub absent: u8,
+  pub extra: ExtraFields,
+}
+
+impl LightPrevout {
+  fn from_value(value: &Value) -> Self {
+    Self {
+      value_sat: btc_to_sats(number_field(value, "value").unwrap_or(0.0)),
+      script_pub_key: value.get("scriptPubKey").filter(|v| v.is_object()).map(LightScriptPubKey::from_value),
+      absent: absent_fields(value, &PREVOUT_FIELDS) as u8,
+      extra: extra_fields(value, &["value", "scriptPubKey"]),
+    }
+  }
+
+  fn to_value(&self) -> Value {
+    let mut out = Map::new();
+    put_fields(&mut out, &PREVOUT_FIELDS, self.absent as u16, [json!(self.value_sat as f64 / SATS_PER_BTC)]);
+    if let Some(script_pub_key) = &self.script_pub_key {
+      out.insert("scriptPubKey".into(), script_pub_key.to_value());
+    }
+    put_extra_fields(&mut out, &self.extra);
+    Value::Object(out)
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LightVin {
+  pub txid: Option<TxKey>,
+  pub vout: Option<u32>,
+  pub sequence: Option<u32>,
+  pub prevout: Option<Box<LightPrevout>>,
+  pub extra: ExtraFields,
+}
+
+impl LightVin {
+  fn from_value(value: &Value) -> Self {
+    Self {
+      txid: txid_field(value, "txid"),
+      vout: number_field(value, "vout").map(|_| u32_field(value, "vout")),
+      sequence: number_field(value, "sequence").map(|_| u32_field(value, "sequence")),
+      prevout: value.get("prevout").filter(|v| v.is_object()).map(|v| Box::new(LightPrevout::from_value(v))),
+      extra: extra_fields(value, &["txid", "vout", "sequence", "prevout"]),
+    }
+  }
+
+  fn to_value(&self) -> Value {
+    let mut out = Map::new();
+    if let Some(txid) = self.txid {
+      out.insert("txid".into(), json!(txid_to_hex(txid)));
+    }
+    if let Some(vout) = self.vout {
+      out.insert("vout".into(), json!(vout));
+    }
+    if let Some(sequence) = self.sequence {
+      out.insert("sequence".into(), json!(sequence));
+    }
+    if let Some(prevout) = &self.prevout {
+      out.insert("prevout".into(), prevout.to_value());
+    }
+    put_extra_fields(&mut out, &self.extra);
+    Value::Object(out)
+  }
+}
+
+/// Output value is kept in satoshis and converted back to the BTC float used
+/// by the TS `LightVout` shape only when crossing the N-API boundary.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LightVout {
+  pub value_sat: u64,
+  pub n: u32,
+  pub script_pub_key: Option<LightScriptPubKey>,
+  /// Bit per `VOUT_FIELDS` entry missing from the input.
+  pub absent: u8,
+  pub extra: ExtraFields,
+}
+
+impl LightVout {
+  fn from_value(value: &Value) -> Self {
+    Self {
+      value_sat: btc_to_sats(number_field(value, "value").unwrap_or(0.0)),
+      n: u32_field(value, "n"),
+      script_pub_key: value.get("scriptPubKey").filter(|v| v.is_object()).map(LightScriptPubKey::from_value),
+      absent: absent_fields(value, &VOUT_FIELDS) as u8,
+      extra: extra_fields(value, &["value", "n", "scriptPubKey"]),
+    }
+  }
+
+  fn to_value(&self) -> Value {
+    let mut out = Map::new();
+    put_fields(
+      &mut out,
+      &VOUT_FIELDS,
+      self.absent as u16,
+      [json!(self.value_sat as f64 / SATS_PER_BTC), json!(self.n)],
+    );
+    if let Some(script_pub_key) = &self.script_pub_key {
+      out.insert("scriptPubKey".into(), script_pub_key.to_value());
+    }
+    put_extra_fields(&mut out, &self.extra);
+    Value::Object(out)
+  }
+}
+
+/// Typed form of the TS `LightTransaction` produced by `Mempool.normalize()`.
+///
+/// `hash`/`wtxid` are stored only when they differ from the txid; the common
+/// non-witness case therefore costs no extra 32-byte key. Absent and
+/// unmodeled input fields are tracked as in `MempoolTxMetadata`, here and in
+/// the nested input/output records.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LightTransaction {
+  pub hash: Option<TxKey>,
+  pub wtxid: Option<TxKey>,
+  pub version: i32,
+  pub size: u32,
+  pub stripped_size: u32,
+  pub vsize: u32,
+  pub weight: u32,
+  pub locktime: u32,
+  pub vin: Box<[LightVin]>,
+  pub vout: Box<[LightVout]>,
+  pub fee_rate: Option<f64>,
+  pub bip125_replaceable: Option<bool>,
+  /// Bit per `TRANSACTION_FIELDS` entry missing from the input.
+  pub absent: u16,
+  pub extra: ExtraFields,
+}
+
+impl LightTransaction {
+  /// `(txid, vout)` of every input with a known prevout; coinbase inputs and
+  /// slim payloads without `vin` yield nothing.
+  pub fn spent_outpoints(&self) -> impl Iterator<Item = (TxKey, u32)> + '_ {
+    self.vin.iter().filter_map(|vin| Some((vin.txid?, vin.vout?)))
+  }
+
+  /// Heap bytes owned by this transaction beyond its inline size: input and
+  /// output records, boxed prevouts and script/address payloads.
+  pub fn heap_bytes(&self) -> usize {
+    let script_bytes =
+      |script_pub_key: &Option<LightScriptPubKey>| script_pub_key.as_ref().map_or(0, LightScriptPubKey::heap_bytes);
+    let prevout_bytes = |prevout: &LightPrevout| {
+      size_of::<LightPrevout>() + script_bytes(&prevout.script_pub_key) + extra_bytes(&prevout.extra)
+    };
+    let vin: usize =
+      self.vin.iter().map(|vin| vin.prevout.as_deref().map_or(0, prevout_bytes) + extra_bytes(&vin.extra)).sum();
+    let vout: usize = self.vout.iter().map(|vout| script_bytes(&vout.script_pub_key) + extra_bytes(&vout.extra)).sum();
+    self.vin.len() * size_of::<LightVin>()
+      + self.vout.len() * size_of::<LightVout>()
+      + vin
+      + vout
+      + extra_bytes(&self.extra)
+  }
+
+  pub fn from_value(value: &Value, txid: TxKey) -> Option<Self> {
+    if !value.is_object() {
+      return None;
+    }
+
+    let stripped_size = number_field(value, "strippedsize")
+      .map(|_| u32_field(value, "strippedsize"))
+      .unwrap_or_else(|| u32_field(value, "sizeWithoutWitnesses"));
+
+    Some(Self {
+      hash: txid_field(value, "hash").filter(|hash| *hash != txid),
+      wtxid: txid_field(value, "wtxid"),
+      version: i64_field(value, "version") as i32,
+      size: u32_field(value, "size"),
+      stripped_size,
+      vsize: u32_field(value, "vsize"),
+      weight: u32_field(value, "weight"),
+      locktime: u32_field(value, "locktime"),
+      vin: value
+        .get("vin")
+        .and_then(Value::as_array)
+        .map(|items| items.iter().map(LightVin::from_value).collect())
+        .unwrap_or_default(),
+      vout: value
+        .get("vout")
+        .and_then(Value::as_array)
+        .map(|items| items.iter().map(LightVout::from_value).collect())
+        .unwrap_or_default(),
+      fee_rate: number_field(value, "feeRate").filter(|rate| rate.is_finite()),
+      bip125_replaceable: bool_field(value, "bip125_replaceable"),
+      absent: absent_fields(value, &TRANSACTION_FIELDS),
+      extra: extra_fields(
+        value,
+        &[&["txid", "wtxid", "feeRate", "bip125_replaceable"], &TRANSACTION_FIELDS[..]].concat(),
+      ),
+    })
+  }
+
+  /// Light form of a decoded raw transaction, with output script types and,
+  /// given a `network`, addresses. Raw bytes carry neither prevouts nor a fee
+  /// rate; coinbase inputs have no `txid`/`vout`.
+  pub fn from_raw(tx: &Transaction, network: Option<Network>) -> Self {
+    let coinbase = tx.is_coinbase();
+    Self {
+      hash: Some(tx.wtxid).filter(|wtxid| *wtxid != tx.txid),
+      wtxid: None,
+      version: tx.version,
+      size: tx.size,
+      stripped_size: tx.stripped_size,
+      vsize: tx.vsize(),
+      weight: tx.weight(),
+      locktime: tx.locktime,
+      vin: tx
+        .inputs
+        .iter()
+        .map(|input| LightVin {
+          txid: (!coinbase).then_some(input.prev_txid),
+          vout: (!coinbase).then_some(input.vout),
+          sequence: Some(input.sequence),
+          prevout: None,
+          extra: None,
+        })
+        .collect(),
+      vout: tx
+        .outputs
+        .iter()
+        .enumerate()
+        .map(|(n, output)| LightVout {
+          value_sat: output.value_sat,
+          n: n as u32,
+          script_pub_key: Some(LightScriptPubKey {
+            script_type: Some(ScriptType::parse(script_type(&output.script_pubkey))),
+            address: network.and_then(|network| address(&output.script_pubkey, network)).map(Into::into),
+            addresses: None,
+            script: Some(output.script_pubkey.clone()),
+            extra: None,
+          }),
+          absent: 0,
+          extra: None,
+        })
+        .collect(),
+      fee_rate: None,
+      bip125_replaceable: Some(tx.signals_rbf()),
+      absent: 0,
+      extra: None,
+    }
+  }
+
+  pub fn to_value(&self, txid: TxKey) -> Value {
+    let mut out = Map::new();
+    out.insert("txid".into(), json!(txid_to_hex(txid)));
+    if let Some(wtxid) = self.wtxid {
+      out.insert("wtxid".into(), json!(txid_to_hex(wtxid)));
+    }
+    put_fields(
+      &mut out,
+      &TRANSACTION_FIELDS,
+      self.absent,
+      [
+        json!(txid_to_hex(self.hash.unwrap_or(txid))),
+        json!(self.version),
+        json!(self.size),
+        json!(self.stripped_size),
+        json!(self.stripped_size),
+        json!(self.vsize),
+        json!(self.weight),
+        json!(self.locktime),
+        Value::Array(self.vin.iter().map(LightVin::to_value).collect()),
+        Value::Array(self.vout.iter().map(LightVout::to_value).collect()),
+      ],
+    );
+    if let Some(fee_rate) = self.fee_rate {
+      out.insert("feeRate".into(), json!(fee_rate));
+    }
+    if let Some(replaceable) = self.bip125_replaceable {
+      out.insert("bip125_replaceable".into(), json!(replaceable));
+    }
+    put_extra_fields(&mut out, &self.extra);
+    Value::Object(out)
+  }
+}
+
+/// Serialized transaction passed to `recordLoadedRaw`.
+///
+/// The bytes are decoded once when stored: construction rejects anything that
+/// does not parse to the expected txid, and keeps the offsets and summary
+/// fields the store's indexes read, so indexing never decodes them again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawTransaction {
+  bytes: Box<[u8]>,
+  /// Offset of each input's outpoint; empty for a coinbase.
+  inputs: Box<[u32]>,
+  /// Value and script byte range of each output, by output index.
+  outputs: Box<[(u64, u32, u32)]>,
+  vsize: u32,
+  bip125_replaceable: bool,
+}
+
+impl RawTransaction {
+  /// Decodes `bytes`, which must serialize exactly one transaction whose txid
+  /// is `txid`. The decoded transaction is returned alongside.
+  pub fn parse(txid: TxKey, bytes: &[u8]) -> Result<(Self, Transaction)> {
+    let tx = Transaction::parse(bytes).map_err(|error| {
+      Error::from_reason(format!("Invalid raw transaction {}: {}", txid_to_hex(txid), error.reason))
+    })?;
+    if tx.txid != txid {
+      return Err(Error::from_reason(format!(
+        "Raw transaction hashes to {}, expected {}",
+        txid_to_hex(tx.txid),
+        txid_to_hex(txid)
+      )));
+    }
+
+    // `Transaction::parse` accepted the bytes, so this walk cannot fail.
+    let mut reader = Reader::new(bytes);
+    reader.take(4)?;
+    if reader.peek(0) == Some(0) {
+      reader.take(2)?;
+    }
+    let mut inputs = Vec::with_capacity(tx.inputs.len());
+    for _ in 0..reader.compact_size()? {
+      inputs.push(reader.position() as u32);
+      reader.take(36)?;
+      reader.var_bytes()?;
+      reader.u32()?;
+    }
+    if tx.is_coinbase() {
+      inputs.clear();
+    }
+    let mut outputs = Vec::with_capacity(tx.outputs.len());
+    for _ in 0..reader.compact_size()? {
+      let value_sat = reader.u64()?;
+      let script = reader.var_bytes()?;
+      let end = reader.position() as u32;
+      outputs.push((value_sat, end - script.len() as u32, end));
+    }
+
+    let raw = Self {
+      bytes: bytes.into(),
+      inputs: inputs.into(),
+      outputs: outputs.into(),
+      vsize: tx.vsize(),
+      bip125_replaceable: tx.signals_rbf(),
+    };
+    Ok((raw, tx))
+  }
+
+  pub fn bytes(&self) -> &[u8] {
+    &self.bytes
+  }
+
+  /// `(txid, vout)` spent by each input.
+  pub fn spent_outpoints(&self) -> impl Iterator<Item = (TxKey, u32)> + '_ {
+    self.inputs.iter().map(|offset| {
+      let at = *offset as usize;
+      let hash: [u8; 32] = self.bytes[at..at + 32].try_into().unwrap();
+      (hash_to_key(hash), u32::from_le_bytes(self.bytes[at + 32..at + 36].try_into().unwrap()))
+    })
+  }
+
+  /// Script and value of output `n`. Only the script bytes are set; raw
+  /// records carry no address.
+  pub fn output(&self, n: u32) -> Option<(LightScriptPubKey, u64)> {
+    let (value_sat, start, end) = *self.outputs.get(n as usize)?;
+    let script = LightScriptPubKey {
+      script_type: None,
+      address: None,
+      addresses: None,
+      script: Some(self.bytes[start as usize..end as usize].into()),
+      extra: None,
+    };
+    Some((script, value_sat))
+  }
+
+  pub fn output_count(&self) -> u32 {
+    self.outputs.len() as u32
+  }
+
+  /// The typed form returned to JS, without `feeRate` or prevouts.
+  pub fn decode(&self) -> Option<LightTransaction> {
+    Transaction::parse(&self.bytes).ok().map(|tx| LightTransaction::from_raw(&tx, None))
+  }
+
+  fn heap_bytes(&self) -> usize {
+    self.bytes.len() + self.inputs.len() * size_of::<u32>() + self.outputs.len() * size_of::<(u64, u32, u32)>()
+  }
+}
+
+/// Loaded transaction as held by the store: typed (`recordLoaded`) or as the
+/// serialized bytes passed to `recordLoadedRaw`, decoded when read.
+///
+/// A typed record costs several times its serialized size; boxing both
+/// variants keeps the map entries small.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StoredTransaction {
+  Light(Box<LightTransaction>),
+  Raw(Box<RawTransaction>),
+}
+
+impl StoredTransaction {
+  pub fn is_raw(&self) -> bool {
+    matches!(self, Self::Raw(_))
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    match self {
+      Self::Light(tx) => size_of::<LightTransaction>() + tx.heap_bytes(),
+      Self::Raw(raw) => size_of::<RawTransaction>() + raw.heap_bytes(),
+    }
+  }
+
+  /// `(txid, vout, prevout)` of every input that names its outpoint.
+  pub fn inputs(&self) -> impl Iterator<Item = (TxKey, u32, Option<&LightPrevout>)> + '_ {
+    let (light, raw) = match self {
+      Self::Light(tx) => (Some(tx.vin.iter()), None),
+      Self::Raw(raw) => (None, Some(raw.spent_outpoints())),
+    };
+    let light = light.into_iter().flatten().filter_map(|vin| Some((vin.txid?, vin.vout?, vin.prevout.as_deref())));
+    light.chain(raw.into_iter().flatten().map(|(txid, vout)| (txid, vout, None)))
+  }
+
+  pub fn spent_outpoints(&self) -> impl Iterator<Item = (TxKey, u32)> + '_ {
+    self.inputs().map(|(txid, vout, _)| (txid, vout))
+  }
+
+  /// Outputs with a script, as `(n, scriptPubKey, value)`.
+  pub fn outputs(&self) -> impl Iterator<Item = (u32, Cow<'_, LightScriptPubKey>, u64)> + '_ {
+    let (light, raw) = match self {
+      Self::Light(tx) => (Some(tx.vout.iter()), None),
+      Self::Raw(raw) => (None, Some((0..raw.output_count()).map(|n| (n, raw.as_ref())))),
+    };
+    let light = light
+      .into_iter()
+      .flatten()
+      .filter_map(|vout| Some((vout.n, Cow::Borrowed(vout.script_pub_key.as_ref()?), vout.value_sat)));
+    light.chain(raw.into_iter().flatten().filter_map(|(n, raw)| {
+      let (script, value_sat) = raw.output(n)?;
+      Some((n, Cow::Owned(script), value_sat))
+    }))
+  }
+
+  /// Script and value of output `n`.
+  pub fn output(&self, n: u32) -> Option<(LightScriptPubKey, u64)> {
+    match self {
+      Self::Light(tx) => {
+        let output = tx.vout.iter().find(|output| output.n == n)?;
+        Some((output.script_pub_key.clone()?, output.value_sat))
+      }
+      Self::Raw(raw) => raw.output(n),
+    }
+  }
+
+  pub fn fee_rate(&self) -> Option<f64> {
+    match self {
+      Self::Light(tx) => tx.fee_rate,
+      Self::Raw(_) => None,
+    }
+  }
+
+  pub fn vsize(&self) -> u32 {
+    match self {
+      Self::Light(tx) => tx.vsize,
+      Self::Raw(raw) => raw.vsize,
+    }
+  }
+
+  pub fn bip125_replaceable(&self) -> Option<bool> {
+    match self {
+      Self::Light(tx) => tx.bip125_replaceable,
+      Self::Raw(raw) => Some(raw.bip125_replaceable),
+    }
+  }
+
+  pub fn to_value(&self, txid: TxKey) -> Option<Value> {
+    match self {
+      Self::Light(tx) => Some(tx.to_value(txid)),
+      Self::Raw(raw) => raw.decode().map(|tx| tx.to_value(txid)),
+    }
+  }
+}
+
+impl From<LightTransaction> for StoredTransaction {
+  fn from(tx: LightTransaction) -> Self {
+    Self::Light(Box::new(tx))
+  }
+}
+
+impl From<RawTransaction> for StoredTransaction {
+  fn from(raw: RawTransaction) -> Self {
+    Self::Raw(Box::new(raw))
+  }
+}
+
+/// Compact provider reference used by per-tx records. Names are interned once
+/// per store in `ProviderNames`.
+pub type ProviderId = u16;
+
+/// Interns provider names so per-tx records carry a 2-byte id instead of a
+/// `String`. Provider sets are tiny and bounded by configuration, so ids are
+/// never recycled; this keeps ids valid across `applySnapshot` refreshes.
+#[derive(Default)]
+pub struct ProviderNames {
+  names: Vec<String>,
+}
+
+impl ProviderNames {
+  pub fn intern(&mut self, name: &str) -> ProviderId {
+    if let Some(id) = self.id_of(name) {
+      return id;
+    }
+    self.names.push(name.to_string());
+    (self.names.len() - 1) as ProviderId
+  }
+
+  pub fn id_of(&self, name: &str) -> Option<ProviderId> {
+    self.names.iter().position(|candidate| candidate == name).map(|id| id as ProviderId)
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    vec_bytes(&self.names) + self.names.iter().map(String::capacity).sum::<usize>()
+  }
+
+  /// Interned names in id order.
+  pub fn names(&self) -> &[String] {
+    &self.names
+  }
+
+  pub fn name(&self, id: ProviderId) -> Option<&str> {
+    self.names.get(id as usize).map(String::as_str)
+  }
+
+  pub fn clear(&mut self) {
+    self.names.clear();
+    self.names.shrink_to_fit();
+  }
+}
+
+/// Typed load-tracker record (`MempoolLoadInfo` in TS).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LoadInfo {
+  pub timestamp: u64,
+  pub fee_rate: f64,
+  pub provider: Option<ProviderId>,
+}
+
+impl LoadInfo {
+  pub fn from_value(value: &Value, providers: &mut ProviderNames) -> Option<Self> {
+    if !value.is_object() {
+      return None;
+    }
+    Some(Self {
+      timestamp: u64_field(value, "timestamp"),
+      fee_rate: number_field(value, "feeRate").unwrap_or(0.0),
+      provider: string_field(value, "providerName").map(|name| providers.intern(name)),
+    })
+  }
+
+  pub fn to_value(self, providers: &ProviderNames) -> Value {
+    let mut out = Map::new();
+    out.insert("timestamp".into(), json!(self.timestamp));
+    out.insert("feeRate".into(), json!(self.fee_rate));
+    if let Some(name) = self.provider.and_then(|id| providers.name(id)) {
+      out.insert("providerName".into(), json!(name));
+    }
+    Value::Object(out)
+  }
+}
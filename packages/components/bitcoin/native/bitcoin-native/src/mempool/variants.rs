@@ -0,0 +1,186 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use super::types::{MempoolTxMetadata, ProviderId, ProviderNames};
+use crate::utils::memory::{map_bytes, vec_bytes};
+use crate::utils::{txid_to_hex, TxKey};
+
+/// How the store picks a txid's metadata when providers disagree.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ReconcilePolicy {
+  /// `applySnapshot` keeps the first provider's metadata, `mergeSnapshot`
+  /// overwrites with the last one.
+  #[default]
+  Default,
+  /// The most recently reported variant.
+  Latest,
+  /// The variant most providers agree on (see `agree`); ties go to the group
+  /// holding the most recent report.
+  Quorum,
+  /// The variant with the highest fee; ties go to the most recent report.
+  HighestFee,
+}
+
+impl ReconcilePolicy {
+  pub fn parse(name: &str) -> Option<Self> {
+    match name {
+      "default" => Some(Self::Default),
+      "latest" => Some(Self::Latest),
+      "quorum" => Some(Self::Quorum),
+      "highestFee" => Some(Self::HighestFee),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      Self::Default => "default",
+      Self::Latest => "latest",
+      Self::Quorum => "quorum",
+      Self::HighestFee => "highestFee",
+    }
+  }
+}
+
+struct Variant {
+  provider: ProviderId,
+  reported_at: u64,
+  metadata: MempoolTxMetadata,
+}
+
+/// Metadata as reported by each provider, kept per handle.
+///
+/// Retention is opt-in because it stores up to one metadata record per
+/// provider and txid. Any policy other than `Default` needs the variants and
+/// turns retention on. Each handle's list is ordered from the oldest to the
+/// most recent report, one entry per provider.
+#[derive(Default)]
+pub struct MetadataVariants {
+  policy: ReconcilePolicy,
+  retain: bool,
+  variants: HashMap<u32, Vec<Variant>>,
+}
+
+impl MetadataVariants {
+  pub fn policy(&self) -> ReconcilePolicy {
+    self.policy
+  }
+
+  pub fn retains(&self) -> bool {
+    self.retain || self.policy != ReconcilePolicy::Default
+  }
+
+  /// Variants already kept stay; turning retention off drops them.
+  pub fn configure(&mut self, policy: ReconcilePolicy, retain: bool) {
+    self.policy = policy;
+    self.retain = retain;
+    if !self.retains() {
+      self.variants = HashMap::new();
+    }
+  }
+
+  pub fn to_value(&self) -> Value {
+    json!({ "policy": self.policy.as_str(), "retainVariants": self.retains() })
+  }
+
+  pub fn clear(&mut self) {
+    self.variants.clear();
+  }
+
+  pub fn shrink_to_fit(&mut self) {
+    self.variants.shrink_to_fit();
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    map_bytes(&self.variants)
+      + self
+        .variants
+        .values()
+        .map(|list| vec_bytes(list) + list.iter().map(|variant| variant.metadata.heap_bytes()).sum::<usize>())
+        .sum::<usize>()
+  }
+
+  /// Replaces `provider`'s variant of `handle`; a no-op unless retaining.
+  pub fn record(&mut self, handle: u32, provider: ProviderId, reported_at: u64, metadata: &MempoolTxMetadata) {
+    if !self.retains() {
+      return;
+    }
+    let list = self.variants.entry(handle).or_default();
+    list.retain(|variant| variant.provider != provider);
+    list.push(Variant { provider, reported_at, metadata: metadata.clone() });
+  }
+
+  pub fn remove(&mut self, handle: u32) {
+    self.variants.remove(&handle);
+  }
+
+  pub fn remap(&mut self, live: impl Fn(u32) -> Option<u32>) {
+    self.variants = std::mem::take(&mut self.variants)
+      .into_iter()
+      .filter_map(|(handle, list)| live(handle).map(|handle| (handle, list)))
+      .collect();
+  }
+
+  pub fn handles(&self) -> Vec<u32> {
+    self.variants.keys().copied().collect()
+  }
+
+  /// Metadata the policy selects for `handle`, or `None` under `Default` or
+  /// without variants.
+  pub fn resolve(&self, handle: u32) -> Option<&MempoolTxMetadata> {
+    let list = self.variants.get(&handle)?;
+    let selected = match self.policy {
+      ReconcilePolicy::Default => return None,
+      ReconcilePolicy::Latest => list.last(),
+      // `max_by_key` returns the last maximum, i.e. the most recent report.
+      ReconcilePolicy::HighestFee => list.iter().max_by_key(|variant| variant.metadata.fee_sats()),
+      ReconcilePolicy::Quorum => list
+        .iter()
+        .enumerate()
+        .filter(|(index, variant)| !list[index + 1..].iter().any(|later| agree(&later.metadata, &variant.metadata)))
+        .max_by_key(|(_, variant)| list.iter().filter(|other| agree(&other.metadata, &variant.metadata)).count())
+        .map(|(_, variant)| variant),
+    };
+    selected.map(|variant| &variant.metadata)
+  }
+
+  /// `{ txid, policy, distinct, variants: [{ provider, reportedAt, metadata }] }`
+  /// where `distinct` counts groups of disagreeing variants.
+  pub fn variants_value(&self, handle: u32, txid: TxKey, names: &ProviderNames) -> Value {
+    let list = self.variants.get(&handle).map(Vec::as_slice).unwrap_or(&[]);
+    let distinct = list
+      .iter()
+      .enumerate()
+      .filter(|(index, variant)| !list[..*index].iter().any(|earlier| agree(&earlier.metadata, &variant.metadata)))
+      .count();
+    let variants: Vec<Value> = list
+      .iter()
+      .map(|variant| {
+        json!({
+          "provider": names.name(variant.provider),
+          "reportedAt": variant.reported_at,
+          "metadata": variant.metadata.to_value(txid),
+        })
+      })
+      .collect();
+    json!({ "txid": txid_to_hex(txid), "policy": self.policy.as_str(), "distinct": distinct, "variants": variants })
+  }
+}
+
+/// Whether two reports describe the transaction the same way. Node-local
+/// fields (`time`, `height`, descendant stats, `unbroadcast`) are ignored,
+/// since they differ between nodes that agree on the transaction itself.
+fn agree(a: &MempoolTxMetadata, b: &MempoolTxMetadata) -> bool {
+  a.wtxid == b.wtxid
+    && a.vsize == b.vsize
+    && a.weight == b.weight
+    && a.fee == b.fee
+    && a.modified_fee == b.modified_fee
+    && a.fees.base == b.fees.base
+    && a.fees.modified == b.fees.modified
+    && a.depends == b.depends
+    && a.ancestor_count == b.ancestor_count
+    && a.ancestor_size == b.ancestor_size
+    && a.ancestor_fees == b.ancestor_fees
+    && a.bip125_replaceable == b.bip125_replaceable
+}
@@ -0,0 +1,180 @@
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+use super::spends::{outpoint_to_string, parse_outpoint, Outpoint};
+use super::types::{LightScriptPubKey, LightTransaction};
+use crate::utils::memory::set_bytes;
+use crate::utils::{parse_txid, txid_to_hex, TxKey};
+
+/// Why a watched txid left the mempool without confirming.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DropReason {
+  /// Replaced by a double spend / RBF loaded later.
+  Replaced,
+  /// Conflicts with an input spent by a connected block.
+  Conflicted,
+  /// Descendant of a replaced, conflicted or explicitly evicted entry.
+  Descendant,
+  /// Evicted to keep the store within its capacity limits.
+  Evicted,
+  /// First seen longer ago than the configured TTL.
+  Expired,
+  /// Removed by the caller as dropped or replaced (`removeTxids` with
+  /// `evictDescendants`).
+  Dropped,
+}
+
+impl DropReason {
+  fn as_str(self) -> &'static str {
+    match self {
+      Self::Replaced => "replaced",
+      Self::Conflicted => "conflicted",
+      Self::Descendant => "descendant",
+      Self::Evicted => "evicted",
+      Self::Expired => "expired",
+      Self::Dropped => "dropped",
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WatchHit {
+  /// A mempool tx pays a watched script or address. `value_sat` is in sats.
+  Payment {
+    txid: TxKey,
+    vout: u32,
+    value_sat: u64,
+  },
+  /// A mempool tx spends a watched outpoint.
+  OutpointSpent {
+    outpoint: Outpoint,
+    txid: TxKey,
+  },
+  /// A watched txid entered the store.
+  TxSeen {
+    txid: TxKey,
+  },
+  TxConfirmed {
+    txid: TxKey,
+  },
+  TxDropped {
+    txid: TxKey,
+    reason: DropReason,
+  },
+}
+
+impl WatchHit {
+  pub fn to_value(&self) -> Value {
+    match self {
+      Self::Payment { txid, vout, value_sat } => {
+        json!({ "kind": "payment", "txid": txid_to_hex(*txid), "vout": vout, "value": value_sat })
+      }
+      Self::OutpointSpent { outpoint, txid } => {
+        json!({ "kind": "outpointSpent", "outpoint": outpoint_to_string(*outpoint), "txid": txid_to_hex(*txid) })
+      }
+      Self::TxSeen { txid } => json!({ "kind": "txSeen", "txid": txid_to_hex(*txid) }),
+      Self::TxConfirmed { txid } => json!({ "kind": "txConfirmed", "txid": txid_to_hex(*txid) }),
+      Self::TxDropped { txid, reason } => {
+        json!({ "kind": "txDropped", "txid": txid_to_hex(*txid), "reason": reason.as_str() })
+      }
+    }
+  }
+}
+
+/// Registered scripts, addresses, outpoints and txids that mutations report on.
+///
+/// Matching is O(1) per output/input/txid touched by a mutation, and is skipped
+/// entirely while the watchlist is empty.
+#[derive(Default)]
+pub struct Watchlist {
+  scripts: HashSet<Box<[u8]>>,
+  addresses: HashSet<Box<str>>,
+  outpoints: HashSet<Outpoint>,
+  txids: HashSet<TxKey>,
+}
+
+impl Watchlist {
+  pub fn is_empty(&self) -> bool {
+    self.scripts.is_empty() && self.addresses.is_empty() && self.outpoints.is_empty() && self.txids.is_empty()
+  }
+
+  pub fn heap_bytes(&self) -> usize {
+    set_bytes(&self.scripts)
+      + self.scripts.iter().map(|script| script.len()).sum::<usize>()
+      + set_bytes(&self.addresses)
+      + self.addresses.iter().map(|address| address.len()).sum::<usize>()
+      + set_bytes(&self.outpoints)
+      + set_bytes(&self.txids)
+  }
+
+  pub fn clear(&mut self) {
+    self.scripts = HashSet::new();
+    self.addresses = HashSet::new();
+    self.outpoints = HashSet::new();
+    self.txids = HashSet::new();
+  }
+
+  /// Adds (or, with `remove`, drops) the entries of a
+  /// `{ scripts, addresses, outpoints, txids }` object. Malformed entries are
+  /// ignored, like malformed snapshot items elsewhere in the store.
+  pub fn update(&mut self, list: &Value, remove: bool) {
+    let strings =
+      |field: &str| list.get(field).and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str);
+
+    for script in strings("scripts").filter_map(|hex_str| hex::decode(hex_str).ok()) {
+      toggle(&mut self.scripts, script.into_boxed_slice(), remove);
+    }
+    for address in strings("addresses") {
+      toggle(&mut self.addresses, address.into(), remove);
+    }
+    for outpoint in strings("outpoints").filter_map(parse_outpoint) {
+      toggle(&mut self.outpoints, outpoint, remove);
+    }
+    for txid in strings("txids").filter_map(parse_txid) {
+      toggle(&mut self.txids, txid, remove);
+    }
+  }
+
+  pub fn to_value(&self) -> Value {
+    json!({
+      "scripts": self.scripts.iter().map(hex::encode).collect::<Vec<_>>(),
+      "addresses": self.addresses.iter().collect::<Vec<_>>(),
+      "outpoints": self.outpoints.iter().map(|outpoint| outpoint_to_string(*outpoint)).collect::<Vec<_>>(),
+      "txids": self.txids.iter().map(|txid| txid_to_hex(*txid)).collect::<Vec<_>>(),
+    })
+  }
+
+  pub fn watches_txid(&self, txid: &TxKey) -> bool {
+    self.txids.contains(txid)
+  }
+
+  fn watches_script(&self, script_pub_key: &LightScriptPubKey) -> bool {
+    script_pub_key.script.as_ref().is_some_and(|script| self.scripts.contains(script))
+      || script_pub_key.address.as_ref().is_some_and(|address| self.addresses.contains(address))
+      || script_pub_key.addresses.iter().flatten().any(|address| self.addresses.contains(address))
+  }
+
+  /// Payments to watched scripts and spends of watched outpoints by a newly
+  /// loaded transaction.
+  pub fn match_loaded(&self, txid: TxKey, tx: &LightTransaction, hits: &mut Vec<WatchHit>) {
+    if self.is_empty() {
+      return;
+    }
+    for output in tx.vout.iter() {
+      if output.script_pub_key.as_ref().is_some_and(|script_pub_key| self.watches_script(script_pub_key)) {
+        hits.push(WatchHit::Payment { txid, vout: output.n, value_sat: output.value_sat });
+      }
+    }
+    for outpoint in tx.spent_outpoints().filter(|outpoint| self.outpoints.contains(outpoint)) {
+      hits.push(WatchHit::OutpointSpent { outpoint, txid });
+    }
+  }
+}
+
+fn toggle<T: Eq + std::hash::Hash>(set: &mut HashSet<T>, value: T, remove: bool) {
+  if remove {
+    set.remove(&value);
+  } else {
+    set.insert(value);
+  }
+}
@@ -1,10 +1,16 @@
+mod partial_tree;
+mod proof;
+
+pub use partial_tree::{bitcoin_build_merkle_block, bitcoin_parse_merkle_block, bitcoin_verify_merkle_block};
+pub use proof::{bitcoin_get_merkle_proof, bitcoin_verify_merkle_proof};
+
 use napi_derive::napi;
 use serde_json::Value;
 use sha2::{Digest, Sha256};
 
 fn dsha256(data: &[u8]) -> [u8; 32] {
   let first = Sha256::digest(data);
-  Sha256::digest(&first).into()
+  Sha256::digest(first).into()
 }
 
 fn be_hex_to_le_bytes(be_hex: &str) -> Option<[u8; 32]> {
@@ -103,8 +109,16 @@ pub fn bitcoin_compute_merkle_root(txids_be: Vec<String>) -> String {
     return txids_be[0].clone();
   }
 
-  let mut level: Vec<[u8; 32]> = txids_be.iter().filter_map(|id| be_hex_to_le_bytes(id)).collect();
+  let level: Vec<[u8; 32]> = txids_be.iter().filter_map(|id| be_hex_to_le_bytes(id)).collect();
+  if level.is_empty() {
+    return "0".repeat(64);
+  }
+
+  le_bytes_to_be_hex(merkle_root(level))
+}
 
+/// Merkle root of non-empty little-endian (wire order) leaves.
+pub(crate) fn merkle_root(mut level: Vec<[u8; 32]>) -> [u8; 32] {
   while level.len() > 1 {
     if level.len() % 2 == 1 {
       level.push(*level.last().unwrap());
@@ -120,7 +134,7 @@ pub fn bitcoin_compute_merkle_root(txids_be: Vec<String>) -> String {
       .collect();
   }
 
-  le_bytes_to_be_hex(level[0])
+  level[0]
 }
 
 #[napi(js_name = "bitcoinVerifyMerkleRoot")]
@@ -0,0 +1,356 @@
+use std::collections::HashSet;
+
+use napi::bindgen_prelude::{Buffer, Either};
+use napi::{Error, Result};
+use napi_derive::napi;
+use serde_json::{json, Value};
+
+use super::proof::parent;
+use crate::block::{Block, BlockHeader, HEADER_LEN};
+use crate::transaction::{hash_to_key, key_to_hash, Reader};
+use crate::utils::txid_to_hex;
+
+/// `MAX_BLOCK_WEIGHT / MIN_TRANSACTION_WEIGHT`, Bitcoin Core's bound on the
+/// transaction count of a partial merkle tree.
+const MAX_TRANSACTIONS: u32 = 4_000_000 / 240;
+
+fn tree_width(total: u32, height: u32) -> u32 {
+  (total + (1 << height) - 1) >> height
+}
+
+fn tree_height(total: u32) -> u32 {
+  let mut height = 0;
+  while tree_width(total, height) > 1 {
+    height += 1;
+  }
+  height
+}
+
+fn write_compact_size(out: &mut Vec<u8>, value: usize) {
+  match value {
+    0..=0xfc => out.push(value as u8),
+    0xfd..=0xffff => {
+      out.push(0xfd);
+      out.extend((value as u16).to_le_bytes());
+    }
+    _ => {
+      out.push(0xfe);
+      out.extend((value as u32).to_le_bytes());
+    }
+  }
+}
+
+/// BIP37 partial merkle tree: a depth-first walk of the block's merkle tree
+/// with one flag bit per visited node and the hashes of the pruned subtrees.
+pub struct PartialMerkleTree {
+  pub total: u32,
+  /// Wire order.
+  pub hashes: Vec<[u8; 32]>,
+  pub bits: Vec<bool>,
+}
+
+/// Leaf index and wire-order txid of a matched transaction.
+pub type TreeMatch = (u32, [u8; 32]);
+
+/// Walk state of `PartialMerkleTree::extract_matches`.
+struct Extraction {
+  bits_used: usize,
+  hashes_used: usize,
+  matches: Vec<TreeMatch>,
+}
+
+impl PartialMerkleTree {
+  /// Tree over the wire-order `txids` proving those with `matched` set.
+  pub fn build(txids: &[[u8; 32]], matched: &[bool]) -> Self {
+    let mut tree = Self { total: txids.len() as u32, hashes: Vec::new(), bits: Vec::new() };
+    tree.build_node(tree_height(tree.total), 0, txids, matched);
+    tree
+  }
+
+  fn node_hash(&self, height: u32, pos: u32, txids: &[[u8; 32]]) -> [u8; 32] {
+    if height == 0 {
+      return txids[pos as usize];
+    }
+    let left = self.node_hash(height - 1, pos * 2, txids);
+    let right = if pos * 2 + 1 < tree_width(self.total, height - 1) {
+      self.node_hash(height - 1, pos * 2 + 1, txids)
+    } else {
+      left
+    };
+    parent(&left, &right)
+  }
+
+  fn build_node(&mut self, height: u32, pos: u32, txids: &[[u8; 32]], matched: &[bool]) {
+    let start = (pos << height) as usize;
+    let end = (((pos + 1) << height) as usize).min(txids.len());
+    let parent_of_match = matched[start..end].iter().any(|is_match| *is_match);
+    self.bits.push(parent_of_match);
+    if height == 0 || !parent_of_match {
+      let hash = self.node_hash(height, pos, txids);
+      self.hashes.push(hash);
+    } else {
+      self.build_node(height - 1, pos * 2, txids, matched);
+      if pos * 2 + 1 < tree_width(self.total, height - 1) {
+        self.build_node(height - 1, pos * 2 + 1, txids, matched);
+      }
+    }
+  }
+
+  pub fn read(reader: &mut Reader) -> Result<Self> {
+    let total = reader.u32()?;
+    let hashes = (0..reader.count(32)?).map(|_| reader.hash()).collect::<Result<Vec<_>>>()?;
+    let flags = reader.var_bytes()?;
+    let bits = flags.iter().flat_map(|byte| (0..8).map(move |bit| (byte >> bit) & 1 == 1)).collect();
+    Ok(Self { total, hashes, bits })
+  }
+
+  pub fn write(&self, out: &mut Vec<u8>) {
+    out.extend(self.total.to_le_bytes());
+    write_compact_size(out, self.hashes.len());
+    self.hashes.iter().for_each(|hash| out.extend(hash));
+    let mut flags = vec![0u8; self.bits.len().div_ceil(8)];
+    for (i, bit) in self.bits.iter().enumerate() {
+      flags[i / 8] |= (*bit as u8) << (i % 8);
+    }
+    write_compact_size(out, flags.len());
+    out.extend(flags);
+  }
+
+  /// Merkle root and the `(index, txid)` of every matched leaf, following
+  /// Bitcoin Core's `ExtractMatches`: trees with unused hashes or flag bytes,
+  /// or with identical sibling subtrees (CVE-2012-2459), are errors.
+  pub fn extract_matches(&self) -> Result<([u8; 32], Vec<TreeMatch>)> {
+    if self.total == 0 {
+      return Err(Error::from_reason("Partial merkle tree has no transactions"));
+    }
+    if self.total > MAX_TRANSACTIONS {
+      return Err(Error::from_reason("Partial merkle tree has too many transactions"));
+    }
+    if self.hashes.len() > self.total as usize {
+      return Err(Error::from_reason("Partial merkle tree has more hashes than transactions"));
+    }
+    if self.bits.len() < self.hashes.len() {
+      return Err(Error::from_reason("Partial merkle tree has fewer flag bits than hashes"));
+    }
+    let mut walk = Extraction { bits_used: 0, hashes_used: 0, matches: Vec::new() };
+    let root = self.extract(tree_height(self.total), 0, &mut walk)?;
+    if walk.bits_used.div_ceil(8) != self.bits.len().div_ceil(8) {
+      return Err(Error::from_reason("Partial merkle tree has unused flag bytes"));
+    }
+    if walk.hashes_used != self.hashes.len() {
+      return Err(Error::from_reason("Partial merkle tree has unused hashes"));
+    }
+    Ok((root, walk.matches))
+  }
+
+  fn extract(&self, height: u32, pos: u32, walk: &mut Extraction) -> Result<[u8; 32]> {
+    let truncated = || Error::from_reason("Partial merkle tree is truncated");
+    let parent_of_match = *self.bits.get(walk.bits_used).ok_or_else(truncated)?;
+    walk.bits_used += 1;
+    if height == 0 || !parent_of_match {
+      let hash = *self.hashes.get(walk.hashes_used).ok_or_else(truncated)?;
+      walk.hashes_used += 1;
+      if height == 0 && parent_of_match {
+        walk.matches.push((pos, hash));
+      }
+      return Ok(hash);
+    }
+    let left = self.extract(height - 1, pos * 2, walk)?;
+    let right = if pos * 2 + 1 < tree_width(self.total, height - 1) {
+      let right = self.extract(height - 1, pos * 2 + 1, walk)?;
+      if right == left {
+        return Err(Error::from_reason("Partial merkle tree has identical sibling hashes"));
+      }
+      right
+    } else {
+      left
+    };
+    Ok(parent(&left, &right))
+  }
+}
+
+/// BIP37 `merkleblock`: a block header and a partial merkle tree, the format
+/// of `gettxoutproof`.
+pub struct MerkleBlock {
+  pub header: BlockHeader,
+  pub tree: PartialMerkleTree,
+}
+
+impl MerkleBlock {
+  pub fn parse(bytes: &[u8]) -> Result<Self> {
+    let mut reader = Reader::new(bytes);
+    let header = BlockHeader::read(&mut reader)?;
+    let tree = PartialMerkleTree::read(&mut reader)?;
+    if reader.remaining() > 0 {
+      return Err(Error::from_reason("Trailing bytes after merkle block"));
+    }
+    Ok(Self { header, tree })
+  }
+}
+
+fn raw_bytes(raw: Either<Buffer, String>) -> Result<Vec<u8>> {
+  match raw {
+    Either::A(bytes) => Ok(bytes.to_vec()),
+    Either::B(hex_str) => hex::decode(hex_str).map_err(|_| Error::from_reason("Invalid merkle block hex")),
+  }
+}
+
+/// Decodes a `merkleblock` (bytes or `gettxoutproof` hex): header fields named
+/// as in `getblockheader`, `totalTransactions`, the proven `matches` as
+/// `{ txid, index }` and whether the tree hashes to the header's merkle root.
+/// Throws on malformed input, including trees Bitcoin Core would reject.
+#[napi(js_name = "bitcoinParseMerkleBlock")]
+pub fn bitcoin_parse_merkle_block(raw: Either<Buffer, String>) -> Result<Value> {
+  parse_merkle_block_bytes(&raw_bytes(raw)?)
+}
+
+/// True when `raw` is a valid `merkleblock` whose tree hashes to the header's
+/// merkle root and, with `txid_be`, proves that transaction. Malformed input
+/// returns false.
+#[napi(js_name = "bitcoinVerifyMerkleBlock")]
+pub fn bitcoin_verify_merkle_block(raw: Either<Buffer, String>, txid_be: Option<String>) -> bool {
+  raw_bytes(raw).is_ok_and(|bytes| verify_merkle_block_bytes(&bytes, txid_be.as_deref()))
+}
+
+/// Serializes a `merkleblock` proving `txids_be` from a serialized block, like
+/// `gettxoutproof`. Throws when some txid is not in the block.
+#[napi(js_name = "bitcoinBuildMerkleBlock")]
+pub fn bitcoin_build_merkle_block(block: Buffer, txids_be: Vec<String>) -> Result<Buffer> {
+  build_merkle_block_bytes(&block, &txids_be).map(Buffer::from)
+}
+
+pub fn parse_merkle_block_bytes(bytes: &[u8]) -> Result<Value> {
+  let merkle_block = MerkleBlock::parse(bytes)?;
+  let (root, matches) = merkle_block.tree.extract_matches()?;
+  let mut value = merkle_block.header.to_value();
+  value["totalTransactions"] = json!(merkle_block.tree.total);
+  value["matches"] = matches
+    .into_iter()
+    .map(|(index, hash)| json!({ "txid": txid_to_hex(hash_to_key(hash)), "index": index }))
+    .collect();
+  value["merkleRootValid"] = json!(hash_to_key(root) == merkle_block.header.merkle_root);
+  Ok(value)
+}
+
+pub fn verify_merkle_block_bytes(bytes: &[u8], txid_be: Option<&str>) -> bool {
+  let Ok(merkle_block) = MerkleBlock::parse(bytes) else {
+    return false;
+  };
+  let Ok((root, matches)) = merkle_block.tree.extract_matches() else {
+    return false;
+  };
+  hash_to_key(root) == merkle_block.header.merkle_root
+    && txid_be
+      .is_none_or(|txid| matches.iter().any(|(_, hash)| txid_to_hex(hash_to_key(*hash)).eq_ignore_ascii_case(txid)))
+}
+
+pub fn build_merkle_block_bytes(block_bytes: &[u8], txids_be: &[String]) -> Result<Vec<u8>> {
+  let block = Block::parse(block_bytes)?;
+  let wanted: HashSet<String> = txids_be.iter().map(|txid| txid.to_ascii_lowercase()).collect();
+  let block_txids: Vec<String> = block.transactions.iter().map(|tx| txid_to_hex(tx.txid)).collect();
+  let present: HashSet<&String> = block_txids.iter().collect();
+  if let Some(missing) = wanted.iter().find(|txid| !present.contains(txid)) {
+    return Err(Error::from_reason(format!("Transaction {missing} is not in the block")));
+  }
+  let leaves: Vec<[u8; 32]> = block.transactions.iter().map(|tx| key_to_hash(tx.txid)).collect();
+  let matched: Vec<bool> = block_txids.iter().map(|txid| wanted.contains(txid)).collect();
+
+  let mut out = block_bytes[..HEADER_LEN].to_vec();
+  PartialMerkleTree::build(&leaves, &matched).write(&mut out);
+  Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::merkle::merkle_root;
+  use crate::transaction::Transaction;
+
+  /// Minimal legacy transaction made unique by `seed`.
+  fn tx(seed: u8) -> Vec<u8> {
+    let mut out = 1u32.to_le_bytes().to_vec();
+    out.push(1);
+    out.extend([seed; 32]);
+    out.extend([0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 1]);
+    out.extend([0; 9]);
+    out.extend(0u32.to_le_bytes());
+    out
+  }
+
+  fn block(count: u8) -> (Vec<u8>, Vec<String>) {
+    let txs: Vec<Vec<u8>> = (1..=count).map(tx).collect();
+    let txids: Vec<_> = txs.iter().map(|raw| Transaction::parse(raw).unwrap().txid).collect();
+    let mut out = 1u32.to_le_bytes().to_vec();
+    out.extend([0; 32]);
+    out.extend(merkle_root(txids.iter().map(|txid| key_to_hash(*txid)).collect()));
+    out.extend([0; 12]);
+    out.push(count);
+    txs.iter().for_each(|raw| out.extend(raw));
+    (out, txids.into_iter().map(txid_to_hex).collect())
+  }
+
+  fn tree_bytes(total: u32, hashes: &[[u8; 32]], flags: &[u8]) -> Vec<u8> {
+    let mut out = vec![0; HEADER_LEN];
+    out.extend(total.to_le_bytes());
+    out.push(hashes.len() as u8);
+    hashes.iter().for_each(|hash| out.extend(hash));
+    out.push(flags.len() as u8);
+    out.extend(flags);
+    out
+  }
+
+  #[test]
+  fn builds_single_transaction_proof_like_gettxoutproof() {
+    let (bytes, txids) = block(1);
+    let proof = build_merkle_block_bytes(&bytes, &txids).unwrap();
+    let mut expected = bytes[..HEADER_LEN].to_vec();
+    expected.extend([1, 0, 0, 0, 1]);
+    expected.extend(key_to_hash(Transaction::parse(&tx(1)).unwrap().txid));
+    expected.extend([1, 1]);
+    assert_eq!(proof, expected);
+  }
+
+  #[test]
+  fn round_trips_matches_through_built_merkle_blocks() {
+    let (bytes, txids) = block(7);
+    for wanted in [vec![], vec![0], vec![6], vec![1, 4], vec![0, 1, 2, 3, 4, 5, 6]] {
+      let subset: Vec<String> = wanted.iter().map(|i| txids[*i].clone()).collect();
+      let proof = build_merkle_block_bytes(&bytes, &subset).unwrap();
+      let parsed = parse_merkle_block_bytes(&proof).unwrap();
+      assert_eq!(parsed["totalTransactions"], json!(7));
+      assert_eq!(parsed["merkleRootValid"], json!(true));
+      let expected: Vec<Value> = wanted.iter().map(|i| json!({ "txid": txids[*i], "index": i })).collect();
+      assert_eq!(parsed["matches"], json!(expected));
+
+      assert!(verify_merkle_block_bytes(&proof, None));
+      for (i, txid) in txids.iter().enumerate() {
+        assert_eq!(verify_merkle_block_bytes(&proof, Some(txid)), wanted.contains(&i));
+      }
+    }
+    assert!(build_merkle_block_bytes(&bytes, &["00".repeat(32)]).is_err());
+  }
+
+  #[test]
+  fn rejects_tampered_and_malformed_trees() {
+    let (bytes, txids) = block(5);
+    let proof = build_merkle_block_bytes(&bytes, &txids[2..3]).unwrap();
+
+    let mut wrong_hash = proof.clone();
+    wrong_hash[HEADER_LEN + 5] ^= 1;
+    assert_eq!(parse_merkle_block_bytes(&wrong_hash).unwrap()["merkleRootValid"], json!(false));
+    assert!(!verify_merkle_block_bytes(&wrong_hash, None));
+
+    assert!(parse_merkle_block_bytes(&proof[..proof.len() - 1]).is_err());
+    assert!(parse_merkle_block_bytes(&[proof.as_slice(), &[0]].concat()).is_err());
+
+    // Identical siblings, as in a tree padded with a duplicated transaction.
+    let leaf = [0x11; 32];
+    assert!(parse_merkle_block_bytes(&tree_bytes(2, &[leaf, leaf], &[0b111])).is_err());
+    assert!(parse_merkle_block_bytes(&tree_bytes(2, &[leaf, [0x22; 32]], &[0b111])).is_ok());
+    // Unused hash, unused flag byte, no transactions.
+    assert!(parse_merkle_block_bytes(&tree_bytes(2, &[leaf, [0x22; 32], leaf], &[0b111])).is_err());
+    assert!(parse_merkle_block_bytes(&tree_bytes(2, &[leaf, [0x22; 32]], &[0b111, 0])).is_err());
+    assert!(parse_merkle_block_bytes(&tree_bytes(0, &[], &[0])).is_err());
+    assert!(!verify_merkle_block_bytes(&tree_bytes(2, &[leaf, leaf], &[0b111]), None));
+  }
+}
@@ -0,0 +1,122 @@
+use napi::{Error, Result};
+use napi_derive::napi;
+use serde_json::{json, Value};
+
+use super::{be_hex_to_le_bytes, dsha256, le_bytes_to_be_hex};
+
+pub(crate) fn parent(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+  let mut buf = [0u8; 64];
+  buf[..32].copy_from_slice(left);
+  buf[32..].copy_from_slice(right);
+  dsha256(&buf)
+}
+
+/// Siblings of the leaf at `index` from the bottom level up, wire order.
+pub(crate) fn merkle_branch(mut level: Vec<[u8; 32]>, mut index: usize) -> Vec<[u8; 32]> {
+  let mut branch = Vec::new();
+  while level.len() > 1 {
+    if level.len() % 2 == 1 {
+      level.push(*level.last().unwrap());
+    }
+    branch.push(level[index ^ 1]);
+    level = level.chunks(2).map(|p| parent(&p[0], &p[1])).collect();
+    index /= 2;
+  }
+  branch
+}
+
+/// Root implied by `leaf` at `index` and its `branch`; `None` when `index`
+/// has bits above the branch depth.
+pub(crate) fn root_from_branch(leaf: [u8; 32], branch: &[[u8; 32]], index: u32) -> Option<[u8; 32]> {
+  if index.checked_shr(branch.len() as u32).unwrap_or(0) != 0 {
+    return None;
+  }
+  let root = branch.iter().enumerate().fold(leaf, |node, (depth, sibling)| {
+    if (index >> depth) & 1 == 1 {
+      parent(sibling, &node)
+    } else {
+      parent(&node, sibling)
+    }
+  });
+  Some(root)
+}
+
+/// Merkle inclusion proof of `txids_be[index]`: `{ txid, index, branch, root }`
+/// with `branch` ordered from the leaf level up. All hashes are big-endian hex.
+#[napi(js_name = "bitcoinGetMerkleProof")]
+pub fn bitcoin_get_merkle_proof(txids_be: Vec<String>, index: u32) -> Result<Value> {
+  let leaves = txids_be
+    .iter()
+    .enumerate()
+    .map(|(i, id)| be_hex_to_le_bytes(id).ok_or_else(|| Error::from_reason(format!("Invalid txid hex at index {i}"))))
+    .collect::<Result<Vec<_>>>()?;
+  let Some(leaf) = leaves.get(index as usize).copied() else {
+    return Err(Error::from_reason("Merkle proof index is out of range"));
+  };
+  let branch = merkle_branch(leaves, index as usize);
+  let root = root_from_branch(leaf, &branch, index).unwrap();
+  Ok(json!({
+    "txid": le_bytes_to_be_hex(leaf),
+    "index": index,
+    "branch": branch.into_iter().map(le_bytes_to_be_hex).collect::<Vec<_>>(),
+    "root": le_bytes_to_be_hex(root),
+  }))
+}
+
+/// True when `branch` (big-endian hex, leaf level first) links `txid_be` at
+/// `index` to `root_be`. Malformed hashes return false.
+#[napi(js_name = "bitcoinVerifyMerkleProof")]
+pub fn bitcoin_verify_merkle_proof(txid_be: String, branch_be: Vec<String>, index: u32, root_be: String) -> bool {
+  let (Some(leaf), Some(root)) = (be_hex_to_le_bytes(&txid_be), be_hex_to_le_bytes(&root_be)) else {
+    return false;
+  };
+  let Some(branch) = branch_be.iter().map(|hash| be_hex_to_le_bytes(hash)).collect::<Option<Vec<_>>>() else {
+    return false;
+  };
+  root_from_branch(leaf, &branch, index) == Some(root)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::merkle::bitcoin_compute_merkle_root;
+
+  fn txids(count: u8) -> Vec<String> {
+    (1..=count).map(|i| format!("{i:02x}").repeat(32)).collect()
+  }
+
+  fn strings(value: &Value) -> Vec<String> {
+    value.as_array().unwrap().iter().map(|hash| hash.as_str().unwrap().to_string()).collect()
+  }
+
+  #[test]
+  fn proofs_link_every_leaf_to_the_computed_root() {
+    for count in [1, 2, 5, 8] {
+      let ids = txids(count);
+      let root = bitcoin_compute_merkle_root(ids.clone());
+      for index in 0..count as u32 {
+        let proof = bitcoin_get_merkle_proof(ids.clone(), index).unwrap();
+        assert_eq!(proof["root"], json!(root));
+        let branch = strings(&proof["branch"]);
+        assert_eq!(branch.len(), (count as f64).log2().ceil() as usize);
+        assert!(bitcoin_verify_merkle_proof(ids[index as usize].clone(), branch, index, root.clone()));
+      }
+    }
+  }
+
+  #[test]
+  fn rejects_wrong_positions_and_hashes() {
+    let ids = txids(5);
+    let root = bitcoin_compute_merkle_root(ids.clone());
+    let branch = strings(&bitcoin_get_merkle_proof(ids.clone(), 2).unwrap()["branch"]);
+
+    assert!(!bitcoin_verify_merkle_proof(ids[2].clone(), branch.clone(), 3, root.clone()));
+    assert!(!bitcoin_verify_merkle_proof(ids[2].clone(), branch.clone(), 2 + 8, root.clone()));
+    assert!(!bitcoin_verify_merkle_proof(ids[3].clone(), branch.clone(), 2, root.clone()));
+    assert!(!bitcoin_verify_merkle_proof(ids[2].clone(), branch[1..].to_vec(), 2, root.clone()));
+    assert!(!bitcoin_verify_merkle_proof(ids[2].clone(), branch, 2, "zz".repeat(32)));
+
+    assert!(bitcoin_get_merkle_proof(ids.clone(), 5).is_err());
+    assert!(bitcoin_get_merkle_proof(vec!["00".repeat(31)], 0).is_err());
+  }
+}
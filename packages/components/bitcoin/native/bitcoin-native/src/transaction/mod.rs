@@ -0,0 +1,395 @@
+use napi::bindgen_prelude::{Buffer, Either};
+use napi::{Error, Result};
+use napi_derive::napi;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::mempool::types::LightTransaction;
+use crate::utils::{txid_to_hex, TxKey};
+use script::Network;
+
+pub mod script;
+
+/// Smallest serialized input (prevout, empty script, sequence) and output
+/// (value, empty script); bounds declared counts before allocating.
+const MIN_INPUT_LEN: usize = 41;
+const MIN_OUTPUT_LEN: usize = 9;
+
+/// Cursor over bitcoin wire-format bytes.
+pub struct Reader<'a> {
+  bytes: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Reader<'a> {
+  pub fn new(bytes: &'a [u8]) -> Self {
+    Self { bytes, pos: 0 }
+  }
+
+  pub fn position(&self) -> usize {
+    self.pos
+  }
+
+  pub fn remaining(&self) -> usize {
+    self.bytes.len() - self.pos
+  }
+
+  pub fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+    if len > self.remaining() {
+      return Err(Error::from_reason("Unexpected end of data"));
+    }
+    let out = &self.bytes[self.pos..self.pos + len];
+    self.pos += len;
+    Ok(out)
+  }
+
+  pub fn peek(&self, offset: usize) -> Option<u8> {
+    self.bytes.get(self.pos + offset).copied()
+  }
+
+  pub fn u8(&mut self) -> Result<u8> {
+    Ok(self.take(1)?[0])
+  }
+
+  pub fn u32(&mut self) -> Result<u32> {
+    Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+  }
+
+  pub fn u64(&mut self) -> Result<u64> {
+    Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+  }
+
+  /// 32 bytes in wire (internal) order.
+  pub fn hash(&mut self) -> Result<[u8; 32]> {
+    Ok(self.take(32)?.try_into().unwrap())
+  }
+
+  /// Bitcoin CompactSize; non-canonical encodings are rejected.
+  pub fn compact_size(&mut self) -> Result<u64> {
+    let (value, min) = match self.u8()? {
+      0xfd => (u16::from_le_bytes(self.take(2)?.try_into().unwrap()) as u64, 0xfd),
+      0xfe => (self.u32()? as u64, 0x1_0000),
+      0xff => (self.u64()?, 0x1_0000_0000),
+      byte => return Ok(byte as u64),
+    };
+    if value < min {
+      return Err(Error::from_reason("Non-canonical CompactSize"));
+    }
+    Ok(value)
+  }
+
+  /// CompactSize count of items at least `min_item_len` bytes long each.
+  pub fn count(&mut self, min_item_len: usize) -> Result<usize> {
+    let count = self.compact_size()?;
+    if count > (self.remaining() / min_item_len.max(1)) as u64 {
+      return Err(Error::from_reason("Item count exceeds the remaining data"));
+    }
+    Ok(count as usize)
+  }
+
+  pub fn var_bytes(&mut self) -> Result<&'a [u8]> {
+    let len = self.count(1)?;
+    self.take(len)
+  }
+}
+
+pub fn dsha256(data: &[u8]) -> [u8; 32] {
+  Sha256::digest(Sha256::digest(data)).into()
+}
+
+/// Display-order key of a wire-order hash.
+pub fn hash_to_key(mut hash: [u8; 32]) -> TxKey {
+  hash.reverse();
+  TxKey(hash)
+}
+
+/// Wire-order hash of a display-order key.
+pub fn key_to_hash(key: TxKey) -> [u8; 32] {
+  let mut hash = key.0;
+  hash.reverse();
+  hash
+}
+
+pub struct TxInput {
+  /// Display order; all zeros for a coinbase input.
+  pub prev_txid: TxKey,
+  pub vout: u32,
+  pub sequence: u32,
+  pub witness: Box<[Box<[u8]>]>,
+}
+
+pub struct TxOutput {
+  pub value_sat: u64,
+  pub script_pubkey: Box<[u8]>,
+}
+
+/// Transaction decoded from its BIP144 (segwit) or legacy serialization.
+pub struct Transaction {
+  pub version: i32,
+  pub inputs: Vec<TxInput>,
+  pub outputs: Vec<TxOutput>,
+  pub locktime: u32,
+  pub txid: TxKey,
+  /// Equals `txid` for transactions without witness data.
+  pub wtxid: TxKey,
+  /// Serialized size including witness data.
+  pub size: u32,
+  /// Serialized size without witness data.
+  pub stripped_size: u32,
+}
+
+impl Transaction {
+  /// Decodes exactly one transaction; trailing bytes are an error.
+  pub fn parse(bytes: &[u8]) -> Result<Self> {
+    let mut reader = Reader::new(bytes);
+    let tx = Self::read(&mut reader)?;
+    if reader.remaining() > 0 {
+      return Err(Error::from_reason("Trailing bytes after transaction"));
+    }
+    Ok(tx)
+  }
+
+  /// Decodes the transaction at the reader's position, e.g. inside a block.
+  pub fn read(reader: &mut Reader) -> Result<Self> {
+    let start = reader.position();
+    let version_bytes = reader.take(4)?;
+    let version = i32::from_le_bytes(version_bytes.try_into().unwrap());
+
+    let segwit = reader.peek(0) == Some(0);
+    if segwit {
+      if reader.peek(1) != Some(1) {
+        return Err(Error::from_reason("Unsupported transaction serialization flag"));
+      }
+      reader.take(2)?;
+    }
+
+    let body_start = reader.position();
+    let mut inputs = Vec::with_capacity(reader.count(MIN_INPUT_LEN)?);
+    for _ in 0..inputs.capacity() {
+      inputs.push(TxInput {
+        prev_txid: hash_to_key(reader.hash()?),
+        vout: reader.u32()?,
+        sequence: {
+          reader.var_bytes()?;
+          reader.u32()?
+        },
+        witness: Box::default(),
+      });
+    }
+    let mut outputs = Vec::with_capacity(reader.count(MIN_OUTPUT_LEN)?);
+    for _ in 0..outputs.capacity() {
+      outputs.push(TxOutput { value_sat: reader.u64()?, script_pubkey: reader.var_bytes()?.into() });
+    }
+    let body_end = reader.position();
+
+    if segwit {
+      for input in &mut inputs {
+        let items = reader.count(1)?;
+        input.witness = (0..items).map(|_| reader.var_bytes().map(Into::into)).collect::<Result<_>>()?;
+      }
+      if inputs.iter().all(|input| input.witness.is_empty()) {
+        return Err(Error::from_reason("Superfluous witness record"));
+      }
+    }
+
+    let locktime_bytes = reader.take(4)?;
+    let locktime = u32::from_le_bytes(locktime_bytes.try_into().unwrap());
+    let end = reader.position();
+    let all = &reader.bytes[start..end];
+    let body = &reader.bytes[body_start..body_end];
+
+    let mut hasher = Sha256::new();
+    hasher.update(version_bytes);
+    hasher.update(body);
+    hasher.update(locktime_bytes);
+    let txid = hash_to_key(Sha256::digest(hasher.finalize()).into());
+    let wtxid = if segwit { hash_to_key(dsha256(all)) } else { txid };
+
+    Ok(Self {
+      version,
+      inputs,
+      outputs,
+      locktime,
+      txid,
+      wtxid,
+      size: all.len() as u32,
+      stripped_size: (version_bytes.len() + body.len() + locktime_bytes.len()) as u32,
+    })
+  }
+
+  pub fn is_coinbase(&self) -> bool {
+    matches!(self.inputs.as_slice(), [input] if input.prev_txid.0 == [0; 32] && input.vout == u32::MAX)
+  }
+
+  /// BIP141 weight: stripped size x 3 + total size.
+  pub fn weight(&self) -> u32 {
+    self.stripped_size * 3 + self.size
+  }
+
+  pub fn vsize(&self) -> u32 {
+    self.weight().div_ceil(4)
+  }
+
+  /// BIP125 opt-in signal: some input has `nSequence < 0xfffffffe`.
+  pub fn signals_rbf(&self) -> bool {
+    self.inputs.iter().any(|input| input.sequence < 0xffff_fffe)
+  }
+}
+
+/// Decodes a serialized transaction (bytes or hex) in segwit or legacy
+/// form into the `LightTransaction` shape: txid, `hash`/`wtxid`, sizes,
+/// vsize and weight, inputs with sequences and outputs with their script
+/// `type`. With `network` (`mainnet`, `testnet`, `regtest` or `signet`)
+/// outputs also get their `address`. Inputs carry no `prevout` and there is
+/// no `feeRate`, since neither is part of the serialization.
+#[napi(js_name = "bitcoinParseTransaction")]
+pub fn bitcoin_parse_transaction(raw: Either<Buffer, String>, network: Option<String>) -> Result<Value> {
+  match raw {
+    Either::A(bytes) => parse_transaction_bytes(&bytes, network.as_deref()),
+    Either::B(hex_str) => {
+      let bytes = hex::decode(hex_str).map_err(|_| Error::from_reason("Invalid transaction hex"))?;
+      parse_transaction_bytes(&bytes, network.as_deref())
+    }
+  }
+}
+
+pub fn parse_transaction_bytes(bytes: &[u8], network: Option<&str>) -> Result<Value> {
+  let network = match network {
+    None => None,
+    Some(name) => Some(Network::parse(name).ok_or_else(|| {
+      Error::from_reason("Unsupported network. Expected 'mainnet', 'testnet', 'regtest' or 'signet'.")
+    })?),
+  };
+  let tx = Transaction::parse(bytes)?;
+  let mut value = LightTransaction::from_raw(&tx, network).to_value(tx.txid);
+  value["wtxid"] = json!(txid_to_hex(tx.wtxid));
+  Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const GENESIS_COINBASE: &str = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+  /// One-input, one-output P2WPKH spend; with `witness`, in BIP144 form.
+  fn spend(witness: bool) -> Vec<u8> {
+    let mut out = hex::decode("02000000").unwrap();
+    if witness {
+      out.extend([0x00, 0x01]);
+    }
+    out.push(1);
+    out.extend([0x11; 32]);
+    out.extend(1u32.to_le_bytes());
+    out.push(0);
+    out.extend(0xffff_fffdu32.to_le_bytes());
+    out.push(1);
+    out.extend(50_000u64.to_le_bytes());
+    out.extend(hex::decode(format!("160014{}", "22".repeat(20))).unwrap());
+    if witness {
+      out.push(2);
+      out.push(71);
+      out.extend([0x30; 71]);
+      out.push(33);
+      out.extend([0x02; 33]);
+    }
+    out.extend(0u32.to_le_bytes());
+    out
+  }
+
+  #[test]
+  fn parses_legacy_genesis_coinbase() {
+    let tx = Transaction::parse(&hex::decode(GENESIS_COINBASE).unwrap()).unwrap();
+    assert_eq!(txid_to_hex(tx.txid), "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b");
+    assert_eq!(tx.wtxid, tx.txid);
+    assert!(tx.is_coinbase());
+    assert_eq!((tx.size, tx.stripped_size, tx.weight(), tx.vsize()), (204, 204, 816, 204));
+    assert_eq!(tx.outputs[0].value_sat, 5_000_000_000);
+  }
+
+  #[test]
+  fn parses_segwit_and_hashes_txid_without_witness() {
+    let legacy = Transaction::parse(&spend(false)).unwrap();
+    let segwit = Transaction::parse(&spend(true)).unwrap();
+    assert_eq!(segwit.txid, legacy.txid);
+    assert_ne!(segwit.wtxid, segwit.txid);
+    assert_eq!(segwit.stripped_size, legacy.size);
+    assert_eq!(segwit.size, legacy.size + 2 + 1 + 72 + 34);
+    assert_eq!(segwit.weight(), legacy.size * 3 + segwit.size);
+    assert_eq!(segwit.inputs[0].prev_txid.0, [0x11; 32]);
+    assert_eq!(segwit.inputs[0].witness.len(), 2);
+    assert!(segwit.signals_rbf() && !segwit.is_coinbase());
+  }
+
+  #[test]
+  fn rejects_malformed_serializations() {
+    let bytes = spend(true);
+    assert!(Transaction::parse(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Transaction::parse(&[bytes.as_slice(), &[0]].concat()).is_err());
+
+    // Marker and flag without any witness item.
+    let mut empty_witness = spend(false);
+    empty_witness.splice(4..4, [0x00, 0x01]);
+    let locktime = empty_witness.len() - 4;
+    empty_witness.insert(locktime, 0);
+    assert!(Transaction::parse(&empty_witness).is_err());
+
+    // Input count encoded as 0xfd 0x0100 instead of a single byte.
+    let mut non_canonical = spend(false);
+    non_canonical.splice(4..5, [0xfd, 0x01, 0x00]);
+    assert!(Transaction::parse(&non_canonical).is_err());
+  }
+
+  #[test]
+  fn classifies_scripts_and_derives_addresses() {
+    let cases = [
+      ("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac", "pubkeyhash", Some("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa")),
+      (
+        "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+        "witness_v0_keyhash",
+        Some("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+      ),
+      ("6002751e", "witness_unknown", Some("bc1sw50qgdz25j")),
+      ("51024e73", "anchor", Some("bc1pfeessrawgf")),
+      ("a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87", "scripthash", None),
+      (&format!("5120{}", "11".repeat(32)), "witness_v1_taproot", None),
+      (&format!("0015{}", "11".repeat(21)), "nonstandard", None),
+      ("6a0401020304", "nulldata", None),
+      ("6aac", "nonstandard", None),
+      (&format!("21{}ac", "02".repeat(33)), "pubkey", None),
+      (&format!("5121{}21{}52ae", "02".repeat(33), "03".repeat(33)), "multisig", None),
+    ];
+    for (script, expected_type, expected_address) in cases {
+      let script = hex::decode(script).unwrap();
+      assert_eq!(script::script_type(&script), expected_type, "{}", hex::encode(&script));
+      if let Some(expected) = expected_address {
+        assert_eq!(script::address(&script, Network::Mainnet).as_deref(), Some(expected));
+      }
+    }
+    let p2wpkh = hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+    assert_eq!(
+      script::address(&p2wpkh, Network::Testnet).as_deref(),
+      Some("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx")
+    );
+  }
+
+  #[test]
+  fn parse_transaction_returns_light_transaction_shape() {
+    let tx = parse_transaction_bytes(&spend(true), Some("mainnet")).unwrap();
+    let parsed = Transaction::parse(&spend(true)).unwrap();
+    assert_eq!(tx["txid"], json!(txid_to_hex(parsed.txid)));
+    assert_eq!(tx["wtxid"], json!(txid_to_hex(parsed.wtxid)));
+    assert_eq!(tx["hash"], tx["wtxid"]);
+    assert_eq!((tx["size"].clone(), tx["vsize"].clone()), (json!(parsed.size), json!(parsed.vsize())));
+    assert_eq!(tx["vin"][0]["sequence"], json!(0xffff_fffdu32));
+    assert_eq!(tx["vout"][0]["scriptPubKey"]["type"], json!("witness_v0_keyhash"));
+    assert!(tx["vout"][0]["scriptPubKey"]["address"].as_str().unwrap().starts_with("bc1q"));
+
+    let genesis_bytes = hex::decode(GENESIS_COINBASE).unwrap();
+    let genesis = parse_transaction_bytes(&genesis_bytes, None).unwrap();
+    assert_eq!(genesis["vin"][0], json!({ "sequence": u32::MAX }));
+    assert_eq!(genesis["vout"][0]["scriptPubKey"]["type"], json!("pubkey"));
+    assert!(parse_transaction_bytes(&genesis_bytes[1..], None).is_err());
+    assert!(parse_transaction_bytes(&genesis_bytes, Some("litecoin")).is_err());
+  }
+}
@@ -0,0 +1,242 @@
+use super::dsha256;
+
+const OP_0: u8 = 0x00;
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+const OP_PUSHDATA4: u8 = 0x4e;
+const OP_1: u8 = 0x51;
+const OP_16: u8 = 0x60;
+const OP_RETURN: u8 = 0x6a;
+const OP_DUP: u8 = 0x76;
+const OP_EQUAL: u8 = 0x87;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_HASH160: u8 = 0xa9;
+const OP_CHECKSIG: u8 = 0xac;
+const OP_CHECKMULTISIG: u8 = 0xae;
+
+/// Pay-to-anchor witness program (`OP_1 <0x4e73>`).
+const ANCHOR_PROGRAM: [u8; 2] = [0x4e, 0x73];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Network {
+  Mainnet,
+  Testnet,
+  Regtest,
+  Signet,
+}
+
+impl Network {
+  pub fn parse(name: &str) -> Option<Self> {
+    match name {
+      "mainnet" => Some(Self::Mainnet),
+      "testnet" => Some(Self::Testnet),
+      "regtest" => Some(Self::Regtest),
+      "signet" => Some(Self::Signet),
+      _ => None,
+    }
+  }
+
+  fn p2pkh_version(self) -> u8 {
+    if self == Self::Mainnet {
+      0x00
+    } else {
+      0x6f
+    }
+  }
+
+  fn p2sh_version(self) -> u8 {
+    if self == Self::Mainnet {
+      0x05
+    } else {
+      0xc4
+    }
+  }
+
+  fn hrp(self) -> &'static str {
+    match self {
+      Self::Mainnet => "bc",
+      Self::Testnet | Self::Signet => "tb",
+      Self::Regtest => "bcrt",
+    }
+  }
+}
+
+/// Bitcoin Core's `scriptPubKey.type` for `script`, following the templates of
+/// its `Solver`.
+pub fn script_type(script: &[u8]) -> &'static str {
+  if let [OP_HASH160, 20, .., OP_EQUAL] = script {
+    if script.len() == 23 {
+      return "scripthash";
+    }
+  }
+  if let Some((version, program)) = witness_program(script) {
+    return match (version, program.len()) {
+      (0, 20) => "witness_v0_keyhash",
+      (0, 32) => "witness_v0_scripthash",
+      (0, _) => "nonstandard",
+      (1, 32) => "witness_v1_taproot",
+      (1, _) if program == ANCHOR_PROGRAM => "anchor",
+      _ => "witness_unknown",
+    };
+  }
+  if script.first() == Some(&OP_RETURN) && is_push_only(&script[1..]) {
+    return "nulldata";
+  }
+  if let [push, key @ .., OP_CHECKSIG] = script {
+    if *push as usize == key.len() && is_pubkey(key) {
+      return "pubkey";
+    }
+  }
+  if let [OP_DUP, OP_HASH160, 20, .., OP_EQUALVERIFY, OP_CHECKSIG] = script {
+    if script.len() == 25 {
+      return "pubkeyhash";
+    }
+  }
+  if is_multisig(script) {
+    return "multisig";
+  }
+  "nonstandard"
+}
+
+/// Address of a P2PKH, P2SH or witness-program script, like the `address`
+/// field of `decoderawtransaction`; `None` for other scripts.
+pub fn address(script: &[u8], network: Network) -> Option<String> {
+  match script_type(script) {
+    "pubkeyhash" => Some(base58check(network.p2pkh_version(), &script[3..23])),
+    "scripthash" => Some(base58check(network.p2sh_version(), &script[2..22])),
+    "witness_v0_keyhash" | "witness_v0_scripthash" | "witness_v1_taproot" | "anchor" | "witness_unknown" => {
+      let (version, program) = witness_program(script)?;
+      Some(segwit_address(network.hrp(), version, program))
+    }
+    _ => None,
+  }
+}
+
+/// `(version, program)` of a BIP141 witness program: a version opcode and one
+/// direct push of 2 to 40 bytes.
+fn witness_program(script: &[u8]) -> Option<(u8, &[u8])> {
+  let [opcode, len, program @ ..] = script else {
+    return None;
+  };
+  let version = match *opcode {
+    OP_0 => 0,
+    OP_1..=OP_16 => opcode - OP_1 + 1,
+    _ => return None,
+  };
+  ((2..=40).contains(len) && *len as usize == program.len()).then_some((version, program))
+}
+
+/// Splits `script` into pushed data, or `None` at a non-push opcode or a
+/// truncated push.
+fn pushes(mut script: &[u8]) -> Option<Vec<&[u8]>> {
+  let mut out = Vec::new();
+  while let Some((&opcode, rest)) = script.split_first() {
+    let (len, rest) = match opcode {
+      0x01..=0x4b => (opcode as usize, rest),
+      OP_PUSHDATA1 => (*rest.first()? as usize, &rest[1..]),
+      OP_PUSHDATA2 => (u16::from_le_bytes(rest.get(..2)?.try_into().ok()?) as usize, &rest[2..]),
+      OP_PUSHDATA4 => (u32::from_le_bytes(rest.get(..4)?.try_into().ok()?) as usize, &rest[4..]),
+      OP_0 | OP_1..=OP_16 | 0x4f => (0, rest),
+      _ => return None,
+    };
+    out.push(rest.get(..len)?);
+    script = &rest[len..];
+  }
+  Some(out)
+}
+
+fn is_push_only(script: &[u8]) -> bool {
+  pushes(script).is_some()
+}
+
+fn is_pubkey(key: &[u8]) -> bool {
+  match key.first() {
+    Some(0x02 | 0x03) => key.len() == 33,
+    Some(0x04 | 0x06 | 0x07) => key.len() == 65,
+    _ => false,
+  }
+}
+
+/// `OP_m <pubkey>... OP_n OP_CHECKMULTISIG` with `1 <= m <= n <= 16`.
+fn is_multisig(script: &[u8]) -> bool {
+  let [m @ OP_1..=OP_16, keys @ .., n @ OP_1..=OP_16, OP_CHECKMULTISIG] = script else {
+    return false;
+  };
+  let Some(keys) = pushes(keys) else {
+    return false;
+  };
+  m <= n && keys.len() == (n - OP_1 + 1) as usize && keys.iter().all(|key| is_pubkey(key))
+}
+
+fn base58check(version: u8, payload: &[u8]) -> String {
+  const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+  let mut data = Vec::with_capacity(payload.len() + 5);
+  data.push(version);
+  data.extend_from_slice(payload);
+  let checksum = dsha256(&data);
+  data.extend_from_slice(&checksum[..4]);
+
+  // Little-endian base-58 digits of the big-endian number `data`.
+  let mut digits: Vec<u8> = Vec::new();
+  for byte in &data {
+    let mut carry = *byte as u32;
+    for digit in &mut digits {
+      carry += (*digit as u32) << 8;
+      *digit = (carry % 58) as u8;
+      carry /= 58;
+    }
+    while carry > 0 {
+      digits.push((carry % 58) as u8);
+      carry /= 58;
+    }
+  }
+  let zeros = data.iter().take_while(|byte| **byte == 0).count();
+  std::iter::repeat_n('1', zeros).chain(digits.iter().rev().map(|digit| ALPHABET[*digit as usize] as char)).collect()
+}
+
+/// BIP173 (version 0, bech32) or BIP350 (version 1+, bech32m) address.
+fn segwit_address(hrp: &str, version: u8, program: &[u8]) -> String {
+  const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+  let mut data = vec![version];
+  let mut acc = 0u32;
+  let mut bits = 0;
+  for byte in program {
+    acc = (acc << 8) | *byte as u32;
+    bits += 8;
+    while bits >= 5 {
+      bits -= 5;
+      data.push(((acc >> bits) & 31) as u8);
+    }
+  }
+  if bits > 0 {
+    data.push(((acc << (5 - bits)) & 31) as u8);
+  }
+
+  let constant = if version == 0 { 1 } else { 0x2bc8_30a3 };
+  let mut values: Vec<u8> = hrp.bytes().map(|c| c >> 5).chain([0]).chain(hrp.bytes().map(|c| c & 31)).collect();
+  values.extend_from_slice(&data);
+  values.extend_from_slice(&[0; 6]);
+  let checksum = polymod(&values) ^ constant;
+  data.extend((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 31) as u8));
+
+  let mut out = String::with_capacity(hrp.len() + 1 + data.len());
+  out.push_str(hrp);
+  out.push('1');
+  out.extend(data.iter().map(|value| CHARSET[*value as usize] as char));
+  out
+}
+
+fn polymod(values: &[u8]) -> u32 {
+  const GENERATOR: [u32; 5] = [0x3b6a_57b2, 0x2650_8e6d, 0x1ea1_19fa, 0x3d42_33dd, 0x2a14_62b3];
+  let mut chk = 1u32;
+  for value in values {
+    let top = chk >> 25;
+    chk = ((chk & 0x1ff_ffff) << 5) ^ *value as u32;
+    for (i, generator) in GENERATOR.iter().enumerate() {
+      if (top >> i) & 1 == 1 {
+        chk ^= generator;
+      }
+    }
+  }
+  chk
+}
@@ -7,3 +7,21 @@ pub fn number_field(value: &Value, key: &str) -> Option<f64> {
 pub fn string_field<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
   value.get(key).and_then(Value::as_str)
 }
+
+pub fn bool_field(value: &Value, key: &str) -> Option<bool> {
+  value.get(key).and_then(Value::as_bool)
+}
+
+/// Reads a non-negative integer field. JS numbers arrive as `f64`, so values
+/// are rounded and clamped instead of rejected.
+pub fn u64_field(value: &Value, key: &str) -> u64 {
+  number_field(value, key).map(|n| n.max(0.0).round() as u64).unwrap_or(0)
+}
+
+pub fn u32_field(value: &Value, key: &str) -> u32 {
+  u64_field(value, key).min(u32::MAX as u64) as u32
+}
+
+pub fn i64_field(value: &Value, key: &str) -> i64 {
+  number_field(value, key).map(|n| n.round() as i64).unwrap_or(0)
+}
@@ -2,6 +2,7 @@ pub mod hex;
 pub mod json;
 pub mod time;
 
+pub use easylayer_native_common::memory;
 pub use hex::{parse_txid, txid_to_hex, TxKey};
-pub use json::{number_field, string_field};
+pub use json::{bool_field, i64_field, number_field, string_field, u32_field, u64_field};
 pub use time::now_ms;
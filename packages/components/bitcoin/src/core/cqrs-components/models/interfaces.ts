@@ -28,6 +28,8 @@ export interface LightVin {
   vout?: number;
   /** required for BIP-125 signaling check */
   sequence?: number;
+  /** spent output (getrawtransaction verbosity 2); enables script spend tracking */
+  prevout?: { value: number; scriptPubKey?: LightScriptPubKey };
 }
 
 /** Lightweight vout: numeric value + minimal script */
@@ -3,8 +3,11 @@ import {
   isBitcoinNativeAvailable,
   isBitcoinNativeMempoolStateAvailable,
   isBitcoinNativeMerkleVerifierAvailable,
+  requireBitcoinNativeBlockParser,
   requireBitcoinNativeMempoolState,
+  requireBitcoinNativeMerkleProver,
   requireBitcoinNativeMerkleVerifier,
+  requireBitcoinNativeTransactionParser,
   setBitcoinNativeBindings,
   setBitcoinNativeLoadError,
 } from '../bitcoin-native.registry';
@@ -50,5 +53,10 @@ describe('bitcoin native registry capability checks', () => {
 
     expect(requireBitcoinNativeMempoolState()).toBeDefined();
     expect(() => requireBitcoinNativeMerkleVerifier()).toThrow(/NativeMerkleVerifier requires the Bitcoin Rust native addon/);
+    expect(() => requireBitcoinNativeTransactionParser()).toThrow(
+      /NativeTransactionParser requires the Bitcoin Rust native addon/
+    );
+    expect(() => requireBitcoinNativeBlockParser()).toThrow(/NativeBlockParser requires the Bitcoin Rust native addon/);
+    expect(() => requireBitcoinNativeMerkleProver()).toThrow(/NativeMerkleProver requires the Bitcoin Rust native addon/);
   });
 });
@@ -1,4 +1,11 @@
-import type { NativeBitcoinBindings, NativeMempoolStateConstructor, NativeMerkleVerifier } from './interfaces';
+import type {
+  NativeBitcoinBindings,
+  NativeBlockParser,
+  NativeMempoolStateConstructor,
+  NativeMerkleProver,
+  NativeMerkleVerifier,
+  NativeTransactionParser,
+} from './interfaces';
 
 export class BitcoinNativeRuntimeError extends Error {
   constructor(message: string) {
@@ -29,7 +36,12 @@ export function getBitcoinNativeLoadError(): Error | undefined {
 }
 
 function nativeRequiredMessage(component: string): string {
-  const reason = loadError ? ` Native load error: ${loadError.message}` : ' Native bindings are not registered.';
+  let reason = ' Native bindings are not registered.';
+  if (loadError) {
+    reason = ` Native load error: ${loadError.message}`;
+  } else if (bindings) {
+    reason = ' The loaded native addon does not export it; rebuild the addon.';
+  }
   return `${component} requires the Bitcoin Rust native addon in Node.js runtime; JavaScript fallback is disabled.${reason}`;
 }
 
@@ -56,6 +68,30 @@ export function requireBitcoinNativeMerkleVerifier(): NativeMerkleVerifier {
   return NativeMerkleVerifier;
 }
 
+export function requireBitcoinNativeTransactionParser(): NativeTransactionParser {
+  const NativeTransactionParser = requireBitcoinNativeBindings('NativeTransactionParser').NativeTransactionParser;
+  if (!NativeTransactionParser) {
+    throw new BitcoinNativeRuntimeError(nativeRequiredMessage('NativeTransactionParser'));
+  }
+  return NativeTransactionParser;
+}
+
+export function requireBitcoinNativeBlockParser(): NativeBlockParser {
+  const NativeBlockParser = requireBitcoinNativeBindings('NativeBlockParser').NativeBlockParser;
+  if (!NativeBlockParser) {
+    throw new BitcoinNativeRuntimeError(nativeRequiredMessage('NativeBlockParser'));
+  }
+  return NativeBlockParser;
+}
+
+export function requireBitcoinNativeMerkleProver(): NativeMerkleProver {
+  const NativeMerkleProver = requireBitcoinNativeBindings('NativeMerkleProver').NativeMerkleProver;
+  if (!NativeMerkleProver) {
+    throw new BitcoinNativeRuntimeError(nativeRequiredMessage('NativeMerkleProver'));
+  }
+  return NativeMerkleProver;
+}
+
 /**
  * Returns true when at least one Bitcoin native component is registered.
  *
@@ -64,7 +100,13 @@ export function requireBitcoinNativeMerkleVerifier(): NativeMerkleVerifier {
  * instead of falling back to JavaScript.
  */
 export function isBitcoinNativeAvailable(): boolean {
-  return isBitcoinNativeMempoolStateAvailable() || isBitcoinNativeMerkleVerifierAvailable();
+  return (
+    isBitcoinNativeMempoolStateAvailable() ||
+    isBitcoinNativeMerkleVerifierAvailable() ||
+    isBitcoinNativeTransactionParserAvailable() ||
+    isBitcoinNativeBlockParserAvailable() ||
+    isBitcoinNativeMerkleProverAvailable()
+  );
 }
 
 export function isBitcoinNativeMempoolStateAvailable(): boolean {
@@ -74,3 +116,15 @@ export function isBitcoinNativeMempoolStateAvailable(): boolean {
 export function isBitcoinNativeMerkleVerifierAvailable(): boolean {
   return Boolean(bindings?.NativeMerkleVerifier);
 }
+
+export function isBitcoinNativeTransactionParserAvailable(): boolean {
+  return Boolean(bindings?.NativeTransactionParser);
+}
+
+export function isBitcoinNativeBlockParserAvailable(): boolean {
+  return Boolean(bindings?.NativeBlockParser);
+}
+
+export function isBitcoinNativeMerkleProverAvailable(): boolean {
+  return Boolean(bindings?.NativeMerkleProver);
+}
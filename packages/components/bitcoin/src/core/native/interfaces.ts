@@ -7,6 +7,80 @@ export interface NativeMerkleVerifier {
   bitcoinVerifyWitnessCommitment(block: any): boolean;
 }
 
+export type BitcoinNetworkName = 'mainnet' | 'testnet' | 'regtest' | 'signet';
+
+export interface NativeTransactionParser {
+  /**
+   * Decodes a segwit or legacy serialization (bytes or hex). Outputs carry their
+   * script `type`, plus `address` when `network` is given; inputs have no `prevout`
+   * and there is no `feeRate`. Throws on malformed input.
+   */
+  bitcoinParseTransaction(raw: Buffer | string, network?: BitcoinNetworkName): LightTransaction & { wtxid: string };
+}
+
+export interface NativeBlockHeader {
+  hash: string;
+  version: number;
+  versionHex: string;
+  previousblockhash: string;
+  merkleroot: string;
+  time: number;
+  bits: string;
+  nonce: number;
+}
+
+export interface NativeParsedBlock extends NativeBlockHeader {
+  size: number;
+  strippedsize: number;
+  weight: number;
+  nTx: number;
+  /** Big-endian hex, in block order. */
+  txids: string[];
+  /** Big-endian hex, in block order; equal to the txid for transactions without witness data. */
+  wtxids: string[];
+  /** Commitment of the last coinbase output carrying one, or null. */
+  witnessCommitment: string | null;
+  merkleRootValid: boolean;
+  /** False when the commitment mismatches, or when there is none but some transaction carries witness data. */
+  witnessCommitmentValid: boolean;
+}
+
+export interface NativeBlockParser {
+  /** Decodes a serialized block in one native pass. Throws on malformed input. */
+  bitcoinParseBlock(raw: Buffer): NativeParsedBlock;
+  /** True when the block is well-formed and both its merkle root and witness commitment check out. */
+  bitcoinVerifyBlockBytes(raw: Buffer): boolean;
+}
+
+export interface NativeMerkleProof {
+  txid: string;
+  index: number;
+  /** Sibling hashes from the leaf level up, big-endian hex. */
+  branch: string[];
+  root: string;
+}
+
+export interface NativeParsedMerkleBlock extends NativeBlockHeader {
+  totalTransactions: number;
+  /** Transactions the partial merkle tree proves, in block order. */
+  matches: Array<{ txid: string; index: number }>;
+  merkleRootValid: boolean;
+}
+
+export interface NativeMerkleProver {
+  bitcoinGetMerkleProof(txidsBE: string[], index: number): NativeMerkleProof;
+  bitcoinVerifyMerkleProof(txidBE: string, branchBE: string[], index: number, rootBE: string): boolean;
+  /**
+   * Decodes a BIP37 `merkleblock` (bytes or `gettxoutproof` hex). Throws on malformed input,
+   * including partial merkle trees Bitcoin Core would reject.
+   */
+  bitcoinParseMerkleBlock(raw: Buffer | string): NativeParsedMerkleBlock;
+  /** True when the tree hashes to the header's merkle root and, with `txidBE`, proves that transaction. */
+  bitcoinVerifyMerkleBlock(raw: Buffer | string, txidBE?: string): boolean;
+  /** Serializes a `merkleblock` proving `txidsBE` from a serialized block, like `gettxoutproof`. */
+  bitcoinBuildMerkleBlock(block: Buffer, txidsBE: string[]): Buffer;
+}
+
 export interface MempoolLoadInfo {
   timestamp: number;
   feeRate: number;
@@ -19,6 +93,8 @@ export interface MempoolStateSnapshotV2 {
   providerTx: Array<[string, string[]]>;
   metadata: Array<[string, MempoolTxMetadata]>;
   transactions: Array<[string, LightTransaction]>;
+  /** Transactions recorded with `recordLoadedRaw`, as serialized hex; native store only. */
+  rawTransactions?: Array<[string, string]>;
   loadTracker: Array<[string, MempoolLoadInfo]>;
 }
 
@@ -28,6 +104,8 @@ export interface MempoolMemoryUsage {
     txids: number;
     metadata: number;
     transactions: number;
+    /** Loaded transactions held as serialized bytes (`recordLoadedRaw`); native store only. */
+    rawTransactions?: number;
     loaded: number;
     providers: number;
   };
@@ -37,8 +115,22 @@ export interface MempoolMemoryUsage {
     txStore: number;
     loadTracker: number;
     providerTx: number;
+    /** Native store only: index components tracked from real capacities. */
+    feeIndex?: number;
+    graph?: number;
+    spends?: number;
+    scripts?: number;
+    journal?: number;
+    pendingIndex?: number;
+    coverage?: number;
+    other?: number;
     total: number;
   };
+  /**
+   * Live/peak heap of the whole native addon, shared by every store in the process rather than
+   * attributed to this one; null unless built with `counting-allocator`.
+   */
+  processHeap?: { allocated: number; peak: number } | null;
 }
 
 export type MempoolProviderSnapshot = Record<string, Array<{ txid: string; metadata: MempoolTxMetadata }>>;
@@ -88,7 +180,389 @@ export interface MempoolStateStore {
   dispose(): void;
 }
 
-export interface NativeMempoolState extends MempoolStateStore {}
+export interface MempoolFeeRateEntry {
+  txid: string;
+  /** sat/vB */
+  feeRate: number;
+  vsize: number;
+}
+
+export interface MempoolFeeHistogramBucket {
+  minFeeRate: number;
+  /** null for the last, open-ended bucket */
+  maxFeeRate: number | null;
+  count: number;
+  vsize: number;
+}
+
+export interface MempoolProjectedBlock {
+  index: number;
+  txids: string[];
+  txCount: number;
+  totalFees: number;
+  weight: number;
+  vsize: number;
+  /** Effective package fee rates, sat/vB */
+  minFeeRate: number;
+  medianFeeRate: number;
+  maxFeeRate: number;
+}
+
+export type MempoolFeeEstimateMode = 'economical' | 'conservative';
+
+export interface MempoolFeeEstimate {
+  /** sat/vB */
+  feeRate: number;
+  targetBlocks: number;
+  mode: MempoolFeeEstimateMode;
+  /** Rate required by the projected block at `targetBlocks`. */
+  projected: number;
+  /** Rate derived from the confirmation history; null while history is too thin. */
+  historical: number | null;
+  samples: number;
+}
+
+export interface MempoolPackage {
+  txid: string;
+  /** In-mempool ancestors, nearest first. */
+  ancestors: string[];
+  /** In-mempool descendants, nearest first. */
+  descendants: string[];
+  /** Ancestors + tx + descendants, sats. */
+  fee: number;
+  vsize: number;
+  /** sat/vB over the whole package */
+  feeRate: number;
+  /** sat/vB over ancestors + tx (CPFP rate) */
+  ancestorFeeRate: number;
+}
+
+export interface MempoolConflict {
+  /** Spent outpoint, `txid:vout`. */
+  outpoint: string;
+  /** Newly loaded tx that spends the outpoint. */
+  txid: string;
+  /** Previous spender, evicted from the store. */
+  replacedTxid: string;
+}
+
+export interface MempoolWatchlist {
+  /** scriptPubKey hex */
+  scripts?: string[];
+  addresses?: string[];
+  /** `txid:vout` */
+  outpoints?: string[];
+  txids?: string[];
+}
+
+export type MempoolWatchHit =
+  /** `value` in sats */
+  | { kind: 'payment'; txid: string; vout: number; value: number }
+  | { kind: 'outpointSpent'; outpoint: string; txid: string }
+  | { kind: 'txSeen'; txid: string }
+  | { kind: 'txConfirmed'; txid: string }
+  | { kind: 'txDropped'; txid: string; reason: 'replaced' | 'conflicted' | 'descendant' | 'evicted' | 'expired' | 'dropped' };
+
+export interface MempoolMutationDiff {
+  added: string[];
+  removed: string[];
+  /** Fees in sats */
+  metadataChanged: Array<{ txid: string; oldFee: number; newFee: number }>;
+  loaded: string[];
+}
+
+export interface MempoolLimits {
+  maxEntries?: number | null;
+  maxVbytes?: number | null;
+  /** Budget for the store's per-entry byte estimate. */
+  maxBytes?: number | null;
+  /** Expiry after first-seen time. */
+  ttlMs?: number | null;
+}
+
+export interface MempoolEviction {
+  txid: string;
+  reason: 'maxEntries' | 'maxVbytes' | 'maxBytes' | 'expired';
+}
+
+export interface MempoolWatchReport {
+  watchHits: MempoolWatchHit[];
+  /** Present when the mutation triggered automatic compaction. */
+  compaction?: MempoolCompactionStats;
+  /** Present when the mutation was called with `reportDiff`. */
+  diff?: MempoolMutationDiff;
+  /** Present when the mutation exceeded the configured limits. */
+  evicted?: MempoolEviction[];
+}
+
+export interface MempoolConflictReport extends MempoolWatchReport {
+  replaced: string[];
+  evictedDescendants: string[];
+  conflicts: MempoolConflict[];
+}
+
+export type MempoolBlockInput =
+  | { txids: string[]; spentOutpoints?: string[] }
+  | { tx: Array<{ txid: string; vin?: Array<{ txid?: string; vout?: number }> }> };
+
+export interface MempoolBlockApplyResult extends MempoolWatchReport {
+  confirmed: string[];
+  conflicted: string[];
+  evictedDescendants: string[];
+}
+
+export interface MempoolScriptBalance {
+  /** sats paid to the script by mempool txs */
+  received: number;
+  /** sats spent from the script by mempool txs */
+  spent: number;
+  /** received - spent; may be negative */
+  balance: number;
+  txCount: number;
+}
+
+export interface MempoolCompactionStats {
+  handlesBefore: number;
+  handlesAfter: number;
+  tombstonesRemoved: number;
+  bytesBefore: number;
+  bytesAfter: number;
+  reclaimedBytes: number;
+}
+
+export interface MempoolDeltaEntry {
+  txid: string;
+  providers: string[];
+  metadata?: MempoolTxMetadata;
+  transaction?: LightTransaction;
+  /** Serialized hex, in place of `transaction` for `recordLoadedRaw` entries. */
+  rawTransaction?: string;
+  load?: MempoolLoadInfo;
+}
+
+export interface MempoolDelta {
+  fromSeq: number;
+  toSeq: number;
+  added: MempoolDeltaEntry[];
+  updated: MempoolDeltaEntry[];
+  removed: string[];
+}
+
+export interface MempoolLoadRetryPolicy {
+  /** Delay after the first failure; doubled on every further failure. */
+  baseDelayMs?: number;
+  maxDelayMs?: number;
+  /** Failures after which another provider listing the txid takes over. */
+  maxFailures?: number;
+}
+
+export interface MempoolLoadFailure {
+  txid: string;
+  attempts: number;
+  retryAt: number;
+  exhausted: boolean;
+}
+
+export interface MempoolLoadFailures {
+  reason: string | null;
+  providers: Array<{ provider: string; attempts: number; retryAt: number }>;
+}
+
+export interface MempoolProviderCoverage {
+  providers: string[];
+  /** Txids at least one provider reported. */
+  total: number;
+  seen: Record<string, number>;
+  /** Txids reported by that provider only. */
+  unique: Record<string, number>;
+  /** Txids reported by other providers but not by that one. */
+  missing: Record<string, number>;
+  /** `overlap[i][j]`: txids reported by both `providers[i]` and `providers[j]`. */
+  overlap: number[][];
+}
+
+export interface MempoolPropagationDelays {
+  providers: string[];
+  /** `delays[i][j]`: median `firstSeen(j) - firstSeen(i)` in ms, null when no common txids. */
+  delays: Array<Array<number | null>>;
+  /** Median ms behind the first provider that reported each txid. */
+  lag: Record<string, number | null>;
+}
+
+export interface MempoolMetadataPolicy {
+  /** `default`: first provider wins in `applySnapshot`, `mergeSnapshot` overwrites. */
+  policy?: 'default' | 'latest' | 'quorum' | 'highestFee';
+  /** Keep per-provider variants under `default`; other policies always keep them. */
+  retainVariants?: boolean;
+}
+
+export interface MempoolMetadataVariants {
+  txid: string;
+  policy: NonNullable<MempoolMetadataPolicy['policy']>;
+  /** Number of disagreeing reports; above 1 when providers disagree. */
+  distinct: number;
+  variants: Array<{ provider: string; reportedAt: number; metadata: MempoolTxMetadata }>;
+}
+
+export type MempoolPageKind = 'txIds' | 'metadata' | 'loadedTransactions';
+
+export interface MempoolPage<K extends MempoolPageKind> {
+  items: Array<K extends 'txIds' ? string : K extends 'metadata' ? MempoolTxMetadata : LightTransaction>;
+  /** Null once the iteration reached the end. */
+  nextCursor: string | null;
+}
+
+export type MempoolQuerySort = 'insertion' | 'feeRate' | 'vsize' | 'age' | 'ancestorCount' | 'txid';
+
+/** Every predicate is optional; ranges are inclusive. */
+export interface MempoolQuery {
+  minFeeRate?: number;
+  maxFeeRate?: number;
+  minVsize?: number;
+  maxVsize?: number;
+  minAncestorCount?: number;
+  maxAncestorCount?: number;
+  /** Age is measured from the provider's `time`, or from the load. */
+  minAgeMs?: number;
+  maxAgeMs?: number;
+  bip125Replaceable?: boolean;
+  loaded?: boolean;
+  provider?: string;
+  txidPrefix?: string;
+  sort?: MempoolQuerySort;
+  /** Defaults to 'desc' for 'feeRate' and 'asc' otherwise. */
+  order?: 'asc' | 'desc';
+  offset?: number;
+  /** Defaults to 100, at most 10000. */
+  limit?: number;
+  includeTransactions?: boolean;
+}
+
+export interface MempoolQueryResult {
+  /** Matches before `offset` and `limit`. */
+  total: number;
+  items: Array<{
+    txid: string;
+    feeRate: number;
+    vsize: number;
+    loaded: boolean;
+    metadata: MempoolTxMetadata | null;
+    transaction?: LightTransaction | null;
+  }>;
+}
+
+export type MempoolPendingOrder = 'insertion' | 'feeRate' | 'firstSeen' | 'watchedFirst';
+
+export interface NativeMempoolState extends MempoolStateStore {
+  /** With `reportDiff`, returns the diff against the previous mempool. */
+  applySnapshot(
+    perProvider: MempoolProviderSnapshot,
+    reportDiff?: boolean
+  ): { diff?: MempoolMutationDiff; evicted?: MempoolEviction[] };
+  /**
+   * Bounds the store; adding mutations then evict the lowest descendant
+   * fee-rate packages, and entries older than `ttlMs` expire.
+   */
+  setLimits(limits: MempoolLimits): void;
+  getLimits(): MempoolLimits & { usage: { entries: number; vbytes: number; bytes: number } };
+  /** Runs TTL expiry and size bounds now; `nowMs` overrides the clock. */
+  enforceLimits(nowMs?: number): MempoolWatchReport & { evicted: MempoolEviction[] };
+  /** Backs the txids off on `providerName`; `pendingTxids` skips them meanwhile. */
+  recordLoadFailed(txids: string[], providerName: string, reason?: string): MempoolLoadFailure[];
+  setLoadRetryPolicy(policy: MempoolLoadRetryPolicy): void;
+  getLoadFailures(txid: string): MempoolLoadFailures | null;
+  /** Order in which `pendingTxids` returns txids; defaults to `insertion`. */
+  setPendingOrder(order: MempoolPendingOrder): void;
+  getPendingOrder(): MempoolPendingOrder;
+  /** First time (ms) each provider reported `txid`. */
+  getProviderFirstSeen(txid: string): Record<string, number> | null;
+  getProviderCoverage(): MempoolProviderCoverage;
+  getUniqueTxids(providerName: string, limit?: number): string[];
+  getPropagationDelays(): MempoolPropagationDelays;
+  setMetadataPolicy(policy: MempoolMetadataPolicy): void;
+  getMetadataPolicy(): Required<MempoolMetadataPolicy>;
+  getMetadataVariants(txid: string): MempoolMetadataVariants | null;
+  /** Pages through a collection; cursors survive compaction but fail after a full replace. */
+  iterate<K extends MempoolPageKind>(kind: K, cursor: string | null, pageSize: number): MempoolPage<K>;
+  /** Sequence of the latest change; pass it to exportDelta() later. */
+  getChangeSeq(): number;
+  /**
+   * Txids added, updated and removed after `sinceSeq`, with payloads. Throws
+   * when `sinceSeq` predates the journal (after an import or clear(), or
+   * once old removals were trimmed): persist a full snapshot instead.
+   */
+  exportDelta(sinceSeq: number): MempoolDelta;
+  /**
+   * Applies another store's exportDelta() result, removals first. Each entry's
+   * `providers` replace the provider lists holding its txid.
+   */
+  applyDelta(delta: MempoolDelta): MempoolWatchReport;
+  /**
+   * Same state as exportSnapshot() in a compact binary format: raw 32-byte
+   * txids stored once, version header and SHA-256 checksum.
+   */
+  exportSnapshotBinary(): Buffer;
+  /** Throws on a bad header, unsupported version or checksum mismatch, leaving the store unchanged. */
+  importSnapshotBinary(buf: Buffer): void;
+  /** Renumbers handles and drops tombstones left by removals. */
+  compact(): MempoolCompactionStats;
+  /**
+   * Auto-compacts after removals once tombstones reach `tombstoneRatio` of all
+   * handles (default 0.5, min 1024 tombstones). Pass null to disable.
+   */
+  setAutoCompaction(tombstoneRatio?: number | null, minTombstones?: number): void;
+  getTransactionsByScript(scriptHex: string): string[];
+  getTransactionsByAddress(address: string): string[];
+  getScriptBalance(scriptHex: string): MempoolScriptBalance;
+  getAddressBalance(address: string): MempoolScriptBalance;
+  /**
+   * Removes block txs as confirmed, evicts mempool txs double-spending block
+   * inputs and their descendants, in one call.
+   */
+  applyBlock(block: MempoolBlockInput, reportDiff?: boolean): MempoolBlockApplyResult;
+  /**
+   * A loaded tx that double-spends an existing entry replaces it: the older
+   * spender and its descendants are evicted and reported.
+   */
+  recordLoaded(
+    loadedTransactions: Array<{
+      txid: string;
+      transaction: LightTransaction;
+      providerName?: string;
+    }>,
+    reportDiff?: boolean
+  ): MempoolConflictReport;
+  /**
+   * Stores only the serialized transaction; readers decode it on demand.
+   * Throws when the bytes are not exactly one transaction hashing to `txid`.
+   */
+  recordLoadedRaw(txid: string, rawBytes: Buffer, providerName?: string, reportDiff?: boolean): MempoolConflictReport;
+  /** Txid spending `outpoint` (`txid:vout`), or null. */
+  getOutpointSpender(outpoint: string): string | null;
+  /** Mempool txids spending any of `outpoints` (`txid:vout`); read-only. */
+  findConflicts(outpoints: string[]): string[];
+  /**
+   * With `evictDescendants`, the txids are treated as dropped or replaced, not
+   * confirmed: in-mempool descendants are removed too, no fee-estimator
+   * samples are recorded and watched txids are reported as `txDropped`.
+   */
+  removeTxids(txids: string[], evictDescendants?: boolean, reportDiff?: boolean): MempoolWatchReport;
+  mergeSnapshot(perProvider: MempoolProviderSnapshot, reportDiff?: boolean): MempoolWatchReport;
+  /** Registers watched entries; later mutations report matches as `watchHits`. */
+  watch(list: MempoolWatchlist): void;
+  unwatch(list: MempoolWatchlist): void;
+  clearWatchlist(): void;
+  getWatchlist(): Required<MempoolWatchlist>;
+  getAncestors(txid: string): string[];
+  getDescendants(txid: string): string[];
+  getPackage(txid: string): MempoolPackage | null;
+  topByFeeRate(n: number): MempoolFeeRateEntry[];
+  feeRateRange(minFeeRate: number, maxFeeRate: number, limit?: number): MempoolFeeRateEntry[];
+  query(filter: MempoolQuery): MempoolQueryResult;
+  feeHistogram(bucketBoundaries: number[]): MempoolFeeHistogramBucket[];
+  projectBlocks(n: number): MempoolProjectedBlock[];
+  estimateFeeRate(targetBlocks: number, mode?: MempoolFeeEstimateMode): MempoolFeeEstimate;
+}
 
 export interface NativeMempoolStateConstructor {
   new (): NativeMempoolState;
@@ -97,4 +571,7 @@ export interface NativeMempoolStateConstructor {
 export interface NativeBitcoinBindings {
   NativeMempoolState?: NativeMempoolStateConstructor;
   NativeMerkleVerifier?: NativeMerkleVerifier;
+  NativeTransactionParser?: NativeTransactionParser;
+  NativeBlockParser?: NativeBlockParser;
+  NativeMerkleProver?: NativeMerkleProver;
 }
@@ -12,6 +12,14 @@ describe('node native register', () => {
         bitcoinComputeMerkleRoot: () => '00'.repeat(32),
         bitcoinVerifyMerkleRoot: () => true,
         bitcoinVerifyWitnessCommitment: () => true,
+        bitcoinParseTransaction: () => ({}),
+        bitcoinParseBlock: () => ({}),
+        bitcoinVerifyBlockBytes: () => true,
+        bitcoinGetMerkleProof: () => ({}),
+        bitcoinVerifyMerkleProof: () => true,
+        bitcoinParseMerkleBlock: () => ({}),
+        bitcoinVerifyMerkleBlock: () => true,
+        bitcoinBuildMerkleBlock: () => Buffer.alloc(0),
       }),
     }));
 
@@ -20,6 +28,9 @@ describe('node native register', () => {
 
     expect(native.requireBitcoinNativeMempoolState()).toBe(FakeNativeMempoolState);
     expect(native.requireBitcoinNativeMerkleVerifier()).toBeDefined();
+    expect(native.requireBitcoinNativeTransactionParser()).toBeDefined();
+    expect(native.requireBitcoinNativeBlockParser()).toBeDefined();
+    expect(native.requireBitcoinNativeMerkleProver()).toBeDefined();
     expect(native.getBitcoinNativeLoadError()).toBeUndefined();
   });
 
@@ -35,11 +46,37 @@ describe('node native register', () => {
     require('../register');
 
     expect(native.getBitcoinNativeLoadError()?.message).toMatch(
-      /missing required export\(s\): bitcoinVerifyMerkleRoot, bitcoinVerifyWitnessCommitment/
+      /missing required export\(s\): bitcoinVerifyMerkleRoot, bitcoinVerifyWitnessCommitment$/
     );
     expect(() => native.requireBitcoinNativeMerkleVerifier()).toThrow(/bitcoinVerifyMerkleRoot/);
   });
 
+  it('registers an older addon without the optional parser and prover exports', () => {
+    class FakeNativeMempoolState {}
+
+    jest.doMock('../loader', () => ({
+      loadBitcoinNativeBindings: () => ({
+        NativeMempoolState: FakeNativeMempoolState,
+        bitcoinComputeMerkleRoot: () => '00'.repeat(32),
+        bitcoinVerifyMerkleRoot: () => true,
+        bitcoinVerifyWitnessCommitment: () => true,
+        bitcoinParseBlock: () => ({}),
+      }),
+    }));
+
+    const native = require('../../../core/native');
+    require('../register');
+
+    expect(native.getBitcoinNativeLoadError()).toBeUndefined();
+    expect(native.requireBitcoinNativeMempoolState()).toBe(FakeNativeMempoolState);
+    expect(native.requireBitcoinNativeMerkleVerifier()).toBeDefined();
+    expect(native.isBitcoinNativeTransactionParserAvailable()).toBe(false);
+    expect(native.isBitcoinNativeBlockParserAvailable()).toBe(false);
+    expect(() => native.requireBitcoinNativeTransactionParser()).toThrow(/NativeTransactionParser.*does not export it/);
+    expect(() => native.requireBitcoinNativeBlockParser()).toThrow(/NativeBlockParser/);
+    expect(() => native.requireBitcoinNativeMerkleProver()).toThrow(/NativeMerkleProver/);
+  });
+
   it('records a load error when the native addon is absent', () => {
     jest.doMock('../loader', () => ({
       loadBitcoinNativeBindings: () => undefined,
@@ -1,5 +1,12 @@
 import { setBitcoinNativeBindings, setBitcoinNativeLoadError } from '../../core/native';
-import type { NativeBitcoinBindings, NativeMerkleVerifier } from '../../core/native';
+import type {
+  BitcoinNetworkName,
+  NativeBitcoinBindings,
+  NativeBlockParser,
+  NativeMerkleProver,
+  NativeMerkleVerifier,
+  NativeTransactionParser,
+} from '../../core/native';
 import { loadBitcoinNativeBindings } from './loader';
 
 function missingRequiredExports(raw: any): string[] {
@@ -11,6 +18,15 @@ function missingRequiredExports(raw: any): string[] {
   return missing;
 }
 
+/**
+ * Parser and prover exports are optional: an addon built before they existed
+ * still registers the mempool and merkle verifier, and the component whose
+ * exports are incomplete stays unregistered, so its require* helper throws.
+ */
+function hasExports(raw: any, names: string[]): boolean {
+  return names.every((name) => typeof raw?.[name] === 'function');
+}
+
 try {
   const raw = loadBitcoinNativeBindings() as any;
   if (!raw) {
@@ -28,9 +44,44 @@ try {
     bitcoinVerifyWitnessCommitment: (block: any) => raw.bitcoinVerifyWitnessCommitment(block),
   };
 
+  const parser: NativeTransactionParser | undefined = hasExports(raw, ['bitcoinParseTransaction'])
+    ? {
+        bitcoinParseTransaction: (rawTx: Buffer | string, network?: BitcoinNetworkName) =>
+          raw.bitcoinParseTransaction(rawTx, network),
+      }
+    : undefined;
+
+  const blockParser: NativeBlockParser | undefined = hasExports(raw, ['bitcoinParseBlock', 'bitcoinVerifyBlockBytes'])
+    ? {
+        bitcoinParseBlock: (block: Buffer) => raw.bitcoinParseBlock(block),
+        bitcoinVerifyBlockBytes: (block: Buffer) => raw.bitcoinVerifyBlockBytes(block),
+      }
+    : undefined;
+
+  const prover: NativeMerkleProver | undefined = hasExports(raw, [
+    'bitcoinGetMerkleProof',
+    'bitcoinVerifyMerkleProof',
+    'bitcoinParseMerkleBlock',
+    'bitcoinVerifyMerkleBlock',
+    'bitcoinBuildMerkleBlock',
+  ])
+    ? {
+        bitcoinGetMerkleProof: (txids: string[], index: number) => raw.bitcoinGetMerkleProof(txids, index),
+        bitcoinVerifyMerkleProof: (txid: string, branch: string[], index: number, root: string) =>
+          raw.bitcoinVerifyMerkleProof(txid, branch, index, root),
+        bitcoinParseMerkleBlock: (merkleBlock: Buffer | string) => raw.bitcoinParseMerkleBlock(merkleBlock),
+        bitcoinVerifyMerkleBlock: (merkleBlock: Buffer | string, txid?: string) =>
+          raw.bitcoinVerifyMerkleBlock(merkleBlock, txid),
+        bitcoinBuildMerkleBlock: (block: Buffer, txids: string[]) => raw.bitcoinBuildMerkleBlock(block, txids),
+      }
+    : undefined;
+
   const bindings: NativeBitcoinBindings = {
     NativeMempoolState: raw.NativeMempoolState,
     NativeMerkleVerifier: verifier,
+    NativeTransactionParser: parser,
+    NativeBlockParser: blockParser,
+    NativeMerkleProver: prover,
   };
 
   setBitcoinNativeBindings(bindings);
@@ -330,11 +330,11 @@ impl NativeBlocksQueue {
     self.hash_index.clear();
 
     let mut idx = self.head_index;
-    for i in 0..self.current_block_count {
+    for (i, slot) in new_blocks.iter_mut().enumerate().take(self.current_block_count) {
       if let Some(entry) = self.blocks[idx].take() {
         self.height_index.insert(entry.height, i);
         self.hash_index.insert(entry.hash.clone(), i);
-        new_blocks[i] = Some(entry);
+        *slot = Some(entry);
       }
       idx = (idx + 1) % old_len;
     }
@@ -1,8 +1,9 @@
-use napi::Result;
+use napi::{Error, Result};
 use napi_derive::napi;
 use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
 
+use crate::utils::memory::{heap_usage, map_bytes, value_bytes, vec_bytes};
 use crate::utils::{hash_to_hex, now_ms, parse_hash, HashKey};
 
 fn nonce_key(from: &str, nonce: u64) -> String {
@@ -31,6 +32,33 @@ fn effective_gas_price(metadata: &Value) -> u128 {
   parse(metadata.get("gasPrice"))
 }
 
+/// Upper bound on `iterate` page sizes.
+const MAX_PAGE_SIZE: usize = 10_000;
+
+/// Collections `iterate` pages through.
+#[derive(Clone, Copy)]
+enum PageKind {
+  Hashes,
+  Metadata,
+  LoadedEntries,
+}
+
+impl PageKind {
+  fn parse(name: &str) -> Option<Self> {
+    match name {
+      "hashes" => Some(Self::Hashes),
+      "metadata" => Some(Self::Metadata),
+      "loadedEntries" => Some(Self::LoadedEntries),
+      _ => None,
+    }
+  }
+}
+
+/// Handles an `iterate` page may visit, so sparse kinds still return promptly.
+fn scan_budget(page_size: usize) -> usize {
+  page_size.saturating_mul(4).max(1024)
+}
+
 fn convert_units(units: Option<String>) -> (&'static str, f64) {
   match units.as_deref().unwrap_or("MB") {
     "B" => ("B", 1.0),
@@ -48,10 +76,14 @@ struct EvmMempoolBackingStore {
   metadata: HashMap<u32, Value>,
   load_tracker: HashMap<u32, Value>,
   nonce_index: HashMap<String, u32>,
+  /// Bumped whenever handles are renumbered (`clear`), which invalidates
+  /// outstanding `iterate` cursors.
+  generation: u32,
 }
 
 impl EvmMempoolBackingStore {
   fn clear(&mut self) {
+    self.generation = self.generation.wrapping_add(1);
     self.hash_to_handle.clear();
     self.hashes.clear();
     self.provider_tx.clear();
@@ -147,6 +179,12 @@ pub struct NativeEvmMempoolState {
   store: EvmMempoolBackingStore,
 }
 
+impl Default for NativeEvmMempoolState {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 #[napi]
 impl NativeEvmMempoolState {
   #[napi(constructor)]
@@ -334,6 +372,61 @@ impl NativeEvmMempoolState {
     entries
   }
 
+  /// One page of `kind` (`hashes`, `metadata` or `loadedEntries`), shaped
+  /// like the matching bulk method: `{ items, nextCursor }`, with `nextCursor`
+  /// null once the end is reached. Pass null as `cursor` to start.
+  ///
+  /// Handles are never reused, so paging in handle order stays consistent
+  /// across mutations between calls: entries present for the whole iteration
+  /// are returned exactly once, removed ones are skipped and added ones come
+  /// last. `applySnapshot` and `importSnapshot` renumber handles and make
+  /// older cursors fail; the caller restarts.
+  ///
+  /// Each page visits at most `scan_budget(pageSize)` handles, so a page may
+  /// hold fewer than `pageSize` items while `nextCursor` is still set.
+  #[napi]
+  pub fn iterate(&self, kind: String, cursor: Option<String>, page_size: f64) -> Result<Value> {
+    let kind = PageKind::parse(&kind).ok_or_else(|| {
+      Error::from_reason("Unsupported iteration kind. Expected 'hashes', 'metadata' or 'loadedEntries'.")
+    })?;
+    let start = match cursor.as_deref() {
+      None => 0,
+      Some(cursor) => {
+        let (generation, handle) = cursor
+          .split_once('.')
+          .and_then(|(generation, handle)| Some((generation.parse::<u32>().ok()?, handle.parse::<u32>().ok()?)))
+          .ok_or_else(|| Error::from_reason("Invalid mempool cursor"))?;
+        if generation != self.store.generation {
+          return Err(Error::from_reason("Stale mempool cursor; restart the iteration"));
+        }
+        handle
+      }
+    };
+    let page_size = (page_size.max(1.0) as usize).min(MAX_PAGE_SIZE);
+    let end = self.store.hashes.len() as u32;
+    let stop = start.saturating_add(scan_budget(page_size) as u32).min(end);
+
+    let mut items = Vec::new();
+    let mut handle = start;
+    while handle < stop && items.len() < page_size {
+      if let Some(hash) = self.store.hash_of_handle(handle) {
+        let item = match kind {
+          PageKind::Hashes => Some(Value::String(hash)),
+          PageKind::Metadata => self.store.metadata.get(&handle).cloned(),
+          PageKind::LoadedEntries if self.store.load_tracker.contains_key(&handle) => {
+            self.store.metadata.get(&handle).map(|metadata| json!({ "hash": hash, "metadata": metadata }))
+          }
+          PageKind::LoadedEntries => None,
+        };
+        items.extend(item);
+      }
+      handle += 1;
+    }
+
+    let next_cursor = (handle < end).then(|| format!("{}.{}", self.store.generation, handle));
+    Ok(json!({ "items": items, "nextCursor": next_cursor }))
+  }
+
   #[napi(js_name = "hasTransaction")]
   pub fn has_transaction(&self, hash: String) -> bool {
     self.store.handle_of_hash(&hash).is_some()
@@ -384,14 +477,24 @@ impl NativeEvmMempoolState {
     self.remove_hashes(to_remove)
   }
 
+  /// Memory held by this store, per component, in `units` (default MB).
+  ///
+  /// `bytes` follows real container capacities plus the deep size of stored
+  /// JSON values and strings; `processHeap` is the live/peak heap of the
+  /// whole addon from the counting allocator, shared by every store rather
+  /// than attributed to this one, or null unless built with
+  /// `counting-allocator`.
   #[napi(js_name = "getMemoryUsage")]
   pub fn get_memory_usage(&self, units: Option<String>) -> Value {
     let (unit, div) = convert_units(units);
-    let hash_index = self.store.hash_to_handle.len() * (32 + 16);
-    let metadata = self.store.metadata.len() * 256;
-    let load_tracker = self.store.load_tracker.len() * 64;
-    let provider_tx = self.store.provider_tx.values().map(|v| v.len() * 4).sum::<usize>();
-    let nonce_index = self.store.nonce_index.len() * 56;
+    let store = &self.store;
+    let values = |map: &HashMap<u32, Value>| map_bytes(map) + map.values().map(value_bytes).sum::<usize>();
+    let hash_index = map_bytes(&store.hash_to_handle) + vec_bytes(&store.hashes);
+    let metadata = values(&store.metadata);
+    let load_tracker = values(&store.load_tracker);
+    let provider_tx = map_bytes(&store.provider_tx)
+      + store.provider_tx.iter().map(|(name, handles)| name.capacity() + vec_bytes(handles)).sum::<usize>();
+    let nonce_index = map_bytes(&store.nonce_index) + store.nonce_index.keys().map(String::capacity).sum::<usize>();
     let total = hash_index + metadata + load_tracker + provider_tx + nonce_index;
 
     json!({
@@ -410,7 +513,9 @@ impl NativeEvmMempoolState {
         "providerTx": provider_tx as f64 / div,
         "nonceIndex": nonce_index as f64 / div,
         "total": total as f64 / div
-      }
+      },
+      "processHeap": heap_usage()
+        .map(|(allocated, peak)| json!({ "allocated": allocated as f64 / div, "peak": peak as f64 / div }))
     })
   }
 
@@ -529,3 +634,86 @@ impl NativeEvmMempoolState {
     self.store.dispose();
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn native_evm_mempool_iterate_pages_stay_consistent_across_mutations() {
+    let hash = |i: u32| format!("0x{:064x}", i + 1);
+    let entries = |range: std::ops::Range<u32>| -> Vec<Value> {
+      range.map(|i| json!({ "hash": hash(i), "metadata": { "hash": hash(i), "gasPrice": "0x1" } })).collect()
+    };
+    let page = |store: &NativeEvmMempoolState, kind: &str, cursor: Option<String>, size: f64| {
+      let page = store.iterate(kind.into(), cursor, size).unwrap();
+      let items: Vec<Value> = page["items"].as_array().unwrap().clone();
+      (items, page["nextCursor"].as_str().map(str::to_string))
+    };
+    let mut store = NativeEvmMempoolState::new();
+    store.apply_snapshot(json!({ "providerA": entries(0..6) })).unwrap();
+    assert!(store.iterate("txIds".into(), None, 10.0).is_err());
+    assert!(store.iterate("hashes".into(), Some("x".into()), 10.0).is_err());
+
+    let (first, cursor) = page(&store, "hashes", None, 2.0);
+    assert_eq!(first, vec![json!(hash(0)), json!(hash(1))]);
+
+    // Removals behind and ahead of the cursor and an addition.
+    assert_eq!(store.remove_hashes(vec![hash(1), hash(3)]), 2);
+    store.add_transactions(json!({ "providerA": entries(6..7) }), 0).unwrap();
+
+    let mut rest = Vec::new();
+    let mut cursor = cursor;
+    while let Some(current) = cursor {
+      let (items, next) = page(&store, "hashes", Some(current), 2.0);
+      rest.extend(items);
+      cursor = next;
+    }
+    assert_eq!(rest, vec![json!(hash(2)), json!(hash(4)), json!(hash(5)), json!(hash(6))]);
+
+    let (metadata, _) = page(&store, "metadata", None, 10.0);
+    assert_eq!(metadata.len(), 5);
+    assert_eq!(metadata[0]["hash"], json!(hash(0)));
+    store.record_loaded(json!([{ "hash": hash(2), "metadata": { "gasPrice": "0x1" } }])).unwrap();
+    let (loaded, next) = page(&store, "loadedEntries", None, 10.0);
+    assert_eq!(loaded, vec![json!({ "hash": hash(2), "metadata": { "hash": hash(2), "gasPrice": "0x1" } })]);
+    assert!(next.is_none());
+
+    // `clear()` renumbers handles, so a full replace or dispose invalidates outstanding cursors.
+    let (_, cursor) = page(&store, "hashes", None, 1.0);
+    store.apply_snapshot(json!({ "providerA": entries(0..3) })).unwrap();
+    assert!(store.iterate("hashes".into(), cursor, 1.0).is_err());
+    let (_, cursor) = page(&store, "hashes", None, 1.0);
+    store.dispose();
+    assert!(store.iterate("hashes".into(), cursor, 1.0).is_err());
+  }
+
+  #[test]
+  fn native_evm_mempool_iterate_yields_a_removed_and_readded_hash_as_a_new_entry() {
+    let hash = |i: u32| format!("0x{:064x}", i + 1);
+    let entries = |indices: &[u32]| -> Vec<Value> {
+      indices.iter().map(|&i| json!({ "hash": hash(i), "metadata": { "hash": hash(i), "gasPrice": "0x1" } })).collect()
+    };
+    let mut store = NativeEvmMempoolState::new();
+    store.apply_snapshot(json!({ "providerA": entries(&[0, 1, 2, 3]) })).unwrap();
+
+    let page = store.iterate("hashes".into(), None, 2.0).unwrap();
+    assert_eq!(page["items"], json!([hash(0), hash(1)]));
+    let mut cursor = page["nextCursor"].as_str().map(str::to_string);
+
+    // One hash behind the cursor and one ahead of it leave and come back
+    // between pages. Their old slots stay tombstoned and the hashes get fresh
+    // handles at the end, so both show up once more as additions.
+    assert_eq!(store.remove_hashes(vec![hash(0), hash(2)]), 2);
+    store.add_transactions(json!({ "providerA": entries(&[2, 0]) }), 0).unwrap();
+    assert!(store.has_transaction(hash(0)) && store.has_transaction(hash(2)));
+
+    let mut rest = Vec::new();
+    while let Some(current) = cursor {
+      let page = store.iterate("hashes".into(), Some(current), 2.0).unwrap();
+      rest.extend(page["items"].as_array().unwrap().clone());
+      cursor = page["nextCursor"].as_str().map(str::to_string);
+    }
+    assert_eq!(rest, vec![json!(hash(3)), json!(hash(2)), json!(hash(0))]);
+  }
+}
@@ -0,0 +1,18 @@
+use serde_json::Value;
+use std::mem::size_of;
+
+pub use easylayer_native_common::memory::{heap_usage, map_bytes, vec_bytes};
+
+/// Heap bytes owned by a JSON value: string and array capacity, and object
+/// entries (a B-tree charged 1.5x per entry for half-full nodes), recursively.
+pub fn value_bytes(value: &Value) -> usize {
+  match value {
+    Value::String(string) => string.capacity(),
+    Value::Array(items) => vec_bytes(items) + items.iter().map(value_bytes).sum::<usize>(),
+    Value::Object(map) => map
+      .iter()
+      .map(|(key, item)| size_of::<(String, Value)>() * 3 / 2 + key.capacity() + value_bytes(item))
+      .sum(),
+    _ => 0,
+  }
+}
@@ -1,5 +1,6 @@
 pub mod hex;
 pub mod json;
+pub mod memory;
 pub mod time;
 
 pub use hex::{hash_to_hex, parse_hash, HashKey};
@@ -97,6 +97,11 @@ export interface EvmMempoolMemoryUsage {
     nonceIndex: number;
     total: number;
   };
+  /**
+   * Live/peak heap of the whole native addon, shared by every store in the process rather than
+   * attributed to this one; null unless built with `counting-allocator`.
+   */
+  processHeap?: { allocated: number; peak: number } | null;
 }
 
 /**
@@ -148,7 +153,20 @@ export interface EvmMempoolStateStore {
   dispose(): void;
 }
 
-export interface NativeEvmMempoolState extends EvmMempoolStateStore {}
+export type EvmMempoolPage<K extends 'hashes' | 'metadata' | 'loadedEntries'> = {
+  items: Array<K extends 'hashes' ? string : K extends 'metadata' ? MempoolTxMetadata : EvmLoadedMempoolTx>;
+  /** Null once the iteration reached the end. */
+  nextCursor: string | null;
+};
+
+export interface NativeEvmMempoolState extends EvmMempoolStateStore {
+  /** Pages through a collection; cursors fail once `applySnapshot`/`importSnapshot` replaced the store. */
+  iterate<K extends 'hashes' | 'metadata' | 'loadedEntries'>(
+    kind: K,
+    cursor: string | null,
+    pageSize: number
+  ): EvmMempoolPage<K>;
+}
 
 export interface NativeEvmMempoolStateConstructor {
   new (): NativeEvmMempoolState;
@@ -0,0 +1,65 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// System allocator that counts live and peak heap bytes of this addon.
+///
+/// Compiled and installed as the global allocator only with the
+/// `counting-allocator` feature, since every allocation then pays for
+/// atomic updates. The counts cover all Rust allocations of the addon, not a
+/// single store instance.
+pub struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn grew(bytes: usize) {
+  let now = ALLOCATED.fetch_add(bytes, Ordering::Relaxed) + bytes;
+  PEAK.fetch_max(now, Ordering::Relaxed);
+}
+
+fn shrank(bytes: usize) {
+  ALLOCATED.fetch_sub(bytes, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    let ptr = System.alloc(layout);
+    if !ptr.is_null() {
+      grew(layout.size());
+    }
+    ptr
+  }
+
+  unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+    let ptr = System.alloc_zeroed(layout);
+    if !ptr.is_null() {
+      grew(layout.size());
+    }
+    ptr
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    System.dealloc(ptr, layout);
+    shrank(layout.size());
+  }
+
+  unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    let new_ptr = System.realloc(ptr, layout, new_size);
+    if !new_ptr.is_null() {
+      if new_size > layout.size() {
+        grew(new_size - layout.size());
+      } else {
+        shrank(layout.size() - new_size);
+      }
+    }
+    new_ptr
+  }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// `(live, peak)` heap bytes allocated through the counting allocator.
+pub fn heap_usage() -> Option<(usize, usize)> {
+  Some((ALLOCATED.load(Ordering::Relaxed), PEAK.load(Ordering::Relaxed)))
+}
@@ -0,0 +1,5 @@
+//! Helpers shared by the Bitcoin and EVM native addons.
+
+#[cfg(feature = "counting-allocator")]
+mod alloc;
+pub mod memory;
@@ -0,0 +1,61 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::mem::size_of;
+
+/// SIMD group width of the std (hashbrown) hash table: control bytes are
+/// padded by one group past the last bucket.
+const HASH_GROUP_WIDTH: usize = 16;
+
+/// Bucket count behind a std hash table of usable `capacity`: tables keep a
+/// power-of-two bucket count, at most 7/8 full once past 8 buckets.
+fn hash_buckets(capacity: usize) -> usize {
+  match capacity {
+    0 => 0,
+    1..=7 => (capacity + 1).next_power_of_two(),
+    _ => (capacity * 8 / 7).next_power_of_two(),
+  }
+}
+
+fn table_bytes(capacity: usize, slot: usize) -> usize {
+  match hash_buckets(capacity) {
+    0 => 0,
+    buckets => buckets * (slot + 1) + HASH_GROUP_WIDTH,
+  }
+}
+
+/// Bytes allocated by the table of `map` (slots plus control bytes). Heap
+/// payloads owned by keys or values are not included.
+pub fn map_bytes<K, V>(map: &HashMap<K, V>) -> usize {
+  table_bytes(map.capacity(), size_of::<(K, V)>())
+}
+
+pub fn set_bytes<T>(set: &HashSet<T>) -> usize {
+  table_bytes(set.capacity(), size_of::<T>())
+}
+
+pub fn vec_bytes<T>(vec: &Vec<T>) -> usize {
+  vec.capacity() * size_of::<T>()
+}
+
+pub fn deque_bytes<T>(deque: &VecDeque<T>) -> usize {
+  deque.capacity() * size_of::<T>()
+}
+
+/// B-tree nodes hold up to 11 entries and are split half full, so entries are
+/// charged 1.5x their size; a node's own header is amortized into that.
+pub fn btree_map_bytes<K, V>(map: &BTreeMap<K, V>) -> usize {
+  map.len() * size_of::<(K, V)>() * 3 / 2
+}
+
+pub fn btree_set_bytes<T>(set: &BTreeSet<T>) -> usize {
+  set.len() * size_of::<T>() * 3 / 2
+}
+
+#[cfg(feature = "counting-allocator")]
+pub use super::alloc::heap_usage;
+
+/// `(live, peak)` heap bytes of the addon; `None` unless built with the
+/// `counting-allocator` feature.
+#[cfg(not(feature = "counting-allocator"))]
+pub fn heap_usage() -> Option<(usize, usize)> {
+  None
+}